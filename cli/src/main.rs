@@ -1,17 +1,30 @@
 use anyhow::{Context, Result};
 use clap::{Parser, Subcommand};
-use std::fs;
-use std::io::{self, Write};
-use std::path::PathBuf;
+use std::borrow::Cow;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, IsTerminal, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
 use tracing::instrument;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
-use mdman_service::{Config, FileWatcher, DiffReport};
+#[cfg(feature = "tui")]
+mod tui;
+mod priority;
+#[cfg(feature = "self-update")]
+mod update;
+
+use mdman_service::{backend, fsutil, Config, DiffReport, FileWatcher, LinkKind, SyncOptions, SyncStats, Timing, TimingPhase};
 
 #[derive(Parser)]
 #[command(name = "mdman")]
 #[command(about = "Markdown file manager for keeping files in sync", long_about = None)]
 struct Cli {
+    #[arg(long, global = true, value_name = "DIR", help = "Keep the config, hooks and sync state in DIR instead of the user's config directory")]
+    config: Option<PathBuf>,
+    #[arg(long, global = true, help = "Never write, create or delete any file; report what would have been done instead")]
+    read_only: bool,
     #[command(subcommand)]
     command: Commands,
 }
@@ -19,23 +32,89 @@ struct Cli {
 #[derive(Subcommand)]
 enum Commands {
     #[command(about = "Install mdman as a systemd service")]
-    Install,
+    Install {
+        #[arg(long, help = "Print the unit file and the commands that would run, without changing anything")]
+        dry_run: bool,
+    },
+    
+    #[command(about = "Create a starter config and optionally track a first file")]
+    Init {
+        #[arg(long, help = "Replace an existing config with an empty one")]
+        force: bool,
+    },
     
     #[command(about = "Copy a source file to destination and track it for synchronization")]
     Copy {
         #[arg(help = "Source markdown file path")]
         source: PathBuf,
-        #[arg(help = "Destination directory")]
+        #[arg(help = "Destination directory", required_unless_present = "template")]
+        destination: Option<PathBuf>,
+        #[arg(long, conflicts_with = "destination", help = "Destination template whose directory part is a glob, e.g. '~/projects/*/README.md'")]
+        template: Option<String>,
+        #[arg(long, help = "Display this mapping relative to the current directory")]
+        relative: bool,
+        #[arg(long, help = "Overwrite an existing destination whose content differs from the source")]
+        overwrite: bool,
+        #[arg(long, allow_negative_numbers = true, help = "Sync priority, lower numbers are synced first (default 0)")]
+        priority: Option<i32>,
+        #[arg(long = "desc", value_name = "TEXT", help = "Describe why this mapping exists")]
+        description: Option<String>,
+        #[arg(long, help = "Store paths without resolving symlinks, for filesystems where that fails (applies to all mappings)")]
+        no_canonicalize: bool,
+        #[arg(long, conflicts_with = "symlink", help = "Create the destination as a hardlink of the source, which must be on the same filesystem")]
+        hardlink: bool,
+        #[arg(long, help = "Create the destination as a symlink to the source")]
+        symlink: bool,
+        #[arg(long, conflicts_with_all = ["overwrite", "hardlink", "symlink", "template"], help = "Track an existing destination without rewriting it if it already matches the source, asking before overwriting it otherwise")]
+        adopt: bool,
+        #[arg(short, long, requires = "adopt", help = "Overwrite a differing destination without asking")]
+        yes: bool,
+    },
+    
+    #[command(about = "Set or clear the description of a tracked file")]
+    Describe {
+        #[arg(help = "Tracked source file")]
+        source: PathBuf,
+        #[arg(help = "Description text (clears the description if omitted)")]
+        description: Option<String>,
+    },
+    
+    #[command(about = "Generate one destination by concatenating several sources")]
+    Aggregate {
+        #[arg(help = "Destination file to generate")]
         destination: PathBuf,
+        #[arg(required = true, help = "Source files, in the order they are concatenated")]
+        sources: Vec<PathBuf>,
+        #[arg(long, help = "Text inserted between sources (default: a newline)")]
+        separator: Option<String>,
+    },
+    
+    #[command(about = "Find untracked markdown files in a directory and choose which to track")]
+    Discover {
+        #[arg(help = "Directory to search, honouring .gitignore")]
+        dir: PathBuf,
+        #[arg(help = "Directory the chosen files are copied to, keeping their relative paths")]
+        dest_root: PathBuf,
+        #[arg(long, help = "Track every file found without asking")]
+        all: bool,
     },
     
     #[command(about = "List all tracked files")]
-    List,
+    List {
+        #[arg(long, help = "Show paths relative to the current directory")]
+        relative: bool,
+        #[arg(long, help = "Print NUL-separated source and destination pairs for scripts, e.g. with xargs -0")]
+        print0: bool,
+    },
     
     #[command(about = "Stop tracking a file")]
     Untrack {
-        #[arg(help = "File path to stop tracking")]
-        file: PathBuf,
+        #[arg(help = "File path to stop tracking", required_unless_present_any = ["all", "glob"])]
+        file: Option<PathBuf>,
+        #[arg(long, conflicts_with_all = ["file", "glob"], help = "Stop tracking every file")]
+        all: bool,
+        #[arg(long, value_name = "GLOB", conflicts_with = "file", help = "Stop tracking every source matching this glob")]
+        glob: Option<String>,
     },
     
     #[command(about = "Remove source file and all its destination files")]
@@ -45,40 +124,395 @@ enum Commands {
     },
     
     #[command(about = "Run the file watcher service")]
-    Watch,
+    Watch {
+        #[arg(long, value_name = "MS", help = "Wait until no events arrive for this many milliseconds, then apply all changes at once")]
+        batch_window: Option<u64>,
+        #[arg(long, value_name = "CMD", help = "Shell command to run when a desync is detected ({source} and {dest} are substituted)")]
+        exec_on_desync: Option<String>,
+        #[arg(long, value_name = "PATH", help = "Keep a JSON snapshot of the watcher's state at this path for debugging")]
+        state_file: Option<PathBuf>,
+        #[arg(long, help = "Only watch the config file and log reloads, without syncing anything")]
+        watch_config_only: bool,
+        #[arg(long, value_name = "PATH", help = "Touch this file periodically while the watcher is running, for external monitoring")]
+        healthcheck_file: Option<PathBuf>,
+        #[arg(long, value_name = "SECS", default_value_t = 30, requires = "healthcheck_file", help = "Seconds between healthcheck file updates")]
+        healthcheck_interval: u64,
+        #[arg(long, help = "Remove destinations listed twice for the same source before starting")]
+        dedupe_destinations: bool,
+        #[arg(long, value_name = "DURATION", value_parser = humantime::parse_duration, help = "Stop watching after this long, e.g. '30s' or '5m'")]
+        exit_after: Option<Duration>,
+        #[arg(long, value_name = "N", help = "Stop watching after N events (syncs, desyncs, errors, ...)")]
+        exit_after_events: Option<usize>,
+        #[arg(long, help = "When a source is renamed, rename its destinations too instead of untracking it")]
+        follow_renames: bool,
+        #[arg(long, help = "Start monitoring destinations that are created after the watcher started")]
+        watch_new_destinations: bool,
+        #[arg(long, help = "Don't log how many destinations are out of sync when starting")]
+        quiet_startup: bool,
+        #[arg(long, value_name = "N", allow_negative_numbers = true, value_parser = clap::value_parser!(i32).range(-20..=19), help = "Run with this niceness, 19 yielding the most to other processes")]
+        nice: Option<i32>,
+        #[arg(long, value_enum, help = "IO scheduling class, 'idle' only doing IO when no other process needs the disk (Linux only)")]
+        io_class: Option<priority::IoClass>,
+        #[arg(long, help = "Log what each change would sync without writing any file")]
+        dry_run: bool,
+    },
     
     #[command(about = "Synchronize all tracked files from source to destination")]
-    Sync,
+    Sync {
+        #[arg(long, value_name = "N", help = "Number of sources to sync concurrently (1 syncs serially)")]
+        threads: Option<usize>,
+        #[arg(long, value_name = "PATH", help = "Treat this destination as the source of truth and copy it to its source and sibling destinations")]
+        from_destination: Option<PathBuf>,
+        #[arg(long, value_name = "N", help = "Abort the run once N errors occurred")]
+        max_errors: Option<usize>,
+        #[arg(long, value_name = "GLOB", help = "Only sync sources matching this glob")]
+        only: Option<String>,
+        #[arg(long, help = "Write the destinations of each source concurrently")]
+        parallel_destinations: bool,
+        #[arg(long, value_name = "PATH", help = "Write a JSON report of every destination's outcome to this file")]
+        report: Option<PathBuf>,
+        #[arg(long, conflicts_with = "from_destination", help = "Print one JSON object per destination as it is synced, instead of progress messages")]
+        json_lines: bool,
+        #[arg(long, conflicts_with_all = ["from_destination", "json_lines"], help = "Delete the destinations of sources that no longer exist, after confirmation")]
+        delete: bool,
+        #[arg(long, conflicts_with_all = ["from_destination", "json_lines"], help = "Print how long reading each source and writing each destination took")]
+        timings: bool,
+    },
+    
+    #[command(about = "Open a tracked file's source in $EDITOR and sync its destinations afterwards")]
+    Edit {
+        #[arg(help = "Source file, or a destination to edit the source of")]
+        file: PathBuf,
+    },
+    
+    #[command(about = "Turn automatic syncing of a destination on or off; it is checked for drift either way")]
+    AutoSync {
+        #[arg(help = "Tracked destination file")]
+        destination: PathBuf,
+        #[arg(value_parser = ["on", "off"], help = "Whether the destination is written when its source changes")]
+        state: String,
+    },
+    
+    #[command(about = "Temporarily write a destination somewhere else, e.g. while its drive is serviced")]
+    Redirect {
+        #[arg(help = "Tracked destination file")]
+        destination: PathBuf,
+        #[arg(required_unless_present = "clear", help = "Where to write it until the redirect is cleared")]
+        target: Option<PathBuf>,
+        #[arg(long, conflicts_with = "target", help = "Write the destination to its own path again")]
+        clear: bool,
+    },
+    
+    #[command(about = "Re-sync drifted destinations of one file from its source")]
+    Repair {
+        #[arg(help = "Source file (repairs all its destinations) or a single destination")]
+        file: PathBuf,
+    },
     
     #[command(about = "Show differences between source and destination files")]
     Diff {
-        #[arg(help = "Optional specific file to check (checks all if not specified)")]
+        #[arg(help = "Optional specific file, or directory to check everything under (checks all if not specified)")]
         file: Option<PathBuf>,
+        #[arg(long, value_name = "GLOB", help = "Only check sources matching this glob")]
+        only: Option<String>,
+        #[arg(long, help = "Exit with 1 if there are differences and 2 on errors, like git diff")]
+        exit_code: bool,
+        #[arg(short, long, help = "Print nothing; implies --exit-code")]
+        quiet: bool,
+        #[arg(long, conflicts_with = "quiet", help = "Show changed words inline, marked [-removed-]{+added+}")]
+        word_diff: bool,
+        #[arg(long, value_name = "PATH", requires = "file", conflicts_with = "only", help = "Compare FILE against this file instead of its destinations; neither has to be tracked")]
+        against: Option<PathBuf>,
+        #[arg(long, value_enum, value_name = "WHEN", default_value_t = ColorChoice::Auto, help = "Color the output: auto colors it on terminals unless NO_COLOR is set")]
+        color: ColorChoice,
+        #[arg(long, conflicts_with = "sources_only", help = "Only report missing or drifted destinations")]
+        destinations_only: bool,
+        #[arg(long, conflicts_with = "against", help = "Only report missing sources")]
+        sources_only: bool,
+    },
+    
+    #[command(about = "Read or change a config option")]
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+    
+    #[command(about = "Show whether a file is tracked, and which source owns it")]
+    Whose {
+        #[arg(help = "File to look up")]
+        file: PathBuf,
+    },
+    
+    #[command(about = "Check that all tracked files are in sync, exiting 1 otherwise or 2 if some can't be read")]
+    Check {
+        #[arg(short, long, help = "Print a line even when everything is in sync")]
+        verbose: bool,
+    },
+    
+    #[command(about = "Show totals of tracked files and how many are in sync")]
+    Stats {
+        #[arg(long, help = "Print the totals as JSON")]
+        json: bool,
+    },
+    
+    #[command(about = "Rewrite tracked paths in canonical form, merging duplicates")]
+    Normalize,
+    
+    #[command(about = "Remove leftover config backups and temp files created by mdman")]
+    Gc {
+        #[arg(long, value_name = "DURATION", value_parser = humantime::parse_duration, help = "Only remove files older than this, e.g. '7d' or '12h'")]
+        older_than: Option<Duration>,
+    },
+    
+    #[cfg(feature = "tui")]
+    #[command(about = "Open an interactive dashboard of tracked files")]
+    Tui,
+    
+    #[cfg(feature = "self-update")]
+    #[command(about = "Update mdman to the latest release and restart the service")]
+    Update {
+        #[arg(long, help = "Only report whether a newer release is available")]
+        check: bool,
+    },
+    
+    #[command(about = "Show the watcher service's log output")]
+    Log {
+        #[arg(short, long, help = "Keep printing new log entries as they arrive")]
+        follow: bool,
+        #[arg(short = 'n', long, default_value_t = 50, help = "Number of recent lines to show")]
+        lines: usize,
+    },
+}
+
+#[derive(Subcommand)]
+enum ConfigAction {
+    #[command(about = "Print the value of an option")]
+    Get {
+        #[arg(help = "Option name, e.g. detect_renames")]
+        key: String,
+    },
+    #[command(about = "Change an option, checking the value has the right type")]
+    Set {
+        #[arg(help = "Option name, e.g. detect_renames")]
+        key: String,
+        #[arg(help = "New value, e.g. true, 4 or poll; null restores the default")]
+        value: String,
     },
 }
 
+impl Commands {
+    /// Whether the command writes the config, so it can't run with a read-only config directory.
+    fn modifies_config(&self) -> bool {
+        match self {
+            Commands::Init { .. }
+            | Commands::Copy { .. }
+            | Commands::Describe { .. }
+            | Commands::AutoSync { .. }
+            | Commands::Redirect { .. }
+            | Commands::Aggregate { .. }
+            | Commands::Discover { .. }
+            | Commands::Untrack { .. }
+            | Commands::Remove { .. }
+            | Commands::Normalize
+            | Commands::Gc { .. } => true,
+            Commands::Watch { dedupe_destinations, .. } => *dedupe_destinations,
+            Commands::Sync { delete, .. } => *delete,
+            Commands::Config { action } => matches!(action, ConfigAction::Set { .. }),
+            _ => false,
+        }
+    }
+}
+
 fn main() -> Result<()> {
-    // Initialize tracing with journald
-    tracing_subscriber::registry()
-        .with(tracing_journald::layer().unwrap())
-        .init();
-    
     let cli = Cli::parse();
+    if cli.read_only {
+        fsutil::set_read_only();
+    }
+    
+    // Initialize tracing with journald, falling back to a log file where journald is unavailable
+    match tracing_journald::layer() {
+        Ok(layer) => tracing_subscriber::registry().with(layer).init(),
+        Err(_) => {
+            if !cli.read_only && let Ok(file) = open_log_file() {
+                tracing_subscriber::fmt()
+                    .with_writer(Mutex::new(file))
+                    .with_ansi(false)
+                    .init();
+            }
+        }
+    }
+    
+    if let Some(dir) = cli.config {
+        Config::set_config_dir(std::path::absolute(dir)?);
+    }
+    if cli.command.modifies_config() && !cli.read_only {
+        Config::ensure_writable()?;
+    }
     
     match cli.command {
-        Commands::Install => install_service(),
-        Commands::Copy { source, destination } => copy_and_track(source, destination),
-        Commands::List => list_tracked_files(),
-        Commands::Untrack { file } => untrack_file(file),
+        Commands::Install { dry_run } => install_service(dry_run || fsutil::read_only()),
+        Commands::Init { force } => init_config(force),
+        Commands::Copy {
+            source,
+            destination,
+            template,
+            relative,
+            overwrite,
+            priority,
+            description,
+            no_canonicalize,
+            hardlink,
+            symlink,
+            adopt,
+            yes,
+        } => {
+            if no_canonicalize {
+                let mut config = Config::load()?;
+                if !config.no_canonicalize {
+                    config.no_canonicalize = true;
+                    config.save()?;
+                    println!("Paths will now be stored without resolving symlinks");
+                }
+            }
+            let link = match (hardlink, symlink) {
+                (true, _) => Some(LinkKind::Hardlink),
+                (_, true) => Some(LinkKind::Symlink),
+                _ => None,
+            };
+            let options = CopyOptions { overwrite, link, adopt, yes };
+            match template {
+                Some(template) => copy_from_template(source.clone(), &template, &options)?,
+                None => copy_and_track(source.clone(), destination.context("Destination is required")?, &options)?,
+            }
+            if !relative && priority.is_none() && description.is_none() {
+                return Ok(());
+            }
+            Config::load()?.transaction(|config| {
+                if relative {
+                    config.set_display_base(&source, &std::env::current_dir()?)?;
+                }
+                if let Some(priority) = priority {
+                    config.set_priority(&source, priority)?;
+                }
+                if description.is_some() {
+                    config.set_description(&source, description)?;
+                }
+                Ok(())
+            })
+        }
+        Commands::Describe { source, description } => Config::load()?.set_description(&source, description),
+        Commands::Aggregate { destination, sources, separator } => aggregate_files(&destination, &sources, separator),
+        Commands::List { relative, print0: false } => list_tracked_files(relative),
+        Commands::List { relative, print0: true } => print_tracked_pairs(relative),
+        Commands::Discover { dir, dest_root, all } => discover_files(&dir, &dest_root, all),
+        Commands::Untrack { file: Some(file), .. } => untrack_file(file),
+        Commands::Untrack { file: None, all, glob } => untrack_matching(all, glob.as_deref()),
         Commands::Remove { file } => remove_file(file),
-        Commands::Watch => run_watcher(),
-        Commands::Sync => sync_all_files(),
-        Commands::Diff { file } => show_diff(file),
+        Commands::Watch {
+            batch_window,
+            exec_on_desync,
+            state_file,
+            watch_config_only,
+            healthcheck_file,
+            healthcheck_interval,
+            dedupe_destinations,
+            exit_after,
+            exit_after_events,
+            follow_renames,
+            watch_new_destinations,
+            quiet_startup,
+            nice,
+            io_class,
+            dry_run,
+        } => {
+            if let Some(nice) = nice {
+                priority::set_nice(nice)?;
+            }
+            if let Some(class) = io_class {
+                priority::set_io_class(class)?;
+            }
+            if dedupe_destinations {
+                dedupe_config_destinations()?;
+            }
+            if watch_config_only {
+                FileWatcher::new()?.run_config_only()
+            } else {
+                run_watcher(WatchOptions {
+                    batch_window,
+                    exec_on_desync,
+                    state_file,
+                    healthcheck_file,
+                    healthcheck_interval: Duration::from_secs(healthcheck_interval),
+                    exit_after,
+                    exit_after_events,
+                    follow_renames,
+                    watch_new_destinations,
+                    quiet_startup,
+                    dry_run,
+                })
+            }
+        }
+        Commands::Sync { threads, from_destination, max_errors, only, parallel_destinations, report, json_lines, delete, timings } => match from_destination {
+            Some(destination) => sync_from_destination(destination, report.as_deref()),
+            None => {
+                if delete {
+                    delete_orphaned_destinations(only.as_deref())?;
+                }
+                let options = SyncOptions { threads, max_errors, only, parallel_destinations, timings, ..Default::default() };
+                sync_all_files(options, report.as_deref(), json_lines)
+            }
+        },
+        Commands::Edit { file } => edit_file(file),
+        Commands::AutoSync { destination, state } => set_auto_sync(&destination, state == "on"),
+        Commands::Redirect { destination, target, .. } => redirect_destination(&destination, target.as_deref()),
+        Commands::Repair { file } => repair_file(file),
+        Commands::Diff { file, only, exit_code, quiet, word_diff, against, color, destinations_only, sources_only } => {
+            show_diff(DiffOptions {
+                file,
+                only,
+                exit_code: exit_code || quiet,
+                quiet,
+                word_diff,
+                against,
+                color: color.enabled(),
+                destinations_only,
+                sources_only,
+            })
+        }
+        Commands::Config { action: ConfigAction::Get { key } } => print_option(&key),
+        Commands::Config { action: ConfigAction::Set { key, value } } => Config::load()?.set_option(&key, &value),
+        Commands::Whose { file } => show_owner(&file),
+        Commands::Check { verbose } => check_sync(verbose),
+        Commands::Stats { json } => show_stats(json),
+        Commands::Normalize => normalize_config(),
+        Commands::Gc { older_than } => collect_garbage(older_than),
+        #[cfg(feature = "tui")]
+        Commands::Tui => tui::run(),
+        #[cfg(feature = "self-update")]
+        Commands::Update { check } => update::run(check),
+        Commands::Log { follow, lines } => show_log(follow, lines),
     }
 }
 
+struct CopyOptions {
+    overwrite: bool,
+    /// Create the destination as a link instead of copying the content.
+    link: Option<LinkKind>,
+    /// Re-track an existing destination, only rewriting it if it differs and the user agrees.
+    adopt: bool,
+    /// Don't ask before overwriting a differing destination when adopting.
+    yes: bool,
+}
+
 #[instrument(skip_all, fields(source = %source.display(), destination = %destination.display()))]
-fn copy_and_track(source: PathBuf, destination: PathBuf) -> Result<()> {
+fn copy_and_track(source: PathBuf, destination: PathBuf, options: &CopyOptions) -> Result<()> {
+    check_source_trackable(&source, options.adopt)?;
+    copy_to_destination(source, destination, options)
+}
+
+/// Fail unless `source` can be tracked. With `allow_tracked`, a source that is already tracked
+/// is accepted so further destinations can be added to it.
+fn check_source_trackable(source: &Path, allow_tracked: bool) -> Result<()> {
     if !source.exists() {
         anyhow::bail!("Source file {} does not exist", source.display());
     }
@@ -88,19 +522,25 @@ fn copy_and_track(source: PathBuf, destination: PathBuf) -> Result<()> {
     }
     
     let config = Config::load()?;
-    let canonical_source = source.canonicalize()?;
+    let canonical_source = config.resolve_path(source)?;
     
     // Check if source is already being tracked (either as source or destination)
-    if config.mappings.contains_key(&canonical_source) {
+    if config.mappings.contains_key(&canonical_source) && !allow_tracked {
         anyhow::bail!("{} is already being tracked as a source file", source.display());
     }
     
-    for (_, destinations) in config.mappings.iter() {
-        if destinations.iter().any(|d| d == &canonical_source) {
+    for mapping in config.mappings.values() {
+        if mapping.destinations.iter().any(|d| d == &canonical_source) {
             anyhow::bail!("{} is already being tracked as a destination file", source.display());
         }
     }
     
+    Ok(())
+}
+
+fn copy_to_destination(source: PathBuf, destination: PathBuf, options: &CopyOptions) -> Result<()> {
+    let config = Config::load()?;
+    
     let dest_path = if destination.is_dir() {
         let filename = source.file_name()
             .context("Invalid source filename")?;
@@ -110,25 +550,65 @@ fn copy_and_track(source: PathBuf, destination: PathBuf) -> Result<()> {
     };
     
     // Check if destination is already being tracked
-    let canonical_dest = dest_path.canonicalize().unwrap_or(dest_path.clone());
+    let canonical_dest = config.resolve_path(&dest_path).unwrap_or(dest_path.clone());
     
     if config.mappings.contains_key(&canonical_dest) {
         anyhow::bail!("{} is already being tracked as a source file", dest_path.display());
     }
     
-    for (_, destinations) in config.mappings.iter() {
-        if destinations.iter().any(|d| d == &canonical_dest) {
+    for (tracked_source, mapping) in &config.mappings {
+        if mapping.destinations.iter().any(|d| d == &canonical_dest) {
+            // Re-adopting a mapping that already exists is a no-op
+            if options.adopt && config.resolve_path(&source).is_ok_and(|source| &source == tracked_source) {
+                println!("{} is already tracked as a destination of {}", dest_path.display(), source.display());
+                return Ok(());
+            }
             anyhow::bail!("{} is already being tracked as a destination file", dest_path.display());
         }
     }
     
-    if let Some(parent) = dest_path.parent() {
-        fs::create_dir_all(parent)
-            .context("Failed to create destination directory")?;
+    // Opening a FIFO for writing would block until something reads it
+    fsutil::ensure_regular_file(&dest_path)?;
+    
+    // Copying onto a hardlink of the source would truncate the source itself
+    if fsutil::same_file(&source, &dest_path) && options.link.is_none() {
+        anyhow::bail!("{} is a hardlink of {}", dest_path.display(), source.display());
+    }
+    
+    if options.adopt && dest_path.is_file() {
+        return adopt_destination(source, destination, &dest_path, options.yes);
     }
     
-    fs::copy(&source, &dest_path)
-        .with_context(|| format!("Failed to copy {} to {}", source.display(), dest_path.display()))?;
+    if dest_path.is_file() && !options.overwrite {
+        let existing = fs::read(&dest_path)
+            .with_context(|| format!("Failed to read existing {}", dest_path.display()))?;
+        let content = fs::read(&source)
+            .with_context(|| format!("Failed to read {}", source.display()))?;
+        
+        if existing != content {
+            println!("{} already exists and differs from {}", dest_path.display(), source.display());
+            println!(
+                "Its current content ({} bytes, {} lines) would be lost",
+                existing.len(),
+                existing.iter().filter(|&&b| b == b'\n').count()
+            );
+            anyhow::bail!("Refusing to overwrite {}, pass --overwrite to replace it", dest_path.display());
+        }
+    }
+    
+    if let Some(kind) = options.link {
+        return link_to_destination(&source, &dest_path, kind);
+    }
+    
+    if fsutil::may_write("copy to", &dest_path) {
+        if let Some(parent) = dest_path.parent() {
+            fs::create_dir_all(parent)
+                .context("Failed to create destination directory")?;
+        }
+        
+        fs::copy(&source, &dest_path)
+            .with_context(|| format!("Failed to copy {} to {}", source.display(), dest_path.display()))?;
+    }
     
     let mut config = Config::load()?;
     config.add_mapping(source.clone(), destination)?;
@@ -139,12 +619,264 @@ fn copy_and_track(source: PathBuf, destination: PathBuf) -> Result<()> {
     Ok(())
 }
 
+/// Track the existing `dest_path` as a destination of `source`, leaving it untouched if it
+/// already matches and asking before overwriting it otherwise.
+fn adopt_destination(source: PathBuf, destination: PathBuf, dest_path: &Path, yes: bool) -> Result<()> {
+    let existing = fs::read(dest_path)
+        .with_context(|| format!("Failed to read existing {}", dest_path.display()))?;
+    let content = fs::read(&source)
+        .with_context(|| format!("Failed to read {}", source.display()))?;
+    
+    if existing == content {
+        Config::load()?.add_mapping(source.clone(), destination)?;
+        println!("Adopted {}, it already matches {}", dest_path.display(), source.display());
+        return Ok(());
+    }
+    
+    println!("{} differs from {}", dest_path.display(), source.display());
+    match mdman_service::markdown::change_stats(&existing, &content) {
+        Some(stats) => println!(
+            "Overwriting it would add {} lines and remove {} ({} words changed)",
+            stats.lines_added, stats.lines_removed, stats.words_changed
+        ),
+        None => println!("Its current content ({} bytes) would be lost", existing.len()),
+    }
+    
+    if !yes && !prompt("Overwrite it with the source? [y/N] ")?.eq_ignore_ascii_case("y") {
+        anyhow::bail!("Not adopting {}", dest_path.display());
+    }
+    
+    if fsutil::may_write("overwrite", dest_path) {
+        fs::copy(&source, dest_path)
+            .with_context(|| format!("Failed to copy {} to {}", source.display(), dest_path.display()))?;
+    }
+    Config::load()?.add_mapping(source.clone(), destination)?;
+    println!("Overwrote and adopted {}", dest_path.display());
+    
+    Ok(())
+}
+
+/// Replace `dest_path` with a link to `source` and track it.
+fn link_to_destination(source: &Path, dest_path: &Path, kind: LinkKind) -> Result<()> {
+    let mut config = Config::load()?;
+    let source = config.resolve_path(source)?;
+    
+    if let Some(parent) = dest_path.parent()
+        && fsutil::may_write("create", parent)
+    {
+        fs::create_dir_all(parent)
+            .context("Failed to create destination directory")?;
+    }
+    
+    let dest_dir = dest_path.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    if kind == LinkKind::Hardlink && !fsutil::read_only() && !fsutil::same_filesystem(&source, dest_dir) {
+        anyhow::bail!(
+            "{} is on a different filesystem than {}, so it can't be a hardlink. Use --symlink instead",
+            dest_path.display(),
+            source.display()
+        );
+    }
+    
+    kind.restore(&source, dest_path)
+        .with_context(|| format!("Failed to link {} to {}", dest_path.display(), source.display()))?;
+    config.add_link(&source, dest_path, kind)?;
+    
+    let kind = match kind {
+        LinkKind::Hardlink => "hardlink of",
+        LinkKind::Symlink => "symlink to",
+    };
+    println!("Created {} as a {} {}", dest_path.display(), kind, source.display());
+    println!("File is now being tracked for synchronization");
+    
+    Ok(())
+}
+
+fn copy_from_template(source: PathBuf, template: &str, options: &CopyOptions) -> Result<()> {
+    let destinations = mdman_service::expand_template(template)?;
+    
+    if destinations.is_empty() {
+        println!("Template {} did not match any existing directory", template);
+        return Ok(());
+    }
+    
+    check_source_trackable(&source, false)?;
+    
+    let mut failed = 0;
+    for destination in destinations {
+        if let Err(e) = copy_to_destination(source.clone(), destination.clone(), options) {
+            eprintln!("Skipping {}: {}", destination.display(), e);
+            failed += 1;
+        }
+    }
+    
+    if failed > 0 {
+        anyhow::bail!("{} destination(s) could not be tracked", failed);
+    }
+    
+    Ok(())
+}
+
+fn discover_files(dir: &Path, dest_root: &Path, all: bool) -> Result<()> {
+    let candidates = mdman_service::discover(&Config::load()?, dir, dest_root)?;
+    if candidates.is_empty() {
+        println!("No untracked markdown files found in {}", dir.display());
+        return Ok(());
+    }
+    
+    println!("Untracked markdown files in {}:", dir.display());
+    for (index, candidate) in candidates.iter().enumerate() {
+        println!("  {:>3}. {} → {}", index + 1, candidate.source.display(), candidate.destination.display());
+    }
+    
+    let chosen = if all {
+        (0..candidates.len()).collect()
+    } else {
+        let answer = prompt("\nTrack which files? (e.g. '1 3-5' or 'all', empty for none) ")?;
+        parse_selection(&answer, candidates.len())?
+    };
+    if chosen.is_empty() {
+        println!("Nothing tracked");
+        return Ok(());
+    }
+    
+    let options = CopyOptions { overwrite: false, link: None, adopt: false, yes: false };
+    let mut failed = 0;
+    for index in &chosen {
+        let candidate = &candidates[*index];
+        if let Err(e) = copy_and_track(candidate.source.clone(), candidate.destination.clone(), &options) {
+            eprintln!("Skipping {}: {}", candidate.source.display(), e);
+            failed += 1;
+        }
+    }
+    
+    if failed > 0 {
+        anyhow::bail!("{} of {} files could not be tracked", failed, chosen.len());
+    }
+    Ok(())
+}
+
+/// Parse a selection such as `1 3-5,7` or `all` into zero-based indices below `count`.
+fn parse_selection(input: &str, count: usize) -> Result<Vec<usize>> {
+    if input.trim().eq_ignore_ascii_case("all") {
+        return Ok((0..count).collect());
+    }
+    
+    let mut chosen = std::collections::BTreeSet::new();
+    for part in input.split([' ', ',']).filter(|part| !part.is_empty()) {
+        let parse = |number: &str| -> Result<usize> {
+            match number.trim().parse::<usize>() {
+                Ok(number) if (1..=count).contains(&number) => Ok(number - 1),
+                _ => anyhow::bail!("{} is not a number from 1 to {}", number, count),
+            }
+        };
+        match part.split_once('-') {
+            Some((start, end)) => chosen.extend(parse(start)?..=parse(end)?),
+            None => {
+                chosen.insert(parse(part)?);
+            }
+        }
+    }
+    Ok(chosen.into_iter().collect())
+}
+
+fn init_config(force: bool) -> Result<()> {
+    let config_path = Config::config_file_path()?;
+    
+    if config_path.exists() && !force {
+        println!("A config already exists at {}", config_path.display());
+        println!("Use 'mdman init --force' to replace it with an empty one");
+        return Ok(());
+    }
+    
+    Config::default().save()?;
+    println!("Created config at {}", config_path.display());
+    
+    print!("\nTrack a first file now? [y/N] ");
+    io::stdout().flush()?;
+    
+    let mut response = String::new();
+    io::stdin().read_line(&mut response)?;
+    
+    if response.trim().to_lowercase() == "y" {
+        let source = prompt("Source markdown file: ")?;
+        let destination = prompt("Destination directory: ")?;
+        copy_and_track(PathBuf::from(source), PathBuf::from(destination), &CopyOptions { overwrite: false, link: None, adopt: false, yes: false })?;
+    }
+    
+    println!();
+    println!("Next steps:");
+    println!("  mdman copy <source.md> <destination-dir>   track a file");
+    println!("  mdman install                             run the watcher as a service");
+    println!("  mdman list                                see what's tracked");
+    
+    Ok(())
+}
+
+fn prompt(question: &str) -> Result<String> {
+    print!("{}", question);
+    io::stdout().flush()?;
+    
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    Ok(answer.trim().to_string())
+}
+
 #[instrument]
-fn list_tracked_files() -> Result<()> {
+fn aggregate_files(destination: &Path, sources: &[PathBuf], separator: Option<String>) -> Result<()> {
+    let mut config = Config::load()?;
+    let destination = config.add_aggregate(destination, sources, separator)?;
+    
+    let content = config.aggregates[&destination].render()?;
+    backend::resolve(&destination)
+        .and_then(|backend| backend::write_verified(&*backend, &destination, &content, config.durable_writes, config.verify_after_write))
+        .with_context(|| format!("Failed to write {}", destination.display()))?;
+    
+    println!("Generated {} from {} sources", destination.display(), sources.len());
+    Ok(())
+}
+
+/// `path` relative to `base` if one is given, otherwise unchanged.
+fn display_path(path: &Path, base: Option<&Path>) -> PathBuf {
+    match base {
+        Some(base) => mdman_service::config::relative_to(path, base),
+        None => path.to_path_buf(),
+    }
+}
+
+/// Write every tracked `source\0destination\0` pair, aggregates included, with nothing else.
+fn print_tracked_pairs(relative: bool) -> Result<()> {
+    let config = Config::load()?;
+    let base = if relative { Some(std::env::current_dir()?) } else { None };
+    let display = |path: &Path| display_path(path, base.as_deref());
+    
+    let mut pairs = Vec::new();
+    for (source, mapping) in &config.mappings {
+        for dest in &mapping.destinations {
+            pairs.push((display(source), display(dest)));
+        }
+    }
+    for (destination, aggregate) in &config.aggregates {
+        for source in &aggregate.sources {
+            pairs.push((display(source), display(destination)));
+        }
+    }
+    
+    let mut out = io::stdout().lock();
+    for (source, dest) in pairs {
+        out.write_all(source.as_os_str().as_encoded_bytes())?;
+        out.write_all(b"\0")?;
+        out.write_all(dest.as_os_str().as_encoded_bytes())?;
+        out.write_all(b"\0")?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
+fn list_tracked_files(relative: bool) -> Result<()> {
     let config = Config::load()?;
-    let mappings = config.list_mappings();
+    let base = if relative { Some(std::env::current_dir()?) } else { None };
     
-    if mappings.is_empty() {
+    if config.mappings.is_empty() && config.aggregates.is_empty() {
         println!("No files are currently being tracked");
         return Ok(());
     }
@@ -152,10 +884,32 @@ fn list_tracked_files() -> Result<()> {
     println!("Tracked files:");
     println!();
     
-    for (source, destinations) in mappings {
-        println!("Source: {}", source.display());
-        for dest in destinations {
-            println!("  → {}", dest.display());
+    for (source, mapping) in &config.mappings {
+        println!("Source: {}", mapping.display_path(source, base.as_deref()).display());
+        if let Some(description) = &mapping.description {
+            println!("  {}", description);
+        }
+        if mapping.priority != 0 {
+            println!("  Priority: {}", mapping.priority);
+        }
+        for dest in &mapping.destinations {
+            match mapping.redirects.get(dest) {
+                Some(target) => println!(
+                    "  → {} (redirected to {})",
+                    mapping.display_path(dest, base.as_deref()).display(),
+                    mapping.display_path(target, base.as_deref()).display()
+                ),
+                None => println!("  → {}", mapping.display_path(dest, base.as_deref()).display()),
+            }
+        }
+        println!();
+    }
+    
+    let display = |path: &Path| display_path(path, base.as_deref());
+    for (destination, aggregate) in &config.aggregates {
+        println!("Aggregate: {}", display(destination).display());
+        for source in &aggregate.sources {
+            println!("  ← {}", display(source).display());
         }
         println!();
     }
@@ -163,6 +917,36 @@ fn list_tracked_files() -> Result<()> {
     Ok(())
 }
 
+fn untrack_matching(all: bool, glob: Option<&str>) -> Result<()> {
+    let mut config = Config::load()?;
+    let matcher = glob.map(mdman_service::sync::source_matcher).transpose()?;
+    let matches = |source: &Path| all || matcher.as_ref().is_some_and(|matcher| matcher.is_match(source));
+    
+    let sources: Vec<&PathBuf> = config.mappings.keys().filter(|source| matches(source)).collect();
+    if sources.is_empty() {
+        println!("No tracked files match");
+        return Ok(());
+    }
+    
+    println!("This will stop tracking:");
+    for source in &sources {
+        println!("  {}", source.display());
+    }
+    print!("\nStop tracking {} source files and their destinations? [y/N] ", sources.len());
+    io::stdout().flush()?;
+    
+    let mut response = String::new();
+    io::stdin().read_line(&mut response)?;
+    
+    if response.trim().to_lowercase() == "y" {
+        let removed = config.remove_matching(|source, _| matches(source))?;
+        println!("Stopped tracking {} files", removed.len());
+    } else {
+        println!("Cancelled");
+    }
+    Ok(())
+}
+
 fn untrack_file(file: PathBuf) -> Result<()> {
     let config = Config::load()?;
     
@@ -181,9 +965,7 @@ fn untrack_file(file: PathBuf) -> Result<()> {
         io::stdin().read_line(&mut response)?;
         
         if response.trim().to_lowercase() == "y" {
-            let mut config = Config::load()?;
-            config.mappings.remove(&source_path);
-            config.save()?;
+            Config::load()?.remove_source(&source_path)?;
             println!("Stopped tracking {} and all its destinations", file.display());
         } else {
             println!("Cancelled");
@@ -191,13 +973,19 @@ fn untrack_file(file: PathBuf) -> Result<()> {
         return Ok(());
     }
     
+    // Check if it's an aggregated destination
+    if Config::load()?.remove_aggregate(&file)? {
+        println!("Stopped generating {}", file.display());
+        return Ok(());
+    }
+    
     // Check if it's a destination file
-    let canonical_file = file.canonicalize().unwrap_or_else(|_| file.clone());
-    for (source, destinations) in config.mappings.iter() {
-        let matches = destinations.iter().any(|d| {
+    let canonical_file = config.resolve_path(&file).unwrap_or_else(|_| file.clone());
+    for (source, mapping) in config.mappings.iter() {
+        let matches = mapping.destinations.iter().any(|d| {
             d == &canonical_file || 
-            d.canonicalize().unwrap_or_else(|_| d.clone()) == canonical_file ||
-            (file.exists() && d.canonicalize().ok() == file.canonicalize().ok())
+            config.resolve_path(d).unwrap_or_else(|_| d.clone()) == canonical_file ||
+            (file.exists() && config.resolve_path(d).ok() == config.resolve_path(&file).ok())
         });
         
         if matches {
@@ -227,6 +1015,12 @@ fn untrack_file(file: PathBuf) -> Result<()> {
 
 fn remove_file(file: PathBuf) -> Result<()> {
     let config = Config::load()?;
+    if config.safe_mode() {
+        anyhow::bail!(
+            "Deleting files is disabled in safe mode. Use `mdman untrack {}` to stop tracking it instead",
+            file.display()
+        );
+    }
     
     // Check if it's a source file
     if let Some((source_path, destinations)) = config.find_by_path(&file) {
@@ -251,7 +1045,7 @@ fn remove_file(file: PathBuf) -> Result<()> {
         
         if response.trim().to_lowercase() == "y" {
             // Delete source file
-            if source_path.exists() {
+            if source_path.exists() && fsutil::may_write("delete", &source_path) {
                 fs::remove_file(&source_path)
                     .with_context(|| format!("Failed to delete source file {}", source_path.display()))?;
                 println!("Deleted source: {}", source_path.display());
@@ -259,7 +1053,7 @@ fn remove_file(file: PathBuf) -> Result<()> {
             
             // Delete destination files
             for dest in &destinations {
-                if dest.exists() {
+                if dest.exists() && fsutil::may_write("delete", dest) {
                     fs::remove_file(dest)
                         .with_context(|| format!("Failed to delete destination file {}", dest.display()))?;
                     println!("Deleted destination: {}", dest.display());
@@ -267,9 +1061,7 @@ fn remove_file(file: PathBuf) -> Result<()> {
             }
             
             // Remove from config
-            let mut config = Config::load()?;
-            config.mappings.remove(&source_path);
-            config.save()?;
+            Config::load()?.remove_source(&source_path)?;
             
             println!("\nAll files deleted and tracking removed.");
         } else {
@@ -283,108 +1075,549 @@ fn remove_file(file: PathBuf) -> Result<()> {
     Ok(())
 }
 
-fn install_service() -> Result<()> {
-    let service_content = r#"[Unit]
+/// Everything `install` does, computed up front so it can be printed with
+/// `--dry-run` before anything is touched.
+struct InstallPlan {
+    unit_path: PathBuf,
+    unit_content: String,
+    /// Whether a unit file already exists and the service is being updated.
+    update: bool,
+    /// Whether the running binary is copied to the install path first.
+    copy_binary: bool,
+    /// Commands run in order after the unit file is written.
+    commands: Vec<PlannedCommand>,
+}
+
+struct PlannedCommand {
+    program: &'static str,
+    args: Vec<String>,
+    /// Abort the install if the command fails instead of carrying on.
+    required: bool,
+}
+
+impl PlannedCommand {
+    fn new(program: &'static str, args: &[&str]) -> Self {
+        Self {
+            program,
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+            required: false,
+        }
+    }
+    
+    fn required(mut self) -> Self {
+        self.required = true;
+        self
+    }
+    
+    fn run(&self) -> Result<()> {
+        let status = std::process::Command::new(self.program)
+            .args(&self.args)
+            .status()
+            .with_context(|| format!("Failed to run {}", self))?;
+        
+        if self.required && !status.success() {
+            anyhow::bail!("{} failed", self);
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for PlannedCommand {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.program)?;
+        for arg in &self.args {
+            write!(f, " {}", arg)?;
+        }
+        Ok(())
+    }
+}
+
+impl InstallPlan {
+    const INSTALL_PATH: &'static str = "/usr/local/bin/mdman";
+    
+    fn new(unit_path: PathBuf, exe_path: &Path) -> Self {
+        let unit_content = format!(r#"[Unit]
 Description=mdman - Markdown file synchronization manager
 After=graphical-session.target
 
 [Service]
 Type=simple
-ExecStart=/usr/local/bin/mdman watch
+ExecStart={} watch
 Restart=on-failure
 RestartSec=10
 Environment="DISPLAY=:0"
 
 [Install]
-WantedBy=default.target"#;
-    
-    let service_path = dirs::config_dir()
-        .context("Could not determine config directory")?
-        .join("systemd/user/mdman.service");
-    
-    let service_exists = service_path.exists();
+WantedBy=default.target"#, Self::INSTALL_PATH);
+
+        let update = unit_path.exists();
+        let copy_binary = exe_path != Path::new(Self::INSTALL_PATH);
+        let mut commands = Vec::new();
+        
+        if copy_binary {
+            commands.push(PlannedCommand::new("sudo", &["cp", &exe_path.to_string_lossy(), Self::INSTALL_PATH]).required());
+            commands.push(PlannedCommand::new("sudo", &["chmod", "+x", Self::INSTALL_PATH]));
+        }
+        
+        if update {
+            commands.push(PlannedCommand::new("systemctl", &["--user", "stop", "mdman.service"]));
+        }
+        commands.push(PlannedCommand::new("systemctl", &["--user", "daemon-reload"]));
+        commands.push(PlannedCommand::new("systemctl", &["--user", "enable", "mdman.service"]));
+        commands.push(PlannedCommand::new("systemctl", &["--user", "start", "mdman.service"]));
+        
+        Self {
+            unit_path,
+            unit_content,
+            update,
+            copy_binary,
+            commands,
+        }
+    }
     
-    if let Some(parent) = service_path.parent() {
-        fs::create_dir_all(parent)
-            .context("Failed to create systemd user directory")?;
+    fn print(&self) {
+        println!("Would write {}:", self.unit_path.display());
+        println!();
+        for line in self.unit_content.lines() {
+            if line.is_empty() {
+                println!();
+            } else {
+                println!("    {}", line);
+            }
+        }
+        println!();
+        println!("Would run:");
+        for command in &self.commands {
+            println!("    {}", command);
+        }
     }
     
-    fs::write(&service_path, service_content)
-        .context("Failed to write systemd service file")?;
+    fn execute(&self) -> Result<()> {
+        if let Some(parent) = self.unit_path.parent() {
+            fs::create_dir_all(parent)
+                .context("Failed to create systemd user directory")?;
+        }
+        
+        fs::write(&self.unit_path, &self.unit_content)
+            .context("Failed to write systemd service file")?;
+        
+        if self.copy_binary {
+            println!("Installing mdman to {} (requires sudo)...", Self::INSTALL_PATH);
+        }
+        if self.update {
+            println!("Updating existing mdman systemd service...");
+        } else {
+            println!("Installing mdman systemd service...");
+        }
+        
+        for command in &self.commands {
+            command.run()?;
+        }
+        
+        if self.update {
+            println!("mdman service updated and restarted successfully!");
+        } else {
+            println!("mdman service installed and started successfully!");
+        }
+        match Config::load() {
+            Ok(config) => println!("{}", config.summary()),
+            Err(e) => println!("Warning: the service will fail to start until the config is fixed: {:#}", e),
+        }
+        println!("Use 'systemctl --user status mdman' to check service status");
+        
+        Ok(())
+    }
+}
+
+fn install_service(dry_run: bool) -> Result<()> {
+    let unit_path = dirs::config_dir()
+        .context("Could not determine config directory")?
+        .join("systemd/user/mdman.service");
     
     let exe_path = std::env::current_exe()
         .context("Failed to get current executable path")?;
     
-    let install_path = PathBuf::from("/usr/local/bin/mdman");
+    let plan = InstallPlan::new(unit_path, &exe_path);
     
-    if exe_path != install_path {
-        println!("Installing mdman to /usr/local/bin/mdman (requires sudo)...");
-        
-        let status = std::process::Command::new("sudo")
-            .args(["cp", exe_path.to_str().unwrap(), "/usr/local/bin/mdman"])
-            .status()
-            .context("Failed to copy executable")?;
-        
-        if !status.success() {
-            anyhow::bail!("Failed to install mdman to /usr/local/bin/");
+    if dry_run {
+        plan.print();
+        return Ok(());
+    }
+    
+    plan.execute()
+}
+
+struct WatchOptions {
+    batch_window: Option<u64>,
+    exec_on_desync: Option<String>,
+    state_file: Option<PathBuf>,
+    healthcheck_file: Option<PathBuf>,
+    healthcheck_interval: Duration,
+    exit_after: Option<Duration>,
+    exit_after_events: Option<usize>,
+    follow_renames: bool,
+    watch_new_destinations: bool,
+    quiet_startup: bool,
+    dry_run: bool,
+}
+
+fn run_watcher(options: WatchOptions) -> Result<()> {
+    let mut watcher = FileWatcher::new()?;
+    if let Some(ms) = options.batch_window {
+        watcher = watcher.with_batch_window(Duration::from_millis(ms));
+    }
+    if let Some(command) = options.exec_on_desync {
+        watcher = watcher.with_on_desync(command);
+    }
+    if let Some(path) = options.state_file {
+        watcher = watcher.with_state_file(path);
+    }
+    if let Some(path) = options.healthcheck_file {
+        watcher = watcher.with_healthcheck_file(path, options.healthcheck_interval);
+    }
+    if let Some(duration) = options.exit_after {
+        watcher = watcher.with_exit_after(duration);
+    }
+    if let Some(count) = options.exit_after_events {
+        watcher = watcher.with_exit_after_events(count);
+    }
+    if options.follow_renames {
+        watcher = watcher.with_follow_renames();
+    }
+    if options.watch_new_destinations {
+        watcher = watcher.with_watch_new_destinations();
+    }
+    if options.quiet_startup {
+        watcher = watcher.with_quiet_startup();
+    }
+    if options.dry_run {
+        watcher = watcher.with_dry_run();
+    }
+    
+    let summary = watcher.run()?;
+    println!(
+        "Watched for {}: {} events, {} files {}synced, {} desyncs, {} errors",
+        humantime::format_duration(Duration::from_secs(summary.elapsed.as_secs())),
+        summary.events,
+        summary.synced,
+        if options.dry_run { "would be " } else { "" },
+        summary.desynced,
+        summary.errors
+    );
+    Ok(())
+}
+
+fn dedupe_config_destinations() -> Result<()> {
+    let removed = Config::load()?.dedupe_destinations()?;
+    for (source, destination) in &removed {
+        println!("Removed duplicate destination {} of {}", destination.display(), source.display());
+    }
+    if !removed.is_empty() {
+        println!("Removed {} duplicate destinations", removed.len());
+    }
+    Ok(())
+}
+
+fn sync_all_files(mut options: SyncOptions, report: Option<&Path>, json_lines: bool) -> Result<()> {
+    let config = Config::load()?;
+    if config.list_mappings().is_empty() && config.aggregates.is_empty() {
+        if !json_lines {
+            println!("No files are currently being tracked");
         }
-        
-        std::process::Command::new("sudo")
-            .args(["chmod", "+x", "/usr/local/bin/mdman"])
-            .status()
-            .context("Failed to make executable")?;
+        return Ok(());
     }
     
-    if service_exists {
-        println!("Updating existing mdman systemd service...");
-        
-        std::process::Command::new("systemctl")
-            .args(["--user", "stop", "mdman.service"])
-            .status()
-            .context("Failed to stop existing service")?;
+    let printer = json_lines.then(|| {
+        let (tx, rx) = std::sync::mpsc::channel::<mdman_service::DestinationReport>();
+        options.quiet = true;
+        options.outcomes = Some(tx);
+        std::thread::spawn(move || {
+            for outcome in rx {
+                match serde_json::to_string(&outcome) {
+                    Ok(line) => println!("{}", line),
+                    Err(e) => eprintln!("Failed to serialize outcome: {}", e),
+                }
+            }
+        })
+    });
+    
+    let started = std::time::Instant::now();
+    let stats = mdman_service::sync_all_files(&options);
+    let elapsed = started.elapsed();
+    // Closing the channel lets the printer finish the remaining lines
+    drop(options);
+    if let Some(printer) = printer {
+        let _ = printer.join();
+    }
+    let stats = stats?;
+    
+    if let Some(path) = report {
+        write_sync_report(path, stats.clone())?;
+    }
+    if !json_lines {
+        println!();
+        if stats.aborted {
+            println!("Synchronization aborted after {} errors: {} files synced", stats.error_count, stats.synced_count);
+        } else {
+            println!("Synchronization complete: {} files synced", stats.synced_count);
+            if stats.error_count > 0 {
+                println!("{} errors occurred", stats.error_count);
+            }
+        }
+        if !stats.timings.is_empty() {
+            print_timings(&stats.timings, elapsed);
+        }
+    }
+    
+    // Scripts and CI need to notice a partial sync without parsing the output
+    if stats.aborted || stats.error_count > 0 {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// Print reads and writes slowest first, then the totals of each phase.
+fn print_timings(timings: &[Timing], elapsed: Duration) {
+    let mut timings = timings.to_vec();
+    timings.sort_by_key(|timing| std::cmp::Reverse(timing.elapsed));
+    
+    let millis = |duration: Duration| duration.as_secs_f64() * 1000.0;
+    let total = |phase: TimingPhase| -> Duration {
+        timings.iter().filter(|timing| timing.phase == phase).map(|timing| timing.elapsed).sum()
+    };
+    
+    println!();
+    println!("Timings:");
+    for timing in &timings {
+        let phase = match timing.phase {
+            TimingPhase::Read => "read",
+            TimingPhase::Write => "write",
+        };
+        println!("  {:<5} {:>10.2} ms  {}", phase, millis(timing.elapsed), timing.path.display());
+    }
+    // Reads and writes of different sources overlap when syncing concurrently
+    println!(
+        "Total: reading {:.2} ms, writing {:.2} ms, {:.2} ms overall",
+        millis(total(TimingPhase::Read)),
+        millis(total(TimingPhase::Write)),
+        millis(elapsed)
+    );
+}
+
+/// Delete the destinations of sources that no longer exist and stop tracking those sources.
+fn delete_orphaned_destinations(only: Option<&str>) -> Result<()> {
+    let config = Config::load()?;
+    if config.safe_mode() {
+        anyhow::bail!("Deleting files is disabled in safe mode. Run `mdman sync` without --delete instead");
+    }
+    
+    let orphaned: Vec<(PathBuf, Vec<PathBuf>)> = mdman_service::check_diff(None, only)?
+        .into_iter()
+        .filter_map(|diff| match diff {
+            DiffReport::SourceMissing { source } => {
+                let destinations = config.mappings.get(&source)?.destinations.clone();
+                Some((source, destinations))
+            }
+            _ => None,
+        })
+        .collect();
+    if orphaned.is_empty() {
+        return Ok(());
+    }
+    
+    println!("These sources no longer exist, their destinations will be DELETED:");
+    for (source, destinations) in &orphaned {
+        println!("  {}", source.display());
+        for dest in destinations {
+            println!("    → {}", dest.display());
+        }
+    }
+    let count: usize = orphaned.iter().map(|(_, destinations)| destinations.len()).sum();
+    let answer = prompt(&format!("\nPERMANENTLY DELETE {} destination file(s)? [y/N] ", count))?;
+    if !answer.eq_ignore_ascii_case("y") {
+        println!("Keeping them, syncing the remaining files");
+        return Ok(());
+    }
+    
+    for (source, destinations) in &orphaned {
+        for dest in destinations {
+            if mdman_service::backend::is_remote(dest) {
+                println!("Skipping {}, only local destinations can be deleted", dest.display());
+            } else if dest.exists() && fsutil::may_write("delete", dest) {
+                fs::remove_file(dest)
+                    .with_context(|| format!("Failed to delete destination file {}", dest.display()))?;
+                println!("Deleted {} (source {} no longer exists)", dest.display(), source.display());
+            }
+        }
+        Config::load()?.remove_source(source)?;
+    }
+    
+    Ok(())
+}
+
+fn set_auto_sync(destination: &Path, enabled: bool) -> Result<()> {
+    Config::load()?.set_auto_sync(destination, enabled)?;
+    if enabled {
+        println!("{} is synced automatically again", destination.display());
     } else {
-        println!("Installing mdman systemd service...");
+        println!("{} is no longer synced automatically, `mdman diff` still reports its drift", destination.display());
+    }
+    Ok(())
+}
+
+fn redirect_destination(destination: &Path, target: Option<&Path>) -> Result<()> {
+    let mut config = Config::load()?;
+    match target {
+        Some(target) => {
+            config.redirect_destination(destination, target)?;
+            println!("{} is now written to {} until 'mdman redirect --clear'", destination.display(), target.display());
+        }
+        None => {
+            let target = config.clear_redirect(destination)?;
+            println!("{} is written to its own path again instead of {}", destination.display(), target.display());
+        }
     }
+    println!("Run 'mdman sync' to bring it up to date");
+    Ok(())
+}
+
+fn edit_file(file: PathBuf) -> Result<()> {
+    let config = Config::load()?;
     
-    std::process::Command::new("systemctl")
-        .args(["--user", "daemon-reload"])
-        .status()
-        .context("Failed to reload systemd")?;
+    let (source, destinations) = if let Some(found) = config.find_by_path(&file) {
+        found
+    } else if let Some((source, destinations)) = config.find_source_of(&file).and_then(|source| config.find_by_path(&source)) {
+        println!("{} is a destination, editing its source {} instead", file.display(), source.display());
+        (source, destinations)
+    } else {
+        anyhow::bail!("{} is not being tracked", file.display());
+    };
     
-    std::process::Command::new("systemctl")
-        .args(["--user", "enable", "mdman.service"])
-        .status()
-        .context("Failed to enable service")?;
+    let editor = std::env::var("EDITOR").context("Set $EDITOR to the editor to open files with")?;
+    let mut words = editor.split_whitespace();
+    let program = words.next().context("$EDITOR is empty")?;
     
-    std::process::Command::new("systemctl")
-        .args(["--user", "start", "mdman.service"])
+    let before = fs::read(&source).ok();
+    let status = std::process::Command::new(program)
+        .args(words)
+        .arg(&source)
         .status()
-        .context("Failed to start service")?;
+        .with_context(|| format!("Failed to run editor {}", program))?;
+    if !status.success() {
+        anyhow::bail!("Editor exited with {}, not syncing {}", status, source.display());
+    }
     
-    if service_exists {
-        println!("mdman service updated and restarted successfully!");
-    } else {
-        println!("mdman service installed and started successfully!");
+    if fs::read(&source).ok() == before {
+        println!("{} is unchanged", source.display());
+        return Ok(());
+    }
+    
+    // Same as the watcher would, so destinations maintained by hand stay untouched
+    let destinations: Vec<PathBuf> = match config.mappings.get(&source) {
+        Some(mapping) => destinations.into_iter().filter(|dest| mapping.auto_syncs(dest)).collect(),
+        None => destinations,
+    };
+    let stats = mdman_service::sync_destinations(&source, &destinations)?;
+    println!("Synced {} to {} destination(s)", source.display(), stats.synced_count);
+    if stats.error_count > 0 {
+        println!("{} errors occurred", stats.error_count);
     }
-    println!("Use 'systemctl --user status mdman' to check service status");
     
     Ok(())
 }
 
-fn run_watcher() -> Result<()> {
-    let mut watcher = FileWatcher::new()?;
-    watcher.run()?;
+fn repair_file(file: PathBuf) -> Result<()> {
+    let config = Config::load()?;
+    
+    // A source repairs all its destinations, a destination only itself
+    let (source, only_destination) = if let Some((source, _)) = config.find_by_path(&file) {
+        (source, None)
+    } else if let Some(source) = config.find_source_of(&file) {
+        let destination = config.resolve_path(&file).unwrap_or_else(|_| file.clone());
+        (source, Some(config.stored_path(&destination)))
+    } else {
+        println!("{} is not being tracked", file.display());
+        return Ok(());
+    };
+    
+    let mut drifted = Vec::new();
+    for diff in mdman_service::check_diff(Some(&source), None)? {
+        match diff {
+            DiffReport::SourceMissing { source } => {
+                anyhow::bail!("Source file {} does not exist, nothing to repair from", source.display());
+            }
+            DiffReport::DestinationMissing { destination, .. } | DiffReport::ContentDiffers { destination, .. } => {
+                if only_destination.as_ref().is_none_or(|only| *only == destination) {
+                    drifted.push(destination);
+                }
+            }
+            DiffReport::Unreadable { path, error, .. } => {
+                anyhow::bail!("Can't read {}: {}", path.display(), error);
+            }
+        }
+    }
+    
+    if drifted.is_empty() {
+        println!("{} is already in sync", file.display());
+        return Ok(());
+    }
+    
+    println!("Will overwrite from {}:", source.display());
+    for dest in &drifted {
+        println!("  → {}", dest.display());
+    }
+    
+    let answer = prompt(&format!("\nRepair {} file(s)? [y/N] ", drifted.len()))?;
+    if answer.to_lowercase() != "y" {
+        println!("Cancelled");
+        return Ok(());
+    }
+    
+    let stats = mdman_service::sync_destinations(&source, &drifted)?;
+    
+    println!();
+    println!("Repair complete: {} files synced", stats.synced_count);
+    if stats.error_count > 0 {
+        println!("{} errors occurred", stats.error_count);
+    }
+    
     Ok(())
 }
 
-fn sync_all_files() -> Result<()> {
+fn write_sync_report(path: &Path, mut stats: SyncStats) -> Result<()> {
+    stats.destinations.sort_by(|a, b| (&a.source, &a.destination).cmp(&(&b.source, &b.destination)));
+    let json = serde_json::to_string_pretty(&stats)?;
+    if !fsutil::may_write("write report to", path) {
+        return Ok(());
+    }
+    fs::write(path, json + "\n").with_context(|| format!("Failed to write report to {}", path.display()))
+}
+
+fn sync_from_destination(destination: PathBuf, report: Option<&Path>) -> Result<()> {
     let config = Config::load()?;
-    if config.list_mappings().is_empty() {
-        println!("No files are currently being tracked");
+    let Some(source) = config.find_source_of(&destination) else {
+        println!("{} is not a tracked destination", destination.display());
+        return Ok(());
+    };
+    
+    println!("{} is a destination of:", destination.display());
+    println!("  ← {}", source.display());
+    
+    print!("\nOverwrite the source and all other destinations with its content? [y/N] ");
+    io::stdout().flush()?;
+    
+    let mut response = String::new();
+    io::stdin().read_line(&mut response)?;
+    
+    if response.trim().to_lowercase() != "y" {
+        println!("Cancelled");
         return Ok(());
     }
     
-    let stats = mdman_service::sync_all_files()?;
+    let stats = mdman_service::sync_from_destination(&destination)?;
+    if let Some(path) = report {
+        write_sync_report(path, stats.clone())?;
+    }
     
     println!();
     println!("Synchronization complete: {} files synced", stats.synced_count);
@@ -395,39 +1628,460 @@ fn sync_all_files() -> Result<()> {
     Ok(())
 }
 
-fn show_diff(file: Option<PathBuf>) -> Result<()> {
-    let config = Config::load()?;
-    if config.list_mappings().is_empty() {
-        println!("No files are currently being tracked");
-        return Ok(());
+struct DiffOptions {
+    file: Option<PathBuf>,
+    only: Option<String>,
+    exit_code: bool,
+    quiet: bool,
+    word_diff: bool,
+    against: Option<PathBuf>,
+    color: bool,
+    /// Leave out missing sources.
+    destinations_only: bool,
+    /// Leave out missing and drifted destinations.
+    sources_only: bool,
+}
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum ColorChoice {
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    /// Whether output to stdout gets ANSI colors.
+    fn enabled(self) -> bool {
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => {
+                std::env::var_os("NO_COLOR").is_none_or(|value| value.is_empty()) && io::stdout().is_terminal()
+            }
+        }
     }
+}
+
+const RED: &str = "\x1b[31m";
+const YELLOW: &str = "\x1b[33m";
+
+/// `text` wrapped in the ANSI escape `code` if `color` is set.
+fn paint(text: &str, code: &str, color: bool) -> String {
+    if color {
+        format!("{}{}\x1b[0m", code, text)
+    } else {
+        text.to_string()
+    }
+}
+
+/// Print how `destination` differs from `source` word by word, if both are text.
+fn print_word_diff(config: &Config, source: &Path, destination: &Path, color: bool) -> Result<()> {
+    let source_content = fs::read(source)?;
+    let source_content = match config.mappings.get(source) {
+        Some(mapping) => mapping.destination_content(&source_content),
+        None => Cow::Borrowed(&source_content[..]),
+    };
+    let dest_content = fs::read(destination)?;
+    
+    match (std::str::from_utf8(&source_content), std::str::from_utf8(&dest_content)) {
+        (Ok(old), Ok(new)) => {
+            println!();
+            print!("{}", mdman_service::markdown::word_diff(old, new, color));
+            println!();
+        }
+        _ => println!("  Binary files, no word diff"),
+    }
+    Ok(())
+}
+
+fn show_diff(options: DiffOptions) -> Result<()> {
+    let has_diffs = match print_diffs(&options) {
+        Ok(has_diffs) => has_diffs,
+        Err(e) if options.exit_code => {
+            eprintln!("Error: {:#}", e);
+            std::process::exit(2);
+        }
+        Err(e) => return Err(e),
+    };
+    
+    if options.exit_code && has_diffs {
+        std::process::exit(1);
+    }
+    
+    Ok(())
+}
+
+/// Print the differences for `options`, returning whether there were any.
+fn print_diffs(options: &DiffOptions) -> Result<bool> {
+    let config = Config::load()?;
+    let diffs = match (&options.file, &options.against) {
+        (Some(file), Some(against)) => mdman_service::diff_against(file, against)?.into_iter().collect(),
+        _ if config.list_mappings().is_empty() => {
+            if !options.quiet {
+                println!("No files are currently being tracked");
+            }
+            return Ok(false);
+        }
+        _ => mdman_service::check_diff(options.file.as_deref(), options.only.as_deref())?,
+    };
+    let diffs: Vec<DiffReport> = diffs
+        .into_iter()
+        .filter(|diff| match diff.is_source_side() {
+            true => !options.destinations_only,
+            false => !options.sources_only,
+        })
+        .collect();
     
-    let diffs = mdman_service::check_diff(file.as_deref())?;
+    let unreadable = diffs.iter().filter(|diff| matches!(diff, DiffReport::Unreadable { .. })).count();
+    
+    if options.quiet {
+        if unreadable > 0 {
+            anyhow::bail!("{} file(s) could not be read", unreadable);
+        }
+        return Ok(!diffs.is_empty());
+    }
     
     if diffs.is_empty() {
-        if file.is_some() {
-            println!("No differences found for the specified file");
+        if let (Some(file), Some(against)) = (&options.file, &options.against) {
+            println!("{} and {} are the same", file.display(), against.display());
+        } else if options.destinations_only {
+            println!("No destinations are missing or out of sync");
+        } else if options.sources_only {
+            println!("No sources are missing");
+        } else if options.file.is_some() || options.only.is_some() {
+            println!("No differences found for the specified path");
         } else {
             println!("All tracked files are in sync");
         }
     } else {
-        for diff in diffs {
+        for diff in &diffs {
             match diff {
                 DiffReport::SourceMissing { source } => {
-                    println!("Source file {} does not exist", source.display());
+                    let message = format!("Source file {} does not exist", source.display());
+                    println!("{}", paint(&message, RED, options.color));
                 }
                 DiffReport::DestinationMissing { source, destination } => {
-                    println!("Destination {} does not exist (source: {})", destination.display(), source.display());
+                    let message = format!("Destination {} does not exist (source: {})", destination.display(), source.display());
+                    println!("{}", paint(&message, RED, options.color));
                 }
-                DiffReport::ContentDiffers { source, destination, source_size, dest_size } => {
-                    println!("Files differ:");
+                DiffReport::ContentDiffers { source, destination, source_size, dest_size, changes, similarity } => {
+                    println!("{}", paint("Files differ:", YELLOW, options.color));
                     println!("  Source: {}", source.display());
                     println!("  Dest:   {}", destination.display());
-                    println!("  Size difference: {} vs {} bytes", source_size, dest_size);
+                    match changes {
+                        Some(changes) => println!(
+                            "  Dest has +{} lines, -{} lines, ~{} words changed",
+                            changes.lines_added, changes.lines_removed, changes.words_changed
+                        ),
+                        None => println!("  Size difference: {} vs {} bytes", source_size, dest_size),
+                    }
+                    if let Some(similarity) = similarity {
+                        println!("  {:.0}% similar", similarity * 100.0);
+                    }
+                    if options.word_diff {
+                        print_word_diff(&config, source, destination, options.color)?;
+                    }
+                }
+                DiffReport::Unreadable { path, error, .. } => {
+                    let message = format!("Can't read {}: {}", path.display(), error);
+                    println!("{}", paint(&message, RED, options.color));
                 }
             }
         }
     }
     
+    if unreadable > 0 {
+        anyhow::bail!("{} file(s) could not be read", unreadable);
+    }
+    Ok(!diffs.is_empty())
+}
+
+fn print_option(key: &str) -> Result<()> {
+    match Config::load()?.get_option(key)? {
+        Some(serde_json::Value::String(value)) => println!("{}", value),
+        Some(value) => println!("{}", value),
+        None => println!("(default)"),
+    }
+    Ok(())
+}
+
+fn show_owner(file: &Path) -> Result<()> {
+    let config = Config::load()?;
+    let resolved = config.resolve_path(file).unwrap_or_else(|_| file.to_path_buf());
+    let resolved = config.stored_path(&resolved);
+    
+    if let Some((source, destinations)) = config.find_by_path(file) {
+        let diffs = mdman_service::check_diff(Some(&source), None)?;
+        if !source.exists() {
+            println!("{} is a tracked source, but it does not exist", source.display());
+        } else {
+            println!("{} is a tracked source with {} destination(s):", source.display(), destinations.len());
+        }
+        for dest in &destinations {
+            println!("  → {} ({}{})", dest.display(), sync_state(&diffs, dest), last_synced_note(dest));
+        }
+    } else if let Some(source) = config.find_source_of(file) {
+        let diffs = mdman_service::check_diff(Some(&source), None)?;
+        println!("{} is a destination of:", file.display());
+        println!("  ← {} ({}{})", source.display(), sync_state(&diffs, &resolved), last_synced_note(&resolved));
+    } else if let Some(aggregate) = config.aggregates.get(&resolved) {
+        println!("{} is generated from {} source(s):", file.display(), aggregate.sources.len());
+        for source in &aggregate.sources {
+            println!("  ← {}", source.display());
+        }
+    } else {
+        println!("{} is not tracked", file.display());
+    }
+    
+    Ok(())
+}
+
+/// ", synced 5m ago" if mdman recorded writing `destination`.
+fn last_synced_note(destination: &Path) -> String {
+    let Some(elapsed) = mdman_service::last_synced(destination).and_then(|when| when.elapsed().ok()) else {
+        return String::new();
+    };
+    // Seconds are precise enough and keep the output short
+    let elapsed = Duration::from_secs(elapsed.as_secs());
+    format!(", synced {} ago", humantime::format_duration(elapsed))
+}
+
+/// One-word sync state of `destination` according to `diffs`.
+fn sync_state(diffs: &[DiffReport], destination: &Path) -> &'static str {
+    for diff in diffs {
+        match diff {
+            DiffReport::SourceMissing { .. } => return "source missing",
+            DiffReport::DestinationMissing { destination: d, .. } if d == destination => return "missing",
+            DiffReport::ContentDiffers { destination: d, .. } if d == destination => return "differs",
+            DiffReport::Unreadable { source, path, .. } if path == destination || path == source => return "unreadable",
+            _ => {}
+        }
+    }
+    "in sync"
+}
+
+fn check_sync(verbose: bool) -> Result<()> {
+    let mut diffs = mdman_service::check_diff(None, None)?;
+    
+    if diffs.is_empty() {
+        if verbose {
+            println!("OK: all tracked files are in sync");
+        }
+        return Ok(());
+    }
+    
+    diffs.sort_by(|a, b| a.path().cmp(b.path()));
+    
+    for diff in &diffs {
+        match diff {
+            DiffReport::SourceMissing { source } => {
+                println!("missing source: {}", source.display());
+            }
+            DiffReport::DestinationMissing { destination, .. } => {
+                println!("missing: {}", destination.display());
+            }
+            DiffReport::ContentDiffers { destination, .. } => {
+                println!("differs: {}", destination.display());
+            }
+            DiffReport::Unreadable { path, error, .. } => {
+                println!("unreadable: {} ({})", path.display(), error);
+            }
+        }
+    }
+    
+    // Like `diff --exit-code`, files that couldn't be compared are worse than drift
+    let unreadable = diffs.iter().any(|diff| matches!(diff, DiffReport::Unreadable { .. }));
+    std::process::exit(if unreadable { 2 } else { 1 });
+}
+
+fn show_stats(json: bool) -> Result<()> {
+    let config = Config::load()?;
+    let stats = config.stats();
+    
+    let (mut missing_sources, mut missing, mut drifted, mut unchecked) = (0, 0, 0, 0);
+    for diff in mdman_service::check_diff(None, None)? {
+        match diff {
+            DiffReport::SourceMissing { source } => {
+                missing_sources += 1;
+                unchecked += config.mappings.get(&source).map_or(0, |m| m.destinations.len());
+            }
+            DiffReport::DestinationMissing { .. } => missing += 1,
+            DiffReport::ContentDiffers { .. } => drifted += 1,
+            DiffReport::Unreadable { source, path, .. } if source == path => {
+                unchecked += config.mappings.get(&source).map_or(0, |m| m.destinations.len());
+            }
+            DiffReport::Unreadable { .. } => unchecked += 1,
+        }
+    }
+    let in_sync = stats.destinations.saturating_sub(missing + drifted + unchecked);
+    
+    if json {
+        let value = serde_json::json!({
+            "sources": stats.sources,
+            "destinations": stats.destinations,
+            "aggregates": stats.aggregates,
+            "source_bytes": stats.source_bytes,
+            "in_sync": in_sync,
+            "drifted": drifted,
+            "missing": missing,
+            "missing_sources": missing_sources,
+            "largest_fan_out": stats.largest_fan_out.as_ref().map(|(source, count)| {
+                serde_json::json!({ "source": source, "destinations": count })
+            }),
+        });
+        println!("{}", serde_json::to_string_pretty(&value)?);
+        return Ok(());
+    }
+    
+    println!(
+        "{} sources ({}) → {} destinations, {} aggregates",
+        stats.sources,
+        format_size(stats.source_bytes),
+        stats.destinations,
+        stats.aggregates
+    );
+    println!("{} in sync, {} drifted, {} missing", in_sync, drifted, missing);
+    if missing_sources > 0 {
+        println!("{} sources missing, their {} destinations were not checked", missing_sources, unchecked);
+    }
+    if let Some((source, count)) = &stats.largest_fan_out {
+        println!("Largest fan-out: {} → {} destinations", source.display(), count);
+    }
+    
+    Ok(())
+}
+
+fn normalize_config() -> Result<()> {
+    let mut config = Config::load()?;
+    let report = config.canonicalize_all()?;
+    
+    for (before, after) in &report.changed {
+        println!("Normalized {} → {}", before.display(), after.display());
+    }
+    for source in &report.merged {
+        println!("Merged duplicate entries for {}", source.display());
+    }
+    for path in &report.unresolved {
+        println!("Could not canonicalize {}", path.display());
+    }
+    
+    if report.changed.is_empty() && report.merged.is_empty() {
+        println!("All tracked paths are already canonical");
+    }
+    
+    Ok(())
+}
+
+fn collect_garbage(older_than: Option<Duration>) -> Result<()> {
+    let report = mdman_service::collect_garbage(older_than)?;
+    
+    for path in &report.removed {
+        println!("Removed {}", path.display());
+    }
+    
+    if report.removed.is_empty() {
+        println!("Nothing to clean up");
+    } else {
+        println!("Freed {} in {} files", format_size(report.freed_bytes), report.removed.len());
+    }
+    
     Ok(())
 }
+
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+    
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+fn log_file_path() -> Result<PathBuf> {
+    let state_dir = dirs::state_dir()
+        .or_else(dirs::cache_dir)
+        .context("Could not determine state directory")?;
+    Ok(state_dir.join("mdman").join("mdman.log"))
+}
+
+fn open_log_file() -> Result<File> {
+    let path = log_file_path()?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    Ok(OpenOptions::new().create(true).append(true).open(path)?)
+}
+
+fn journald_available() -> bool {
+    Path::new("/run/systemd/journal/socket").exists()
+        && std::process::Command::new("journalctl")
+            .arg("--version")
+            .stdout(std::process::Stdio::null())
+            .status()
+            .is_ok_and(|status| status.success())
+}
+
+fn show_log(follow: bool, lines: usize) -> Result<()> {
+    if journald_available() {
+        let mut command = std::process::Command::new("journalctl");
+        command.args(["--user", "-u", "mdman.service", "-n", &lines.to_string()]);
+        if follow {
+            command.arg("-f");
+        }
+        
+        let status = command.status().context("Failed to run journalctl")?;
+        if !status.success() {
+            anyhow::bail!("journalctl exited with {}", status);
+        }
+        return Ok(());
+    }
+    
+    let path = log_file_path()?;
+    if !path.exists() {
+        println!("No log output found at {}", path.display());
+        return Ok(());
+    }
+    
+    let mut file = File::open(&path)
+        .with_context(|| format!("Failed to open log file {}", path.display()))?;
+    let mut content = String::new();
+    file.read_to_string(&mut content)?;
+    
+    let all_lines: Vec<&str> = content.lines().collect();
+    for line in &all_lines[all_lines.len().saturating_sub(lines)..] {
+        println!("{}", line);
+    }
+    
+    if !follow {
+        return Ok(());
+    }
+    
+    let mut position = file.stream_position()?;
+    loop {
+        std::thread::sleep(Duration::from_millis(500));
+        
+        let len = file.metadata()?.len();
+        if len < position {
+            // The log was truncated or rotated, start over from the beginning
+            position = 0;
+        }
+        if len == position {
+            continue;
+        }
+        
+        file.seek(SeekFrom::Start(position))?;
+        let mut appended = String::new();
+        file.read_to_string(&mut appended)?;
+        print!("{}", appended);
+        io::stdout().flush()?;
+        position = file.stream_position()?;
+    }
+}
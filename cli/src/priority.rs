@@ -0,0 +1,64 @@
+use anyhow::Result;
+#[cfg(unix)]
+use anyhow::Context;
+#[cfg(unix)]
+use std::io;
+#[cfg(unix)]
+use tracing::info;
+
+/// IO scheduling class of the watcher, see ioprio_set(2).
+#[derive(Clone, Copy, Debug, clap::ValueEnum)]
+pub enum IoClass {
+    /// Only do IO when no other process needs the disk.
+    Idle,
+    /// The kernel's default class, at its default level.
+    BestEffort,
+}
+
+/// Change the niceness of this process, where higher values yield more CPU time to others.
+#[cfg(unix)]
+pub fn set_nice(nice: i32) -> Result<()> {
+    // Who 0 means the calling process
+    if unsafe { libc::setpriority(libc::PRIO_PROCESS, 0, nice) } != 0 {
+        return Err(io::Error::last_os_error())
+            .with_context(|| format!("Failed to set niceness to {} (negative values require root)", nice));
+    }
+    
+    let applied = unsafe { libc::getpriority(libc::PRIO_PROCESS, 0) };
+    info!("Running with niceness {}", applied);
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn set_nice(_nice: i32) -> Result<()> {
+    anyhow::bail!("--nice is only supported on Unix")
+}
+
+/// Change the IO scheduling class of this process.
+#[cfg(target_os = "linux")]
+pub fn set_io_class(class: IoClass) -> Result<()> {
+    const IOPRIO_WHO_PROCESS: libc::c_int = 1;
+    const IOPRIO_CLASS_SHIFT: libc::c_int = 13;
+    const IOPRIO_CLASS_BE: libc::c_int = 2;
+    const IOPRIO_CLASS_IDLE: libc::c_int = 3;
+    // Level 4 is what best-effort processes get when nobody set one
+    const DEFAULT_BE_LEVEL: libc::c_int = 4;
+    
+    let ioprio = match class {
+        IoClass::Idle => IOPRIO_CLASS_IDLE << IOPRIO_CLASS_SHIFT,
+        IoClass::BestEffort => (IOPRIO_CLASS_BE << IOPRIO_CLASS_SHIFT) | DEFAULT_BE_LEVEL,
+    };
+    // libc has no wrapper for ioprio_set, so it is called directly
+    if unsafe { libc::syscall(libc::SYS_ioprio_set, IOPRIO_WHO_PROCESS, 0, ioprio) } != 0 {
+        return Err(io::Error::last_os_error())
+            .with_context(|| format!("Failed to set the IO scheduling class to {:?}", class));
+    }
+    
+    info!("Running with IO scheduling class {:?}", class);
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn set_io_class(_class: IoClass) -> Result<()> {
+    anyhow::bail!("--io-class is only supported on Linux")
+}
@@ -0,0 +1,400 @@
+use anyhow::Result;
+use mdman_service::{Config, DiffReport, SyncOptions};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Style, Stylize};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, List, ListItem, ListState, Paragraph};
+use ratatui::{DefaultTerminal, Frame};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// How often sync status is recomputed while the dashboard is idle.
+const REFRESH_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Run the interactive dashboard until the user quits.
+pub fn run() -> Result<()> {
+    let mut terminal = ratatui::init();
+    let result = App::new().and_then(|mut app| app.run(&mut terminal));
+    ratatui::restore();
+    result
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Status {
+    InSync,
+    Differs,
+    Missing,
+}
+
+struct App {
+    mappings: Vec<(PathBuf, Vec<PathBuf>)>,
+    diffs: Vec<DiffReport>,
+    /// Sources the running watcher reports as paused, if it has a control socket.
+    paused: HashSet<PathBuf>,
+    control_socket: Option<PathBuf>,
+    list: ListState,
+    show_details: bool,
+    /// Source waiting for the user to confirm untracking it.
+    confirm_untrack: Option<PathBuf>,
+    message: String,
+    last_refresh: Instant,
+}
+
+impl App {
+    fn new() -> Result<Self> {
+        let mut app = Self {
+            mappings: Vec::new(),
+            diffs: Vec::new(),
+            paused: HashSet::new(),
+            control_socket: None,
+            list: ListState::default(),
+            show_details: false,
+            confirm_untrack: None,
+            message: String::new(),
+            last_refresh: Instant::now(),
+        };
+        app.load()?;
+        if !app.mappings.is_empty() {
+            app.list.select(Some(0));
+        }
+        Ok(app)
+    }
+    
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> Result<()> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+            
+            if event::poll(REFRESH_INTERVAL.saturating_sub(self.last_refresh.elapsed()))? {
+                if let Event::Key(key) = event::read()?
+                    && key.kind == KeyEventKind::Press
+                    && !self.handle_key(key.code, terminal)?
+                {
+                    return Ok(());
+                }
+            } else {
+                self.refresh();
+            }
+        }
+    }
+    
+    /// Reload like [`App::load`], showing a failure in the status line instead of quitting,
+    /// e.g. when the config is read while another process is saving it.
+    fn refresh(&mut self) {
+        if let Err(e) = self.load() {
+            self.message = format!("Failed to refresh: {:#}", e);
+        }
+    }
+    
+    /// Reload the config and recompute the status of every mapping.
+    fn load(&mut self) -> Result<()> {
+        // Set first so a failing load is retried after the interval rather than immediately
+        self.last_refresh = Instant::now();
+        let config = Config::load()?;
+        self.mappings = config.list_mappings();
+        self.diffs = mdman_service::check_diff(None, None)?;
+        self.control_socket = config.control_socket.clone();
+        self.paused = self.query_paused();
+        
+        let selected = self.list.selected().map(|i| i.min(self.mappings.len().saturating_sub(1)));
+        self.list.select(if self.mappings.is_empty() { None } else { selected.or(Some(0)) });
+        Ok(())
+    }
+    
+    fn selected_source(&self) -> Option<&Path> {
+        self.list
+            .selected()
+            .and_then(|i| self.mappings.get(i))
+            .map(|(source, _)| source.as_path())
+    }
+    
+    /// Returns `false` once the user asked to quit.
+    fn handle_key(&mut self, key: KeyCode, terminal: &mut DefaultTerminal) -> Result<bool> {
+        if let Some(source) = self.confirm_untrack.take() {
+            if key == KeyCode::Char('y') {
+                self.message = match Config::load().and_then(|mut config| config.remove_source(&source)) {
+                    Ok(_) => format!("Stopped tracking {}", source.display()),
+                    Err(e) => format!("Failed to stop tracking {}: {:#}", source.display(), e),
+                };
+                self.refresh();
+            } else {
+                self.message = "Cancelled".to_string();
+            }
+            return Ok(true);
+        }
+        
+        match key {
+            KeyCode::Char('q') | KeyCode::Esc => return Ok(false),
+            KeyCode::Down | KeyCode::Char('j') => self.list.select_next(),
+            KeyCode::Up | KeyCode::Char('k') => self.list.select_previous(),
+            KeyCode::Enter | KeyCode::Char('d') => self.show_details = !self.show_details,
+            KeyCode::Char('r') => {
+                self.message = "Refreshed".to_string();
+                self.refresh();
+            }
+            KeyCode::Char('s') => {
+                let options = SyncOptions { quiet: true, ..Default::default() };
+                match mdman_service::sync_all_files(&options) {
+                    Ok(stats) => {
+                        // Errors are still reported on stderr, which scribbles over the screen
+                        if stats.error_count > 0 {
+                            terminal.clear()?;
+                        }
+                        self.message = format!("Synced {} files with {} errors", stats.synced_count, stats.error_count);
+                    }
+                    Err(e) => self.message = format!("Sync failed: {:#}", e),
+                }
+                self.refresh();
+            }
+            KeyCode::Char('p') => {
+                if let Some(source) = self.selected_source().map(Path::to_path_buf) {
+                    self.message = self.toggle_pause(&source);
+                    self.paused = self.query_paused();
+                }
+            }
+            KeyCode::Char('u') => {
+                if let Some(source) = self.selected_source().map(Path::to_path_buf) {
+                    self.message = format!("Stop tracking {}? [y/N]", source.display());
+                    self.confirm_untrack = Some(source);
+                }
+            }
+            _ => {}
+        }
+        Ok(true)
+    }
+    
+    fn status_of(&self, source: &Path) -> Status {
+        let mut status = Status::InSync;
+        for diff in &self.diffs {
+            match diff {
+                DiffReport::SourceMissing { source: s }
+                | DiffReport::DestinationMissing { source: s, .. }
+                | DiffReport::Unreadable { source: s, .. }
+                    if s == source =>
+                {
+                    return Status::Missing;
+                }
+                DiffReport::ContentDiffers { source: s, .. } if s == source => status = Status::Differs,
+                _ => {}
+            }
+        }
+        status
+    }
+    
+    fn draw(&mut self, frame: &mut Frame) {
+        let details_height = if self.show_details { 10 } else { 0 };
+        let [list_area, details_area, status_area] = Layout::vertical([
+            Constraint::Min(3),
+            Constraint::Length(details_height),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+        
+        let items: Vec<ListItem> = self
+            .mappings
+            .iter()
+            .map(|(source, destinations)| mapping_item(source, destinations.len(), self.status_of(source), self.paused.contains(source)))
+            .collect();
+        let list = List::new(items)
+            .block(Block::bordered().title(" mdman "))
+            .highlight_style(Style::new().reversed())
+            .highlight_symbol("> ");
+        frame.render_stateful_widget(list, list_area, &mut self.list);
+        
+        if self.show_details {
+            frame.render_widget(self.details(), details_area);
+        }
+        
+        let status = if self.message.is_empty() {
+            "j/k move  enter details  s sync  p pause  u untrack  r refresh  q quit".to_string()
+        } else {
+            self.message.clone()
+        };
+        frame.render_widget(Paragraph::new(status).dim(), status_area);
+    }
+    
+    /// Per-destination status of the selected mapping.
+    fn details(&self) -> Paragraph<'_> {
+        let Some((source, destinations)) = self.list.selected().and_then(|i| self.mappings.get(i)) else {
+            return Paragraph::new("No mapping selected").block(Block::bordered());
+        };
+        
+        let mut lines = Vec::new();
+        if !source.exists() {
+            lines.push(Line::from("Source file does not exist").red());
+        }
+        for dest in destinations {
+            let diff = self.diffs.iter().find(|diff| match diff {
+                DiffReport::DestinationMissing { destination, .. } | DiffReport::ContentDiffers { destination, .. } => {
+                    destination == dest
+                }
+                DiffReport::Unreadable { path, .. } => path == dest,
+                DiffReport::SourceMissing { .. } => false,
+            });
+            let line = match diff {
+                Some(DiffReport::DestinationMissing { .. }) => Line::from(vec![
+                    Span::raw(format!("→ {} ", dest.display())),
+                    Span::styled("missing", Style::new().fg(Color::Red)),
+                ]),
+                Some(DiffReport::Unreadable { error, .. }) => Line::from(vec![
+                    Span::raw(format!("→ {} ", dest.display())),
+                    Span::styled(format!("unreadable ({})", error), Style::new().fg(Color::Red)),
+                ]),
+                Some(DiffReport::ContentDiffers { source_size, dest_size, .. }) => Line::from(vec![
+                    Span::raw(format!("→ {} ", dest.display())),
+                    Span::styled(format!("differs ({} vs {} bytes)", source_size, dest_size), Style::new().fg(Color::Yellow)),
+                ]),
+                _ => Line::from(vec![
+                    Span::raw(format!("→ {} ", dest.display())),
+                    Span::styled("in sync", Style::new().fg(Color::Green)),
+                ]),
+            };
+            lines.push(line);
+        }
+        
+        Paragraph::new(lines).block(Block::bordered().title(format!(" {} ", source.display())))
+    }
+    
+    fn toggle_pause(&self, source: &Path) -> String {
+        let cmd = if self.paused.contains(source) { "resume" } else { "pause" };
+        let request = serde_json::json!({ "cmd": cmd, "file": source });
+        
+        match self.control_request(&request) {
+            Some(reply) if reply["ok"] == true => format!("{}d {}", capitalize(cmd), source.display()),
+            Some(reply) => format!("Watcher refused: {}", reply["error"]),
+            None => "Pausing needs a running watcher with control_socket configured".to_string(),
+        }
+    }
+    
+    fn query_paused(&self) -> HashSet<PathBuf> {
+        self.control_request(&serde_json::json!({ "cmd": "status" }))
+            .and_then(|reply| serde_json::from_value(reply["paused"].clone()).ok())
+            .unwrap_or_default()
+    }
+    
+    /// Send one request to the watcher's control socket, if it is configured and listening.
+    #[cfg(unix)]
+    fn control_request(&self, request: &serde_json::Value) -> Option<serde_json::Value> {
+        use std::io::{BufRead, BufReader, Write};
+        use std::os::unix::net::UnixStream;
+        
+        let mut stream = UnixStream::connect(self.control_socket.as_ref()?).ok()?;
+        stream.set_read_timeout(Some(Duration::from_secs(5))).ok()?;
+        writeln!(stream, "{}", request).ok()?;
+        
+        let mut reply = String::new();
+        BufReader::new(stream).read_line(&mut reply).ok()?;
+        serde_json::from_str(&reply).ok()
+    }
+    
+    #[cfg(not(unix))]
+    fn control_request(&self, _request: &serde_json::Value) -> Option<serde_json::Value> {
+        None
+    }
+}
+
+fn mapping_item(source: &Path, destinations: usize, status: Status, paused: bool) -> ListItem<'static> {
+    let (marker, color) = match status {
+        Status::InSync => ("✓", Color::Green),
+        Status::Differs => ("≠", Color::Yellow),
+        Status::Missing => ("!", Color::Red),
+    };
+    
+    let mut spans = vec![
+        Span::styled(format!("{} ", marker), Style::new().fg(color)),
+        Span::raw(source.display().to_string()),
+        Span::raw(format!("  ({} destinations)", destinations)).dim(),
+    ];
+    if paused {
+        spans.push(Span::raw("  paused").italic());
+    }
+    ListItem::new(Line::from(spans))
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::Terminal;
+    use ratatui::backend::TestBackend;
+    
+    fn app(mappings: Vec<(PathBuf, Vec<PathBuf>)>, diffs: Vec<DiffReport>) -> App {
+        let mut list = ListState::default();
+        list.select(Some(0));
+        App {
+            mappings,
+            diffs,
+            paused: HashSet::new(),
+            control_socket: None,
+            list,
+            show_details: false,
+            confirm_untrack: None,
+            message: String::new(),
+            last_refresh: Instant::now(),
+        }
+    }
+    
+    fn render(app: &mut App) -> Vec<String> {
+        let mut terminal = Terminal::new(TestBackend::new(60, 16)).unwrap();
+        terminal.draw(|frame| app.draw(frame)).unwrap();
+        let buffer = terminal.backend().buffer();
+        (0..buffer.area.height)
+            .map(|y| (0..buffer.area.width).map(|x| buffer[(x, y)].symbol()).collect())
+            .collect()
+    }
+    
+    #[test]
+    fn mapping_list_shows_status_and_destination_count() {
+        let mut app = app(
+            vec![
+                (PathBuf::from("/a.md"), vec![PathBuf::from("/x/a.md"), PathBuf::from("/y/a.md")]),
+                (PathBuf::from("/b.md"), vec![PathBuf::from("/x/b.md")]),
+                (PathBuf::from("/c.md"), vec![PathBuf::from("/x/c.md")]),
+            ],
+            vec![
+                DiffReport::ContentDiffers {
+                    source: PathBuf::from("/b.md"),
+                    destination: PathBuf::from("/x/b.md"),
+                    source_size: 1,
+                    dest_size: 2,
+                    changes: None,
+                    similarity: None,
+                },
+                DiffReport::SourceMissing { source: PathBuf::from("/c.md") },
+            ],
+        );
+        app.paused.insert(PathBuf::from("/b.md"));
+        
+        let lines = render(&mut app);
+        
+        assert!(lines[0].contains("mdman"));
+        assert!(lines[1].contains("> ✓ /a.md  (2 destinations)"), "{lines:#?}");
+        assert!(lines[2].contains("≠ /b.md  (1 destinations)  paused"), "{lines:#?}");
+        assert!(lines[3].contains("! /c.md  (1 destinations)"), "{lines:#?}");
+        assert!(lines[15].starts_with("j/k move"));
+    }
+    
+    #[test]
+    fn details_show_each_destination() {
+        let source = PathBuf::from("/a.md");
+        let mut app = app(
+            vec![(source.clone(), vec![PathBuf::from("/x/a.md"), PathBuf::from("/y/a.md")])],
+            vec![DiffReport::DestinationMissing { source, destination: PathBuf::from("/y/a.md") }],
+        );
+        app.show_details = true;
+        
+        let lines = render(&mut app).join("\n");
+        
+        assert!(lines.contains("Source file does not exist"), "{lines}");
+        assert!(lines.contains("→ /x/a.md in sync"), "{lines}");
+        assert!(lines.contains("→ /y/a.md missing"), "{lines}");
+    }
+}
@@ -0,0 +1,282 @@
+use anyhow::{Context, Result};
+use mdman_service::fsutil;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+use crate::{InstallPlan, PlannedCommand};
+
+const CURRENT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// The newest published release and the download URLs of its assets.
+struct Release {
+    version: String,
+    assets: Vec<(String, String)>,
+}
+
+impl Release {
+    fn asset_url(&self, name: &str) -> Result<&str> {
+        self.assets
+            .iter()
+            .find(|(asset, _)| asset == name)
+            .map(|(_, url)| url.as_str())
+            .with_context(|| format!("Release {} has no {} asset", self.version, name))
+    }
+}
+
+/// Replace the installed binary with the latest GitHub release and restart the service.
+pub fn run(check: bool) -> Result<()> {
+    let release = latest_release()?;
+    if !is_newer(&release.version, CURRENT_VERSION) {
+        println!("mdman {} is already the latest version", CURRENT_VERSION);
+        return Ok(());
+    }
+    
+    println!("mdman {} is available (installed: {})", release.version, CURRENT_VERSION);
+    if check {
+        return Ok(());
+    }
+    
+    // Released binaries are published as e.g. `mdman-x86_64-linux`, with a `.sha256` next to them
+    let asset = format!("mdman-{}-{}", std::env::consts::ARCH, std::env::consts::OS);
+    let binary = download(release.asset_url(&asset)?)?;
+    let checksum = download(release.asset_url(&format!("{}.sha256", asset))?)?;
+    let checksum = String::from_utf8_lossy(&checksum);
+    verify_checksum(&binary, &checksum)?;
+    
+    let target = installed_binary()?;
+    if !fsutil::may_write("replace", &target) {
+        return Ok(());
+    }
+    replace_binary(&target, &binary, &checksum)?;
+    println!("Updated {} to mdman {}", target.display(), release.version);
+    
+    let running = std::process::Command::new("systemctl")
+        .args(["--user", "is-active", "--quiet", "mdman.service"])
+        .status()
+        .is_ok_and(|status| status.success());
+    if running {
+        PlannedCommand::new("systemctl", &["--user", "restart", "mdman.service"]).required().run()?;
+        println!("Restarted the mdman service");
+    }
+    
+    Ok(())
+}
+
+fn latest_release() -> Result<Release> {
+    // e.g. https://github.com/owner/mdman
+    let repository = env!("CARGO_PKG_REPOSITORY");
+    let repo = repository
+        .trim_end_matches('/')
+        .strip_prefix("https://github.com/")
+        .context("mdman was not built from a GitHub repository, so there are no releases to update from")?;
+    
+    fetch_release(&format!("https://api.github.com/repos/{}/releases/latest", repo))
+}
+
+/// Read a release from `url`, which answers like GitHub's releases API.
+fn fetch_release(url: &str) -> Result<Release> {
+    let response: Value = serde_json::from_slice(&download(url)?)
+        .context("GitHub returned an invalid release")?;
+    
+    let tag = response["tag_name"].as_str().context("Latest release has no tag")?;
+    let assets = response["assets"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|asset| Some((asset["name"].as_str()?.to_string(), asset["browser_download_url"].as_str()?.to_string())))
+        .collect();
+    
+    Ok(Release {
+        version: tag.trim_start_matches('v').to_string(),
+        assets,
+    })
+}
+
+fn download(url: &str) -> Result<Vec<u8>> {
+    let response = ureq::get(url)
+        .set("User-Agent", concat!("mdman/", env!("CARGO_PKG_VERSION")))
+        .call()
+        .with_context(|| format!("Failed to download {}", url))?;
+    
+    let mut content = Vec::new();
+    response
+        .into_reader()
+        .read_to_end(&mut content)
+        .with_context(|| format!("Failed to download {}", url))?;
+    Ok(content)
+}
+
+/// Whether `candidate` is a later version than `current`, comparing dotted numbers.
+/// Pre-release suffixes such as `-rc1` are ignored.
+fn is_newer(candidate: &str, current: &str) -> bool {
+    let parse = |version: &str| -> Vec<u64> {
+        version
+            .split(['-', '+'])
+            .next()
+            .unwrap_or_default()
+            .split('.')
+            .map(|part| part.parse().unwrap_or(0))
+            .collect()
+    };
+    parse(candidate) > parse(current)
+}
+
+/// Check `binary` against a checksum file in `sha256sum` format.
+fn verify_checksum(binary: &[u8], checksum_file: &str) -> Result<()> {
+    let expected = checksum_file
+        .split_whitespace()
+        .next()
+        .context("Checksum file is empty")?
+        .to_ascii_lowercase();
+    let actual = format!("{:x}", Sha256::digest(binary));
+    
+    if actual != expected {
+        anyhow::bail!("Checksum mismatch: expected {}, downloaded file has {}", expected, actual);
+    }
+    Ok(())
+}
+
+/// The binary the service runs, or this one if mdman was never installed.
+fn installed_binary() -> Result<PathBuf> {
+    let installed = Path::new(InstallPlan::INSTALL_PATH);
+    if installed.exists() {
+        return Ok(installed.to_path_buf());
+    }
+    std::env::current_exe().context("Failed to get current executable path")
+}
+
+/// Put `binary` in place of `target`, which may be running, falling back to sudo if its
+/// directory isn't writable. `checksum_file` is checked again right before installing with sudo.
+fn replace_binary(target: &Path, binary: &[u8], checksum_file: &str) -> Result<()> {
+    // Renaming over the old file works while it runs, unlike writing into it
+    let staged = fsutil::temp_path(target);
+    match write_executable(&staged, binary) {
+        Ok(()) => {
+            return fs::rename(&staged, target)
+                .with_context(|| format!("Failed to replace {}", target.display()));
+        }
+        Err(e) if e.kind() == io::ErrorKind::PermissionDenied => {}
+        Err(e) => return Err(e).with_context(|| format!("Failed to write {}", staged.display())),
+    }
+    
+    // Staged in a fresh directory only this user can enter, so nobody else can swap the
+    // file before root installs it
+    let staging_dir = private_temp_dir()?;
+    let staged = staging_dir.join("mdman");
+    let result = write_executable(&staged, binary)
+        .with_context(|| format!("Failed to write {}", staged.display()))
+        .and_then(|_| verify_checksum(&fs::read(&staged)?, checksum_file))
+        .and_then(|_| {
+            println!("Installing to {} (requires sudo)...", target.display());
+            PlannedCommand::new("sudo", &["install", "-m", "755", &staged.to_string_lossy(), &target.to_string_lossy()])
+                .required()
+                .run()
+        });
+    let _ = fs::remove_dir_all(&staging_dir);
+    result
+}
+
+/// Create a new directory in the temp dir that only the current user can access.
+/// Fails rather than reusing a directory that already exists.
+fn private_temp_dir() -> Result<PathBuf> {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .subsec_nanos();
+    let dir = std::env::temp_dir().join(format!("mdman-update-{}-{}", std::process::id(), nanos));
+    
+    let mut builder = fs::DirBuilder::new();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::DirBuilderExt;
+        builder.mode(0o700);
+    }
+    builder.create(&dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    Ok(dir)
+}
+
+fn write_executable(path: &Path, content: &[u8]) -> io::Result<()> {
+    fs::write(path, content)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o755))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::net::TcpListener;
+    
+    /// Serve `body` once over HTTP on a local port, returning its URL.
+    fn serve(body: &'static str) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/releases/latest", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0; 1024];
+            while !request.ends_with(b"\r\n\r\n") {
+                let read = stream.read(&mut buf).unwrap();
+                if read == 0 {
+                    break;
+                }
+                request.extend_from_slice(&buf[..read]);
+            }
+            write!(stream, "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}", body.len(), body).unwrap();
+        });
+        url
+    }
+    
+    #[test]
+    fn release_is_read_from_the_endpoint() {
+        let url = serve(r#"{
+            "tag_name": "v1.2.0",
+            "assets": [
+                { "name": "mdman-x86_64-linux", "browser_download_url": "https://example.com/mdman" },
+                { "name": "mdman-x86_64-linux.sha256", "browser_download_url": "https://example.com/mdman.sha256" }
+            ]
+        }"#);
+        
+        let release = fetch_release(&url).unwrap();
+        
+        assert_eq!(release.version, "1.2.0");
+        assert_eq!(release.asset_url("mdman-x86_64-linux.sha256").unwrap(), "https://example.com/mdman.sha256");
+        assert!(release.asset_url("mdman-aarch64-macos").is_err());
+    }
+    
+    #[test]
+    fn release_without_a_tag_is_an_error() {
+        let url = serve(r#"{ "message": "Not Found" }"#);
+        
+        assert!(fetch_release(&url).is_err());
+    }
+    
+    #[test]
+    fn versions_compare_by_their_numbers() {
+        assert!(is_newer("0.10.0", "0.9.3"));
+        assert!(is_newer("1.0.0", "0.99.0"));
+        assert!(is_newer("1.0.1", "1.0"));
+        assert!(!is_newer("1.0.0", "1.0.0"));
+        assert!(!is_newer("0.9.0", "0.10.0"));
+        // Release candidates count as the version they lead up to
+        assert!(!is_newer("1.0.0-rc1", "1.0.0"));
+    }
+    
+    #[test]
+    fn checksum_must_match_the_binary() {
+        let binary = b"mdman";
+        let checksum = format!("{:x}", Sha256::digest(binary));
+        
+        assert!(verify_checksum(binary, &format!("{}  mdman-x86_64-linux\n", checksum)).is_ok());
+        assert!(verify_checksum(binary, &checksum.to_ascii_uppercase()).is_ok());
+        assert!(verify_checksum(b"tampered", &checksum).is_err());
+        assert!(verify_checksum(binary, "").is_err());
+    }
+}
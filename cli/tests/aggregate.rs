@@ -0,0 +1,60 @@
+mod common;
+
+use common::Sandbox;
+use std::process::Stdio;
+use std::thread::sleep;
+use std::time::Duration;
+
+/// Aggregate `a.md`, `b.md` and `c.md` into `book.md`, separated by `---` lines.
+fn aggregate_three(sandbox: &Sandbox) {
+    let sources = ["a.md", "b.md", "c.md"].map(|name| sandbox.write(name, &format!("# {name}\n")));
+    let destination = sandbox.path("book.md");
+    
+    let output = sandbox
+        .mdman()
+        .arg("aggregate")
+        .arg(&destination)
+        .args(&sources)
+        .args(["--separator", "\n---\n"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+}
+
+#[test]
+fn three_sources_are_concatenated_in_order() {
+    let sandbox = Sandbox::new();
+    aggregate_three(&sandbox);
+    
+    assert_eq!(sandbox.read("book.md"), "# a.md\n\n---\n# b.md\n\n---\n# c.md\n");
+}
+
+#[test]
+fn sync_regenerates_after_a_source_changed() {
+    let sandbox = Sandbox::new();
+    aggregate_three(&sandbox);
+    sandbox.write("b.md", "# changed\n");
+    
+    assert!(sandbox.run(&["sync"]).status.success());
+    
+    assert_eq!(sandbox.read("book.md"), "# a.md\n\n---\n# changed\n\n---\n# c.md\n");
+}
+
+#[test]
+fn watcher_regenerates_when_a_source_changes() {
+    let sandbox = Sandbox::new();
+    aggregate_three(&sandbox);
+    sandbox.set_config("notifications", serde_json::json!({ "sync_mode": "none" }));
+    
+    let watcher = sandbox
+        .mdman()
+        .args(["watch", "--exit-after", "4s"])
+        .stdout(Stdio::null())
+        .spawn()
+        .unwrap();
+    sleep(Duration::from_millis(1500));
+    sandbox.write("c.md", "# changed\n");
+    assert!(watcher.wait_with_output().unwrap().status.success());
+    
+    assert_eq!(sandbox.read("book.md"), "# a.md\n\n---\n# b.md\n\n---\n# changed\n");
+}
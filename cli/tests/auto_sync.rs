@@ -0,0 +1,45 @@
+mod common;
+
+use common::Sandbox;
+
+/// Track `notes.md` to `auto/notes.md` and `manual/notes.md`, the latter with auto sync off.
+fn manual_destination() -> Sandbox {
+    let sandbox = Sandbox::new();
+    let source = sandbox.write("notes.md", "one\n");
+    sandbox.track(&source, &[&sandbox.path("auto/notes.md"), &sandbox.path("manual/notes.md")]);
+    let output = sandbox.run(&["auto-sync", sandbox.path("manual/notes.md").to_str().unwrap(), "off"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    sandbox.write("notes.md", "two\n");
+    sandbox
+}
+
+#[test]
+fn sync_skips_destinations_with_auto_sync_off() {
+    let sandbox = manual_destination();
+    
+    assert!(sandbox.run(&["sync"]).status.success());
+    
+    assert_eq!(sandbox.read("auto/notes.md"), "two\n");
+    assert_eq!(sandbox.read("manual/notes.md"), "one\n");
+}
+
+#[test]
+fn diff_still_reports_their_drift() {
+    let sandbox = manual_destination();
+    sandbox.run(&["sync"]);
+    
+    let output = sandbox.run(&["diff", "--exit-code", sandbox.path("notes.md").to_str().unwrap()]);
+    
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stdout).contains(&sandbox.path("manual/notes.md").display().to_string()));
+}
+
+#[test]
+fn turning_auto_sync_back_on_syncs_them_again() {
+    let sandbox = manual_destination();
+    
+    sandbox.run(&["auto-sync", sandbox.path("manual/notes.md").to_str().unwrap(), "on"]);
+    assert!(sandbox.run(&["sync"]).status.success());
+    
+    assert_eq!(sandbox.read("manual/notes.md"), "two\n");
+}
@@ -0,0 +1,42 @@
+mod common;
+
+use common::Sandbox;
+use std::fs;
+
+#[test]
+fn check_succeeds_silently_when_in_sync() {
+    let sandbox = Sandbox::new();
+    let source = sandbox.write("notes.md", "same\n");
+    sandbox.track(&source, &[&sandbox.path("copy/notes.md")]);
+    
+    let output = sandbox.run(&["check"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert!(output.stdout.is_empty());
+}
+
+#[test]
+fn check_fails_for_drifted_destinations() {
+    let sandbox = Sandbox::new();
+    let source = sandbox.write("notes.md", "same\n");
+    let destination = sandbox.path("copy/notes.md");
+    sandbox.track(&source, &[&destination]);
+    sandbox.write("copy/notes.md", "edited\n");
+    
+    let output = sandbox.run(&["check"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), format!("differs: {}\n", destination.display()));
+}
+
+#[test]
+fn check_fails_for_unreadable_destinations() {
+    let sandbox = Sandbox::new();
+    let source = sandbox.write("notes.md", "same\n");
+    let destination = sandbox.path("copy/notes.md");
+    sandbox.track(&source, &[&destination]);
+    fs::remove_file(&destination).unwrap();
+    fs::create_dir(&destination).unwrap();
+    
+    let output = sandbox.run(&["check"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stdout).starts_with(&format!("unreadable: {}", destination.display())));
+}
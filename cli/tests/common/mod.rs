@@ -0,0 +1,148 @@
+#![allow(dead_code)]
+
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::Duration;
+
+/// A scratch directory with its own mdman config, removed again when dropped.
+pub struct Sandbox {
+    root: PathBuf,
+}
+
+impl Sandbox {
+    pub fn new() -> Self {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let root = std::env::temp_dir().join(format!(
+            "mdman-test-{}-{}",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("config")).unwrap();
+        Self { root: root.canonicalize().unwrap() }
+    }
+    
+    pub fn path(&self, name: &str) -> PathBuf {
+        self.root.join(name)
+    }
+    
+    pub fn write(&self, name: &str, content: &str) -> PathBuf {
+        let path = self.path(name);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).unwrap();
+        }
+        fs::write(&path, content).unwrap();
+        path
+    }
+    
+    pub fn read(&self, name: &str) -> String {
+        fs::read_to_string(self.path(name)).unwrap()
+    }
+    
+    /// Path of the config file `mdman` reads in this sandbox.
+    pub fn config_file(&self) -> PathBuf {
+        self.root.join("config").join("mdman").join("config.json")
+    }
+    
+    /// `mdman` using this sandbox's config, state and cache directories.
+    pub fn mdman(&self) -> Command {
+        let mut command = Command::new(env!("CARGO_BIN_EXE_mdman"));
+        command
+            .env("XDG_CONFIG_HOME", self.root.join("config"))
+            .env("XDG_STATE_HOME", self.root.join("state"))
+            .env("XDG_CACHE_HOME", self.root.join("cache"))
+            .env_remove("MDMAN_SAFE_MODE");
+        command
+    }
+    
+    /// Run `mdman` with `args`, failing the test if it can't be started.
+    pub fn run(&self, args: &[&str]) -> Output {
+        self.mdman().args(args).stdin(Stdio::null()).output().unwrap()
+    }
+    
+    /// Run `mdman` with `args`, answering its prompts with `input`.
+    pub fn run_with_input(&self, args: &[&str], input: &str) -> Output {
+        let mut child = self
+            .mdman()
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        child.stdin.take().unwrap().write_all(input.as_bytes()).unwrap();
+        child.wait_with_output().unwrap()
+    }
+    
+    /// Track `source` with `destinations`, which start out with the source's content.
+    pub fn track(&self, source: &Path, destinations: &[&Path]) {
+        let Some((first, rest)) = destinations.split_first() else {
+            return;
+        };
+        let output = self.mdman().arg("copy").arg(source).arg(first).stdin(Stdio::null()).output().unwrap();
+        assert!(output.status.success(), "copy failed: {}", String::from_utf8_lossy(&output.stderr));
+        
+        let mut config = self.config();
+        for destination in rest {
+            fs::create_dir_all(destination.parent().unwrap()).unwrap();
+            fs::copy(source, destination).unwrap();
+            config["mappings"][source.to_str().unwrap()]["destinations"]
+                .as_array_mut()
+                .unwrap()
+                .push(destination.to_str().unwrap().into());
+        }
+        self.save_config(&config);
+    }
+    
+    pub fn config(&self) -> serde_json::Value {
+        serde_json::from_slice(&fs::read(self.config_file()).unwrap()).unwrap()
+    }
+    
+    pub fn save_config(&self, config: &serde_json::Value) {
+        fs::write(self.config_file(), serde_json::to_vec_pretty(config).unwrap()).unwrap();
+    }
+    
+    /// Change `key` in the config file, which must already exist.
+    pub fn set_config(&self, key: &str, value: serde_json::Value) {
+        let mut config = self.config();
+        config[key] = value;
+        self.save_config(&config);
+    }
+    
+    /// Run `watch` with `args` until `destination` was edited behind its back, returning the summary.
+    pub fn watch_desync(&self, destination: &Path, args: &[&str]) -> String {
+        let watcher = self
+            .mdman()
+            .args(["watch", "--exit-after-events", "1", "--exit-after", "20s"])
+            .args(args)
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        thread::sleep(Duration::from_millis(1500));
+        fs::write(destination, "edited\n").unwrap();
+        let output = watcher.wait_with_output().unwrap();
+        assert!(output.status.success());
+        String::from_utf8_lossy(&output.stdout).into_owned()
+    }
+    
+    /// Wait a few seconds for a detached hook to write `name`.
+    pub fn wait_for(&self, name: &str) -> Option<String> {
+        for _ in 0..50 {
+            if let Ok(content) = fs::read_to_string(self.path(name)) {
+                return Some(content);
+            }
+            thread::sleep(Duration::from_millis(100));
+        }
+        None
+    }
+}
+
+impl Drop for Sandbox {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.root);
+    }
+}
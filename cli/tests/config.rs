@@ -0,0 +1,218 @@
+mod common;
+
+use common::Sandbox;
+
+fn set(sandbox: &Sandbox, key: &str, value: &str) -> std::process::Output {
+    sandbox.run(&["config", "set", key, value])
+}
+
+#[test]
+fn string_options_take_values_that_look_like_json() {
+    let sandbox = Sandbox::new();
+    sandbox.run(&["init"]);
+    
+    for value in ["true", "123", "notify-send desync"] {
+        let output = set(&sandbox, "on_desync", value);
+        assert!(output.status.success(), "{value}: {}", String::from_utf8_lossy(&output.stderr));
+        assert_eq!(sandbox.config()["on_desync"], value);
+    }
+}
+
+#[test]
+fn typed_options_are_parsed() {
+    let sandbox = Sandbox::new();
+    sandbox.run(&["init"]);
+    
+    assert!(set(&sandbox, "durable_writes", "true").status.success());
+    assert!(set(&sandbox, "sync_threads", "4").status.success());
+    
+    let config = sandbox.config();
+    assert_eq!(config["durable_writes"], true);
+    assert_eq!(config["sync_threads"], 4);
+}
+
+#[test]
+fn values_of_the_wrong_type_are_rejected() {
+    let sandbox = Sandbox::new();
+    sandbox.run(&["init"]);
+    
+    let output = set(&sandbox, "sync_threads", "many");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Invalid value many for sync_threads"));
+}
+
+#[test]
+fn null_restores_the_default() {
+    let sandbox = Sandbox::new();
+    sandbox.run(&["init"]);
+    set(&sandbox, "on_desync", "true");
+    
+    assert!(set(&sandbox, "on_desync", "null").status.success());
+    assert!(sandbox.config().get("on_desync").is_none());
+}
+
+#[test]
+fn v0_config_is_upgraded_with_a_backup() {
+    let sandbox = Sandbox::new();
+    let v0 = serde_json::json!({
+        "mappings": { sandbox.path("notes.md").to_str().unwrap(): [sandbox.path("copy/notes.md")] },
+    });
+    std::fs::create_dir_all(sandbox.config_file().parent().unwrap()).unwrap();
+    sandbox.save_config(&v0);
+    
+    let output = sandbox.run(&["list", "--print0"]);
+    
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let config = sandbox.config();
+    assert_eq!(config["version"], 1);
+    assert_eq!(
+        config["mappings"][sandbox.path("notes.md").to_str().unwrap()]["destinations"],
+        serde_json::json!([sandbox.path("copy/notes.md")])
+    );
+    let backup = sandbox.config_file().with_extension("json.v0.bak");
+    assert_eq!(serde_json::from_slice::<serde_json::Value>(&std::fs::read(backup).unwrap()).unwrap(), v0);
+}
+
+#[test]
+fn normalize_merges_sources_spelled_differently() {
+    let sandbox = Sandbox::new();
+    let source = sandbox.write("notes.md", "content\n");
+    std::fs::create_dir_all(sandbox.path("sub")).unwrap();
+    let a = sandbox.write("copy/a.md", "content\n");
+    let b = sandbox.write("copy/b.md", "content\n");
+    let missing = sandbox.path("gone.md");
+    let config = serde_json::json!({
+        "version": 1,
+        "mappings": {
+            source.to_str().unwrap(): { "destinations": [a] },
+            sandbox.path("sub/../notes.md").to_str().unwrap(): { "destinations": [b] },
+            missing.to_str().unwrap(): { "destinations": [sandbox.path("copy/gone.md")] },
+        },
+    });
+    std::fs::create_dir_all(sandbox.config_file().parent().unwrap()).unwrap();
+    sandbox.save_config(&config);
+    
+    let output = sandbox.run(&["normalize"]);
+    
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains(&format!("Merged duplicate entries for {}", source.display())), "{stdout}");
+    assert!(stdout.contains(&format!("Could not canonicalize {}", missing.display())), "{stdout}");
+    let mappings = &sandbox.config()["mappings"];
+    assert_eq!(mappings.as_object().unwrap().len(), 2);
+    assert_eq!(mappings[source.to_str().unwrap()]["destinations"], serde_json::json!([a, b]));
+}
+
+#[test]
+fn normalize_leaves_canonical_configs_alone() {
+    let sandbox = Sandbox::new();
+    let source = sandbox.write("notes.md", "content\n");
+    sandbox.track(&source, &[&sandbox.path("copy/notes.md")]);
+    let before = sandbox.config();
+    
+    let output = sandbox.run(&["normalize"]);
+    
+    assert!(String::from_utf8_lossy(&output.stdout).contains("All tracked paths are already canonical"));
+    assert_eq!(sandbox.config(), before);
+}
+
+#[test]
+fn missing_config_means_nothing_is_tracked() {
+    let sandbox = Sandbox::new();
+    
+    let output = sandbox.run(&["list"]);
+    
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("No files are currently being tracked"));
+}
+
+#[test]
+fn invalid_config_is_reported_as_invalid() {
+    let sandbox = Sandbox::new();
+    sandbox.write("config/mdman/config.json", "{ not json");
+    
+    let output = sandbox.run(&["list"]);
+    
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains(&format!("Config {} is invalid", sandbox.config_file().display())), "{stderr}");
+}
+
+#[test]
+fn unreadable_config_is_reported_as_unreadable() {
+    let sandbox = Sandbox::new();
+    // Reading a directory fails even as root, unlike a file without permissions
+    std::fs::create_dir_all(sandbox.config_file()).unwrap();
+    
+    let output = sandbox.run(&["list"]);
+    
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains(&format!("Could not read config {}", sandbox.config_file().display())), "{stderr}");
+}
+
+#[cfg(unix)]
+#[test]
+fn read_only_config_dir_allows_only_read_commands() {
+    use std::os::unix::fs::PermissionsExt;
+    
+    let sandbox = Sandbox::new();
+    let source = sandbox.write("notes.md", "one\n");
+    sandbox.track(&source, &[&sandbox.path("copy/notes.md")]);
+    let dir = sandbox.config_file().parent().unwrap().to_path_buf();
+    std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o555)).unwrap();
+    if std::fs::File::create(dir.join("probe")).is_ok() {
+        // Permissions don't apply to root, there's no way to make the directory read-only
+        std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o755)).unwrap();
+        return;
+    }
+    
+    let list = sandbox.run(&["list"]);
+    let other = sandbox.write("other.md", "two\n");
+    let copy = sandbox.run(&["copy", other.to_str().unwrap(), sandbox.path("copy/other.md").to_str().unwrap()]);
+    std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o755)).unwrap();
+    
+    assert!(list.status.success());
+    assert!(!copy.status.success());
+    assert!(String::from_utf8_lossy(&copy.stderr).contains("is not writable"));
+    assert!(!sandbox.path("copy/other.md").exists());
+}
+
+#[test]
+fn config_option_keeps_the_config_elsewhere() {
+    let sandbox = Sandbox::new();
+    let source = sandbox.write("notes.md", "one\n");
+    let elsewhere = sandbox.path("elsewhere");
+    
+    let output = sandbox.run(&[
+        "--config",
+        elsewhere.to_str().unwrap(),
+        "copy",
+        source.to_str().unwrap(),
+        sandbox.path("copy/notes.md").to_str().unwrap(),
+    ]);
+    
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(elsewhere.join("config.json").exists());
+    assert!(!sandbox.config_file().exists());
+}
+
+#[test]
+fn compact_and_pretty_configs_hold_the_same_mappings() {
+    let sandbox = Sandbox::new();
+    for name in ["a.md", "b.md"] {
+        let source = sandbox.write(name, "content\n");
+        sandbox.track(&source, &[&sandbox.path(&format!("copy/{name}"))]);
+    }
+    let pretty = sandbox.config()["mappings"].clone();
+    
+    assert!(set(&sandbox, "compact_config", "true").status.success());
+    let compact = std::fs::read_to_string(sandbox.config_file()).unwrap();
+    assert_eq!(compact.lines().count(), 1);
+    assert_eq!(sandbox.config()["mappings"], pretty);
+    
+    // Loaded from the compact form and saved pretty again
+    assert!(set(&sandbox, "compact_config", "false").status.success());
+    assert!(std::fs::read_to_string(sandbox.config_file()).unwrap().lines().count() > 1);
+    assert_eq!(sandbox.config()["mappings"], pretty);
+}
@@ -0,0 +1,285 @@
+mod common;
+
+use common::Sandbox;
+use std::fs;
+
+#[test]
+fn template_tracks_a_destination_in_every_matching_directory() {
+    let sandbox = Sandbox::new();
+    let source = sandbox.write("notes.md", "shared\n");
+    fs::create_dir_all(sandbox.path("projects/a")).unwrap();
+    fs::create_dir_all(sandbox.path("projects/b")).unwrap();
+    // Files matching the pattern aren't directories to put a destination in
+    sandbox.write("projects/c", "");
+    let template = sandbox.path("projects/*/README.md");
+    
+    let output = sandbox.run(&["copy", source.to_str().unwrap(), "--template", template.to_str().unwrap()]);
+    
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(sandbox.read("projects/a/README.md"), "shared\n");
+    assert_eq!(sandbox.read("projects/b/README.md"), "shared\n");
+    let destinations = &sandbox.config()["mappings"][source.to_str().unwrap()]["destinations"];
+    assert_eq!(destinations.as_array().unwrap().len(), 2);
+}
+
+#[test]
+fn template_without_matches_tracks_nothing() {
+    let sandbox = Sandbox::new();
+    let source = sandbox.write("notes.md", "shared\n");
+    let template = sandbox.path("projects/*/README.md");
+    
+    let output = sandbox.run(&["copy", source.to_str().unwrap(), "--template", template.to_str().unwrap()]);
+    
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("did not match any existing directory"));
+}
+
+#[test]
+fn copy_onto_a_hardlink_of_the_source_is_refused() {
+    let sandbox = Sandbox::new();
+    let source = sandbox.write("notes.md", "content\n");
+    fs::create_dir_all(sandbox.path("copy")).unwrap();
+    fs::hard_link(&source, sandbox.path("copy/notes.md")).unwrap();
+    
+    let output = sandbox.run(&["copy", source.to_str().unwrap(), sandbox.path("copy").to_str().unwrap()]);
+    
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("is a hardlink of"));
+    assert_eq!(sandbox.read("notes.md"), "content\n");
+}
+
+#[test]
+fn differing_destination_is_kept_without_overwrite() {
+    let sandbox = Sandbox::new();
+    let source = sandbox.write("notes.md", "new\n");
+    let destination = sandbox.write("copy/notes.md", "precious\n");
+    
+    let output = sandbox.run(&["copy", source.to_str().unwrap(), destination.to_str().unwrap()]);
+    
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("pass --overwrite"));
+    assert_eq!(sandbox.read("copy/notes.md"), "precious\n");
+}
+
+#[test]
+fn overwrite_replaces_a_differing_destination() {
+    let sandbox = Sandbox::new();
+    let source = sandbox.write("notes.md", "new\n");
+    let destination = sandbox.write("copy/notes.md", "precious\n");
+    
+    let output = sandbox.run(&["copy", source.to_str().unwrap(), destination.to_str().unwrap(), "--overwrite"]);
+    
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(sandbox.read("copy/notes.md"), "new\n");
+}
+
+#[test]
+fn identical_destination_is_tracked_without_overwrite() {
+    let sandbox = Sandbox::new();
+    let source = sandbox.write("notes.md", "same\n");
+    let destination = sandbox.write("copy/notes.md", "same\n");
+    
+    let output = sandbox.run(&["copy", source.to_str().unwrap(), destination.to_str().unwrap()]);
+    
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(sandbox.config()["mappings"].get(source.to_str().unwrap()).is_some());
+}
+
+#[test]
+fn priority_is_stored_with_the_mapping() {
+    let sandbox = Sandbox::new();
+    let source = sandbox.write("notes.md", "content\n");
+    let destination = sandbox.path("copy/notes.md");
+    fs::create_dir_all(sandbox.path("copy")).unwrap();
+    
+    let output = sandbox.run(&[
+        "copy",
+        source.to_str().unwrap(),
+        destination.to_str().unwrap(),
+        "--priority",
+        "-2",
+    ]);
+    
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(sandbox.config()["mappings"][source.to_str().unwrap()]["priority"], -2);
+}
+
+#[test]
+fn copying_to_a_tracked_destination_again_does_not_duplicate_it() {
+    let sandbox = Sandbox::new();
+    let source = sandbox.write("notes.md", "one\n");
+    let destination = sandbox.path("copy/notes.md");
+    sandbox.track(&source, &[&destination]);
+    
+    sandbox.run(&["copy", source.to_str().unwrap(), destination.to_str().unwrap()]);
+    
+    assert_eq!(sandbox.config()["mappings"][source.to_str().unwrap()]["destinations"], serde_json::json!([destination]));
+}
+
+#[cfg(unix)]
+#[test]
+fn no_canonicalize_keeps_symlinked_paths_and_finds_them() {
+    let sandbox = Sandbox::new();
+    sandbox.write("real/notes.md", "one\n");
+    std::os::unix::fs::symlink(sandbox.path("real"), sandbox.path("link")).unwrap();
+    let source = sandbox.path("link/notes.md");
+    let destination = sandbox.path("copy/notes.md");
+    
+    let output = sandbox.run(&["copy", "--no-canonicalize", source.to_str().unwrap(), destination.to_str().unwrap()]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    
+    let config = sandbox.config();
+    assert_eq!(config["no_canonicalize"], true);
+    assert_eq!(config["mappings"][source.to_str().unwrap()]["destinations"], serde_json::json!([destination]));
+    
+    sandbox.write("copy/notes.md", "edited\n");
+    assert_eq!(sandbox.run(&["diff", "--exit-code", source.to_str().unwrap()]).status.code(), Some(1));
+    assert!(sandbox.run(&["sync"]).status.success());
+    assert_eq!(sandbox.run(&["diff", "--exit-code", source.to_str().unwrap()]).status.code(), Some(0));
+}
+
+#[cfg(unix)]
+#[test]
+fn hardlink_destination_shares_the_source_and_is_relinked_by_sync() {
+    use std::os::unix::fs::MetadataExt;
+    
+    let sandbox = Sandbox::new();
+    let source = sandbox.write("notes.md", "one\n");
+    let destination = sandbox.path("copy/notes.md");
+    fs::create_dir_all(sandbox.path("copy")).unwrap();
+    
+    let output = sandbox.run(&["copy", "--hardlink", source.to_str().unwrap(), destination.to_str().unwrap()]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(fs::metadata(&destination).unwrap().ino(), fs::metadata(&source).unwrap().ino());
+    assert_eq!(sandbox.config()["mappings"][source.to_str().unwrap()]["links"][destination.to_str().unwrap()], "hardlink");
+    
+    // An editor saving by replacing the file breaks the link
+    fs::remove_file(&destination).unwrap();
+    sandbox.write("copy/notes.md", "edited\n");
+    assert!(sandbox.run(&["sync"]).status.success());
+    
+    assert_eq!(fs::metadata(&destination).unwrap().ino(), fs::metadata(&source).unwrap().ino());
+    assert_eq!(sandbox.read("copy/notes.md"), "one\n");
+}
+
+#[cfg(unix)]
+#[test]
+fn symlink_destination_points_to_the_source_and_survives_sync() {
+    let sandbox = Sandbox::new();
+    let source = sandbox.write("notes.md", "one\n");
+    let destination = sandbox.path("copy/notes.md");
+    fs::create_dir_all(sandbox.path("copy")).unwrap();
+    
+    let output = sandbox.run(&["copy", "--symlink", source.to_str().unwrap(), destination.to_str().unwrap()]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(sandbox.config()["mappings"][source.to_str().unwrap()]["links"][destination.to_str().unwrap()], "symlink");
+    
+    sandbox.write("notes.md", "two\n");
+    assert!(sandbox.run(&["sync"]).status.success());
+    
+    assert_eq!(fs::read_link(&destination).unwrap(), source);
+    assert_eq!(sandbox.read("copy/notes.md"), "two\n");
+}
+
+#[test]
+fn hardlink_and_symlink_conflict() {
+    let sandbox = Sandbox::new();
+    let source = sandbox.write("notes.md", "one\n");
+    
+    let output = sandbox.run(&["copy", "--hardlink", "--symlink", source.to_str().unwrap(), sandbox.path("copy").to_str().unwrap()]);
+    
+    assert_eq!(output.status.code(), Some(2));
+}
+
+/// Make a FIFO at `name`, which would block anyone reading it until a writer shows up.
+#[cfg(unix)]
+fn fifo(sandbox: &Sandbox, name: &str) -> std::path::PathBuf {
+    let path = sandbox.path(name);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    let c_path = std::ffi::CString::new(path.to_str().unwrap()).unwrap();
+    assert_eq!(unsafe { libc::mkfifo(c_path.as_ptr(), 0o644) }, 0);
+    path
+}
+
+#[cfg(unix)]
+#[test]
+fn fifo_source_is_rejected() {
+    let sandbox = Sandbox::new();
+    let source = fifo(&sandbox, "notes.md");
+    
+    let output = sandbox.run(&["copy", source.to_str().unwrap(), sandbox.path("copy/notes.md").to_str().unwrap()]);
+    
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("is not a file"));
+    assert!(!sandbox.config_file().exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn fifo_destination_fails_the_sync_instead_of_blocking_it() {
+    let sandbox = Sandbox::new();
+    let source = sandbox.write("notes.md", "one\n");
+    let destination = sandbox.path("copy/notes.md");
+    sandbox.track(&source, &[&destination]);
+    fs::remove_file(&destination).unwrap();
+    fifo(&sandbox, "copy/notes.md");
+    sandbox.write("notes.md", "two\n");
+    
+    let output = sandbox.run(&["sync"]);
+    
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("is a FIFO"), "{}", String::from_utf8_lossy(&output.stderr));
+}
+
+#[test]
+fn adopt_tracks_an_identical_destination_without_writing_it() {
+    let sandbox = Sandbox::new();
+    let source = sandbox.write("notes.md", "same\n");
+    let destination = sandbox.write("copy/notes.md", "same\n");
+    let written = std::time::SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_000_000);
+    fs::File::options().write(true).open(&destination).unwrap().set_modified(written).unwrap();
+    
+    let output = sandbox.run(&["copy", "--adopt", source.to_str().unwrap(), destination.to_str().unwrap()]);
+    
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Adopted"));
+    assert_eq!(fs::metadata(&destination).unwrap().modified().unwrap(), written);
+    assert_eq!(sandbox.config()["mappings"][source.to_str().unwrap()]["destinations"], serde_json::json!([destination]));
+    
+    // Adopting it again changes nothing
+    let output = sandbox.run(&["copy", "--adopt", source.to_str().unwrap(), destination.to_str().unwrap()]);
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("is already tracked"));
+}
+
+#[test]
+fn adopt_asks_before_overwriting_a_differing_destination() {
+    let sandbox = Sandbox::new();
+    let source = sandbox.write("notes.md", "new\n");
+    let destination = sandbox.write("copy/notes.md", "old\n");
+    let args = ["copy", "--adopt", source.to_str().unwrap(), destination.to_str().unwrap()];
+    
+    let declined = sandbox.run_with_input(&args, "n\n");
+    assert!(!declined.status.success());
+    assert!(String::from_utf8_lossy(&declined.stdout).contains("Overwrite it with the source?"));
+    assert_eq!(sandbox.read("copy/notes.md"), "old\n");
+    assert!(!sandbox.config_file().exists());
+    
+    let confirmed = sandbox.run_with_input(&args, "y\n");
+    assert!(confirmed.status.success(), "{}", String::from_utf8_lossy(&confirmed.stderr));
+    assert_eq!(sandbox.read("copy/notes.md"), "new\n");
+    assert_eq!(sandbox.config()["mappings"][source.to_str().unwrap()]["destinations"], serde_json::json!([destination]));
+}
+
+#[test]
+fn adopt_with_yes_overwrites_without_asking() {
+    let sandbox = Sandbox::new();
+    let source = sandbox.write("notes.md", "new\n");
+    let destination = sandbox.write("copy/notes.md", "old\n");
+    
+    let output = sandbox.run(&["copy", "--adopt", "--yes", source.to_str().unwrap(), destination.to_str().unwrap()]);
+    
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(!String::from_utf8_lossy(&output.stdout).contains("Overwrite it with the source?"));
+    assert_eq!(sandbox.read("copy/notes.md"), "new\n");
+}
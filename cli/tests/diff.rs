@@ -0,0 +1,295 @@
+mod common;
+
+use common::Sandbox;
+use std::fs;
+
+#[test]
+fn exit_code_is_zero_when_in_sync() {
+    let sandbox = Sandbox::new();
+    let source = sandbox.write("notes.md", "same\n");
+    sandbox.track(&source, &[&sandbox.path("copy/notes.md")]);
+    
+    let output = sandbox.run(&["diff", "--exit-code"]);
+    assert_eq!(output.status.code(), Some(0));
+}
+
+#[test]
+fn exit_code_is_one_when_destinations_differ() {
+    let sandbox = Sandbox::new();
+    let source = sandbox.write("notes.md", "same\n");
+    sandbox.track(&source, &[&sandbox.path("copy/notes.md")]);
+    sandbox.write("copy/notes.md", "edited\n");
+    
+    let output = sandbox.run(&["diff", "--exit-code"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(!output.stdout.is_empty());
+}
+
+#[test]
+fn quiet_prints_nothing_and_implies_exit_code() {
+    let sandbox = Sandbox::new();
+    let source = sandbox.write("notes.md", "same\n");
+    sandbox.track(&source, &[&sandbox.path("copy/notes.md")]);
+    sandbox.write("copy/notes.md", "edited\n");
+    
+    let output = sandbox.run(&["diff", "--quiet"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(output.stdout.is_empty());
+}
+
+#[test]
+fn exit_code_is_two_on_errors() {
+    let sandbox = Sandbox::new();
+    let source = sandbox.write("notes.md", "same\n");
+    sandbox.track(&source, &[&sandbox.path("copy/notes.md")]);
+    // The config can't be parsed
+    fs::write(sandbox.config_file(), "{").unwrap();
+    
+    let output = sandbox.run(&["diff", "--exit-code"]);
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn exit_code_is_two_for_unreadable_destinations() {
+    let sandbox = Sandbox::new();
+    let source = sandbox.write("notes.md", "same\n");
+    let destination = sandbox.path("copy/notes.md");
+    sandbox.track(&source, &[&destination]);
+    // Reading a directory fails even as root, unlike a file without permissions
+    fs::remove_file(&destination).unwrap();
+    fs::create_dir(&destination).unwrap();
+    
+    let output = sandbox.run(&["diff", "--exit-code"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stdout).contains(&format!("Can't read {}", destination.display())));
+}
+
+#[test]
+fn semantic_compare_ignores_reformatted_whitespace() {
+    let sandbox = Sandbox::new();
+    let source = sandbox.write("notes.md", "# Title\n\nText\n");
+    sandbox.track(&source, &[&sandbox.path("copy/notes.md")]);
+    sandbox.write("copy/notes.md", "# Title  \n\n\nText\n\n");
+    
+    assert_eq!(sandbox.run(&["diff", "--exit-code"]).status.code(), Some(1));
+    sandbox.set_config("semantic_md_compare", true.into());
+    assert_eq!(sandbox.run(&["diff", "--exit-code"]).status.code(), Some(0));
+}
+
+#[test]
+fn only_limits_the_diff_to_matching_sources() {
+    let sandbox = Sandbox::new();
+    let docs = sandbox.write("docs/guide.md", "one\n");
+    let notes = sandbox.write("notes/todo.md", "one\n");
+    sandbox.track(&docs, &[&sandbox.path("copy/guide.md")]);
+    sandbox.track(&notes, &[&sandbox.path("copy/todo.md")]);
+    sandbox.write("copy/todo.md", "edited\n");
+    
+    assert_eq!(sandbox.run(&["diff", "--exit-code", "--only", "docs/*.md"]).status.code(), Some(0));
+    assert_eq!(sandbox.run(&["diff", "--exit-code", "--only", "notes/*.md"]).status.code(), Some(1));
+}
+
+#[test]
+fn word_diff_shows_inline_markers() {
+    let sandbox = Sandbox::new();
+    let source = sandbox.write("notes.md", "The quick brown fox.\n");
+    sandbox.track(&source, &[&sandbox.path("copy/notes.md")]);
+    sandbox.write("copy/notes.md", "The quick red fox.\n");
+    
+    let output = sandbox.run(&["diff", "--word-diff", "--color", "never"]);
+    
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("The quick [-brown-]{+red+} fox."), "{stdout}");
+}
+
+#[test]
+fn word_diff_falls_back_for_binary_files() {
+    let sandbox = Sandbox::new();
+    let source = sandbox.write("image.md", "text\n");
+    sandbox.track(&source, &[&sandbox.path("copy/image.md")]);
+    fs::write(sandbox.path("copy/image.md"), b"\xff\xfe").unwrap();
+    
+    let output = sandbox.run(&["diff", "--word-diff"]);
+    
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Binary files, no word diff"));
+}
+
+#[test]
+fn directory_selects_only_mappings_below_it() {
+    let sandbox = Sandbox::new();
+    let inside = sandbox.write("notes/a.md", "a\n");
+    sandbox.write("notes/untracked.md", "b\n");
+    let outside = sandbox.write("other/c.md", "c\n");
+    sandbox.track(&inside, &[&sandbox.path("copy/a.md")]);
+    sandbox.track(&outside, &[&sandbox.path("copy/c.md")]);
+    sandbox.write("copy/a.md", "edited\n");
+    sandbox.write("copy/c.md", "edited\n");
+    
+    let output = sandbox.run(&["diff", "--exit-code", sandbox.path("notes").to_str().unwrap()]);
+    
+    assert_eq!(output.status.code(), Some(1));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains(&sandbox.path("copy/a.md").display().to_string()), "{stdout}");
+    assert!(!stdout.contains("c.md"), "{stdout}");
+}
+
+#[test]
+fn directory_without_drift_has_no_differences() {
+    let sandbox = Sandbox::new();
+    let source = sandbox.write("notes/a.md", "a\n");
+    sandbox.write("notes/untracked.md", "b\n");
+    sandbox.track(&source, &[&sandbox.path("copy/a.md")]);
+    
+    let output = sandbox.run(&["diff", "--exit-code", sandbox.path("notes").to_str().unwrap()]);
+    
+    assert_eq!(output.status.code(), Some(0));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("No differences found for the specified path"));
+}
+
+#[test]
+fn directory_also_selects_destinations_below_it() {
+    let sandbox = Sandbox::new();
+    let source = sandbox.write("notes/a.md", "a\n");
+    sandbox.track(&source, &[&sandbox.path("published/a.md")]);
+    sandbox.write("published/a.md", "edited\n");
+    
+    let output = sandbox.run(&["diff", "--exit-code", sandbox.path("published").to_str().unwrap()]);
+    
+    assert_eq!(output.status.code(), Some(1));
+}
+
+/// `diff` output for a source with `source` content and a destination edited to `destination`.
+fn diff_output(source: &[u8], destination: &[u8]) -> String {
+    let sandbox = Sandbox::new();
+    let path = sandbox.path("notes.md");
+    fs::write(&path, source).unwrap();
+    sandbox.track(&path, &[&sandbox.path("copy/notes.md")]);
+    fs::write(sandbox.path("copy/notes.md"), destination).unwrap();
+    String::from_utf8_lossy(&sandbox.run(&["diff"]).stdout).into_owned()
+}
+
+#[test]
+fn similarity_is_high_for_a_single_changed_line() {
+    let lines: String = (0..10).map(|i| format!("line {i}\n")).collect();
+    let output = diff_output(lines.as_bytes(), lines.replace("line 5", "edited").as_bytes());
+    
+    assert!(output.contains("  90% similar"), "{output}");
+}
+
+#[test]
+fn similarity_is_zero_for_unrelated_content() {
+    let output = diff_output(b"one\ntwo\n", b"three\nfour\n");
+    
+    assert!(output.contains("  0% similar"), "{output}");
+}
+
+#[test]
+fn similarity_is_left_out_for_binary_files() {
+    let output = diff_output(b"text\n", b"\xff\xfe");
+    
+    assert!(!output.contains("similar"), "{output}");
+}
+
+#[test]
+fn against_reports_a_matching_file_as_the_same() {
+    let sandbox = Sandbox::new();
+    let source = sandbox.write("notes.md", "same\n");
+    let other = sandbox.write("elsewhere/notes.md", "same\n");
+    
+    let output = sandbox.run(&["diff", "--exit-code", source.to_str().unwrap(), "--against", other.to_str().unwrap()]);
+    
+    assert_eq!(output.status.code(), Some(0));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("are the same"));
+}
+
+#[test]
+fn against_shows_the_differences_of_an_untracked_file() {
+    let sandbox = Sandbox::new();
+    let source = sandbox.write("notes.md", "same\n");
+    let other = sandbox.write("elsewhere/notes.md", "changed\n");
+    
+    let output = sandbox.run(&["diff", "--exit-code", source.to_str().unwrap(), "--against", other.to_str().unwrap()]);
+    
+    assert_eq!(output.status.code(), Some(1));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains(&other.display().to_string()), "{stdout}");
+    assert!(!sandbox.config_file().exists());
+}
+
+/// Whether `diff --color when` colors its report of a drifted destination when piped.
+fn colored(when: &str, no_color: bool) -> bool {
+    let sandbox = Sandbox::new();
+    let source = sandbox.write("notes.md", "one\n");
+    sandbox.track(&source, &[&sandbox.path("copy/notes.md")]);
+    sandbox.write("copy/notes.md", "edited\n");
+    
+    let mut diff = sandbox.mdman();
+    diff.args(["diff", "--color", when]);
+    if no_color {
+        diff.env("NO_COLOR", "1");
+    }
+    let output = diff.output().unwrap();
+    String::from_utf8_lossy(&output.stdout).contains("\x1b[")
+}
+
+#[test]
+fn color_always_colors_piped_output() {
+    assert!(colored("always", false));
+    assert!(colored("always", true));
+}
+
+#[test]
+fn color_never_and_auto_leave_piped_output_plain() {
+    assert!(!colored("never", false));
+    assert!(!colored("auto", false));
+    assert!(!colored("auto", true));
+}
+
+/// A deleted source `a.md`, a deleted destination of `b.md` and a drifted destination of `c.md`.
+fn problems_on_both_sides() -> Sandbox {
+    let sandbox = Sandbox::new();
+    for name in ["a.md", "b.md", "c.md"] {
+        let source = sandbox.write(name, "content\n");
+        sandbox.track(&source, &[&sandbox.path(&format!("copy/{name}"))]);
+    }
+    fs::remove_file(sandbox.path("a.md")).unwrap();
+    fs::remove_file(sandbox.path("copy/b.md")).unwrap();
+    sandbox.write("copy/c.md", "edited\n");
+    sandbox
+}
+
+fn diff_stdout(sandbox: &Sandbox, args: &[&str]) -> String {
+    let output = sandbox.run(&[&["diff"], args].concat());
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+#[test]
+fn destinations_only_leaves_out_missing_sources() {
+    let sandbox = problems_on_both_sides();
+    
+    let stdout = diff_stdout(&sandbox, &["--destinations-only"]);
+    
+    assert!(!stdout.contains("Source file"), "{stdout}");
+    assert!(stdout.contains(&format!("Destination {} does not exist", sandbox.path("copy/b.md").display())), "{stdout}");
+    assert!(stdout.contains(&format!("Dest:   {}", sandbox.path("copy/c.md").display())), "{stdout}");
+}
+
+#[test]
+fn sources_only_leaves_out_destination_problems() {
+    let sandbox = problems_on_both_sides();
+    
+    let stdout = diff_stdout(&sandbox, &["--sources-only"]);
+    
+    assert_eq!(stdout.trim_end(), format!("Source file {} does not exist", sandbox.path("a.md").display()));
+}
+
+#[test]
+fn scoped_diff_without_problems_says_so() {
+    let sandbox = Sandbox::new();
+    let source = sandbox.write("notes.md", "content\n");
+    sandbox.track(&source, &[&sandbox.path("copy/notes.md")]);
+    sandbox.write("copy/notes.md", "edited\n");
+    
+    assert!(diff_stdout(&sandbox, &["--sources-only"]).contains("No sources are missing"));
+    assert_eq!(sandbox.run(&["diff", "--sources-only", "--destinations-only"]).status.code(), Some(2));
+}
@@ -0,0 +1,78 @@
+mod common;
+
+use common::Sandbox;
+
+/// A project with markdown files, one of them ignored and one already tracked.
+fn project() -> Sandbox {
+    let sandbox = Sandbox::new();
+    sandbox.write("project/.gitignore", "build/\n");
+    sandbox.write("project/README.md", "readme\n");
+    sandbox.write("project/docs/guide.markdown", "guide\n");
+    sandbox.write("project/build/generated.md", "generated\n");
+    sandbox.write("project/notes.txt", "not markdown\n");
+    let tracked = sandbox.write("project/tracked.md", "tracked\n");
+    sandbox.track(&tracked, &[&sandbox.path("elsewhere/tracked.md")]);
+    sandbox
+}
+
+fn discover(sandbox: &Sandbox, args: &[&str], input: &str) -> std::process::Output {
+    let dir = sandbox.path("project");
+    let dest_root = sandbox.path("mirror");
+    sandbox.run_with_input(&[&["discover", dir.to_str().unwrap(), dest_root.to_str().unwrap()], args].concat(), input)
+}
+
+fn tracked(sandbox: &Sandbox) -> Vec<String> {
+    sandbox.config()["mappings"].as_object().unwrap().keys().cloned().collect()
+}
+
+#[test]
+fn lists_untracked_markdown_files_that_are_not_ignored() {
+    let sandbox = project();
+    
+    let output = discover(&sandbox, &[], "\n");
+    
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let listed: Vec<&str> = stdout.lines().filter(|line| line.contains(" → ")).collect();
+    assert_eq!(listed.len(), 2, "{stdout}");
+    assert!(listed[0].contains(&format!("1. {} → {}", sandbox.path("project/README.md").display(), sandbox.path("mirror/README.md").display())));
+    assert!(listed[1].contains(&format!("2. {}", sandbox.path("project/docs/guide.markdown").display())));
+    assert!(stdout.contains("Nothing tracked"));
+    assert_eq!(tracked(&sandbox).len(), 1);
+}
+
+#[test]
+fn selected_files_are_copied_and_tracked() {
+    let sandbox = project();
+    
+    let output = discover(&sandbox, &[], "2\n");
+    
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(sandbox.read("mirror/docs/guide.markdown"), "guide\n");
+    assert!(!sandbox.path("mirror/README.md").exists());
+    assert!(tracked(&sandbox).contains(&sandbox.path("project/docs/guide.markdown").display().to_string()));
+    assert_eq!(tracked(&sandbox).len(), 2);
+}
+
+#[test]
+fn all_tracks_every_file_found_without_asking() {
+    let sandbox = project();
+    
+    let output = discover(&sandbox, &["--all"], "");
+    
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(sandbox.read("mirror/README.md"), "readme\n");
+    assert_eq!(sandbox.read("mirror/docs/guide.markdown"), "guide\n");
+    assert_eq!(tracked(&sandbox).len(), 3);
+}
+
+#[test]
+fn invalid_selection_tracks_nothing() {
+    let sandbox = project();
+    
+    let output = discover(&sandbox, &[], "1 7\n");
+    
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("7 is not a number from 1 to 2"));
+    assert_eq!(tracked(&sandbox).len(), 1);
+}
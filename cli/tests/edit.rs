@@ -0,0 +1,60 @@
+mod common;
+
+use common::Sandbox;
+use std::process::{Output, Stdio};
+
+/// Editor that replaces the file's content with `edited`.
+const EDITOR: &str = "printf 'edited\\n' > \"$1\"\n";
+
+/// Run `mdman edit file` with `$EDITOR` running the shell `script`.
+fn edit(sandbox: &Sandbox, file: &str, script: &str) -> Output {
+    let editor = sandbox.write("editor.sh", script);
+    sandbox
+        .mdman()
+        .args(["edit", sandbox.path(file).to_str().unwrap()])
+        .env("EDITOR", format!("sh {}", editor.display()))
+        .stdin(Stdio::null())
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn edited_source_is_synced_to_its_destinations() {
+    let sandbox = Sandbox::new();
+    let source = sandbox.write("notes.md", "one\n");
+    sandbox.track(&source, &[&sandbox.path("a/notes.md"), &sandbox.path("b/notes.md")]);
+    
+    let output = edit(&sandbox, "notes.md", EDITOR);
+    
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(sandbox.read("notes.md"), "edited\n");
+    assert_eq!(sandbox.read("a/notes.md"), "edited\n");
+    assert_eq!(sandbox.read("b/notes.md"), "edited\n");
+}
+
+#[test]
+fn destination_opens_its_source_instead() {
+    let sandbox = Sandbox::new();
+    let source = sandbox.write("notes.md", "one\n");
+    sandbox.track(&source, &[&sandbox.path("a/notes.md")]);
+    
+    let output = edit(&sandbox, "a/notes.md", EDITOR);
+    
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("is a destination, editing its source"));
+    assert_eq!(sandbox.read("notes.md"), "edited\n");
+    assert_eq!(sandbox.read("a/notes.md"), "edited\n");
+}
+
+#[test]
+fn failing_editor_syncs_nothing() {
+    let sandbox = Sandbox::new();
+    let source = sandbox.write("notes.md", "one\n");
+    sandbox.track(&source, &[&sandbox.path("a/notes.md")]);
+    
+    // Saved, then quit with an error
+    let output = edit(&sandbox, "notes.md", &format!("{EDITOR}exit 1\n"));
+    
+    assert!(!output.status.success());
+    assert_eq!(sandbox.read("a/notes.md"), "one\n");
+}
@@ -0,0 +1,41 @@
+mod common;
+
+use common::Sandbox;
+
+#[test]
+fn gc_removes_only_mdmans_leftovers() {
+    let sandbox = Sandbox::new();
+    let source = sandbox.write("notes.md", "content\n");
+    sandbox.track(&source, &[&sandbox.path("copy/notes.md")]);
+    let temp = sandbox.write("copy/.notes.md.tmp-mdman", "partial");
+    let backup = sandbox.write("config/mdman/config.json.v0.bak", "{}");
+    // Look alike, but aren't named exactly like mdman's files
+    let user_files = [
+        sandbox.write("copy/notes.md.tmp-mdman", "mine"),
+        sandbox.write("copy/.tmp-mdman", "mine"),
+        sandbox.write("config/mdman/config.json.vX.bak", "mine"),
+    ];
+    
+    let output = sandbox.run(&["gc"]);
+    
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(!temp.exists());
+    assert!(!backup.exists());
+    for file in &user_files {
+        assert!(file.exists(), "{} was removed", file.display());
+    }
+    assert_eq!(sandbox.read("copy/notes.md"), "content\n");
+}
+
+#[test]
+fn gc_keeps_recent_files_with_older_than() {
+    let sandbox = Sandbox::new();
+    let source = sandbox.write("notes.md", "content\n");
+    sandbox.track(&source, &[&sandbox.path("copy/notes.md")]);
+    let temp = sandbox.write("copy/.notes.md.tmp-mdman", "partial");
+    
+    let output = sandbox.run(&["gc", "--older-than", "1h"]);
+    
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(temp.exists());
+}
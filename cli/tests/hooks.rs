@@ -0,0 +1,75 @@
+mod common;
+
+use common::Sandbox;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+
+fn install_hook(sandbox: &Sandbox, name: &str, script: &str) {
+    let path = sandbox.write(&format!("config/mdman/hooks/{name}"), &format!("#!/bin/sh\n{script}\n"));
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+}
+
+#[test]
+fn hooks_receive_paths_and_event() {
+    let sandbox = Sandbox::new();
+    let source = sandbox.write("notes.md", "one\n");
+    let destination = sandbox.path("copy/notes.md");
+    sandbox.track(&source, &[&destination]);
+    let env = sandbox.path("env");
+    install_hook(
+        &sandbox,
+        "post-sync",
+        &format!(r#"printf '%s\n%s\n%s\n' "$MDMAN_SOURCE" "$MDMAN_DEST" "$MDMAN_EVENT" > '{}'"#, env.display()),
+    );
+    sandbox.write("notes.md", "two\n");
+    
+    let output = sandbox.run(&["sync"]);
+    
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(
+        fs::read_to_string(env).unwrap(),
+        format!("{}\n{}\npost-sync\n", source.display(), destination.display())
+    );
+}
+
+#[test]
+fn failing_pre_sync_hook_vetoes_the_write() {
+    let sandbox = Sandbox::new();
+    let source = sandbox.write("notes.md", "one\n");
+    let destination = sandbox.path("copy/notes.md");
+    sandbox.track(&source, &[&destination]);
+    install_hook(&sandbox, "pre-sync", "exit 1");
+    sandbox.write("notes.md", "two\n");
+    
+    sandbox.run(&["sync"]);
+    
+    assert_eq!(sandbox.read("copy/notes.md"), "one\n");
+}
+
+#[test]
+fn passing_pre_sync_hook_allows_the_write() {
+    let sandbox = Sandbox::new();
+    let source = sandbox.write("notes.md", "one\n");
+    let destination = sandbox.path("copy/notes.md");
+    sandbox.track(&source, &[&destination]);
+    install_hook(&sandbox, "pre-sync", "exit 0");
+    sandbox.write("notes.md", "two\n");
+    
+    sandbox.run(&["sync"]);
+    
+    assert_eq!(sandbox.read("copy/notes.md"), "two\n");
+}
+
+#[test]
+fn post_desync_hook_runs_without_a_notification_daemon() {
+    let sandbox = Sandbox::new();
+    let source = sandbox.write("notes.md", "one\n");
+    let destination = sandbox.path("copy/notes.md");
+    sandbox.track(&source, &[&destination]);
+    let event = sandbox.path("event");
+    install_hook(&sandbox, "post-desync", &format!(r#"printf %s "$MDMAN_EVENT" > '{}'"#, event.display()));
+    
+    sandbox.watch_desync(&destination, &[]);
+    
+    assert_eq!(fs::read_to_string(event).unwrap(), "post-desync");
+}
@@ -0,0 +1,30 @@
+mod common;
+
+use common::Sandbox;
+
+#[test]
+fn dry_run_prints_the_plan_without_installing() {
+    let sandbox = Sandbox::new();
+    let unit = sandbox.path("config/systemd/user/mdman.service");
+    
+    let output = sandbox.run(&["install", "--dry-run"]);
+    
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains(&format!("Would write {}:", unit.display())), "{stdout}");
+    assert!(stdout.contains("ExecStart=/usr/local/bin/mdman watch"));
+    assert!(stdout.contains("systemctl --user enable mdman.service"));
+    assert!(stdout.contains("sudo cp"));
+    assert!(!unit.exists());
+}
+
+#[test]
+fn read_only_install_is_a_dry_run() {
+    let sandbox = Sandbox::new();
+    
+    let output = sandbox.run(&["--read-only", "install"]);
+    
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Would run:"));
+    assert!(!sandbox.path("config/systemd").exists());
+}
@@ -0,0 +1,90 @@
+mod common;
+
+use common::Sandbox;
+
+/// `list --print0` output split into its fields.
+fn listed(sandbox: &Sandbox) -> Vec<String> {
+    let output = sandbox.run(&["list", "--print0"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    stdout.strip_suffix('\0').unwrap_or(&stdout).split('\0').map(String::from).collect()
+}
+
+#[test]
+fn mappings_are_listed_in_sorted_order() {
+    let sandbox = Sandbox::new();
+    let b = sandbox.write("b.md", "b\n");
+    let a = sandbox.write("a.md", "a\n");
+    sandbox.track(&b, &[&sandbox.path("copy/b.md")]);
+    // Added to the config file out of order
+    sandbox.track(&a, &[&sandbox.path("copy/a2.md"), &sandbox.path("copy/a1.md")]);
+    
+    let path = |name: &str| sandbox.path(name).display().to_string();
+    assert_eq!(
+        listed(&sandbox),
+        [path("a.md"), path("copy/a1.md"), path("a.md"), path("copy/a2.md"), path("b.md"), path("copy/b.md")]
+    );
+}
+
+#[test]
+fn saved_destinations_are_sorted() {
+    let sandbox = Sandbox::new();
+    let a = sandbox.write("a.md", "a\n");
+    sandbox.track(&a, &[&sandbox.path("copy/a2.md"), &sandbox.path("copy/a1.md")]);
+    
+    // Any change saves the config again
+    assert!(sandbox.run(&["config", "set", "sync_threads", "2"]).status.success());
+    
+    let destinations = &sandbox.config()["mappings"][a.to_str().unwrap()]["destinations"];
+    assert_eq!(*destinations, serde_json::json!([sandbox.path("copy/a1.md"), sandbox.path("copy/a2.md")]));
+}
+
+#[test]
+fn descriptions_are_listed_and_can_be_cleared() {
+    let sandbox = Sandbox::new();
+    let source = sandbox.write("notes.md", "content\n");
+    sandbox.track(&source, &[&sandbox.path("copy/notes.md")]);
+    
+    let output = sandbox.run(&["describe", source.to_str().unwrap(), "Shared with the docs site"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let listing = sandbox.run(&["list"]);
+    assert!(String::from_utf8_lossy(&listing.stdout).contains("  Shared with the docs site"));
+    
+    assert!(sandbox.run(&["describe", source.to_str().unwrap()]).status.success());
+    assert!(sandbox.config()["mappings"][source.to_str().unwrap()].get("description").is_none());
+}
+
+#[test]
+fn describing_an_untracked_file_fails() {
+    let sandbox = Sandbox::new();
+    let source = sandbox.write("notes.md", "content\n");
+    sandbox.track(&sandbox.write("other.md", ""), &[&sandbox.path("copy/other.md")]);
+    
+    let output = sandbox.run(&["describe", source.to_str().unwrap(), "Nothing"]);
+    
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("is not a tracked source"));
+}
+
+#[test]
+fn print0_writes_only_nul_terminated_pairs() {
+    let sandbox = Sandbox::new();
+    let source = sandbox.write("with space.md", "a\n");
+    let destination = sandbox.path("copy/new\nline.md");
+    sandbox.track(&source, &[&destination]);
+    
+    let output = sandbox.run(&["list", "--print0"]);
+    
+    let expected = format!("{}\0{}\0", source.display(), destination.display());
+    assert_eq!(output.stdout, expected.as_bytes());
+}
+
+#[test]
+fn print0_conflicts_with_json() {
+    let sandbox = Sandbox::new();
+    
+    let output = sandbox.run(&["list", "--print0", "--json"]);
+    
+    assert_eq!(output.status.code(), Some(2));
+    assert!(output.stdout.is_empty());
+}
@@ -0,0 +1,59 @@
+mod common;
+
+use common::Sandbox;
+
+/// Track `notes.md` to `drive/notes.md` and redirect that to `temp/notes.md`.
+fn redirected() -> Sandbox {
+    let sandbox = Sandbox::new();
+    let source = sandbox.write("notes.md", "one\n");
+    sandbox.track(&source, &[&sandbox.path("drive/notes.md")]);
+    std::fs::create_dir_all(sandbox.path("temp")).unwrap();
+    let output = sandbox.run(&[
+        "redirect",
+        sandbox.path("drive/notes.md").to_str().unwrap(),
+        sandbox.path("temp/notes.md").to_str().unwrap(),
+    ]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    sandbox
+}
+
+#[test]
+fn sync_writes_to_the_redirect_target() {
+    let sandbox = redirected();
+    sandbox.write("notes.md", "two\n");
+    
+    assert!(sandbox.run(&["sync"]).status.success());
+    
+    assert_eq!(sandbox.read("temp/notes.md"), "two\n");
+    assert_eq!(sandbox.read("drive/notes.md"), "one\n");
+    // The mapping itself still lists the original destination
+    let source = sandbox.path("notes.md");
+    let destinations = &sandbox.config()["mappings"][source.to_str().unwrap()]["destinations"];
+    assert_eq!(*destinations, serde_json::json!([sandbox.path("drive/notes.md")]));
+}
+
+#[test]
+fn clearing_the_redirect_syncs_the_original_path_again() {
+    let sandbox = redirected();
+    sandbox.run(&["sync"]);
+    
+    let output = sandbox.run(&["redirect", "--clear", sandbox.path("drive/notes.md").to_str().unwrap()]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    sandbox.write("notes.md", "three\n");
+    assert!(sandbox.run(&["sync"]).status.success());
+    
+    assert_eq!(sandbox.read("drive/notes.md"), "three\n");
+    assert_eq!(sandbox.read("temp/notes.md"), "one\n");
+}
+
+#[test]
+fn tracked_files_cannot_be_redirect_targets() {
+    let sandbox = redirected();
+    let destination = sandbox.path("drive/notes.md");
+    
+    let output = sandbox.run(&["redirect", destination.to_str().unwrap(), sandbox.path("notes.md").to_str().unwrap()]);
+    
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("is already being tracked"));
+    assert_eq!(sandbox.read("notes.md"), "one\n");
+}
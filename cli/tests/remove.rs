@@ -0,0 +1,90 @@
+mod common;
+
+use common::Sandbox;
+
+#[test]
+fn remove_deletes_source_and_destinations_once_confirmed() {
+    let sandbox = Sandbox::new();
+    let source = sandbox.write("notes.md", "content\n");
+    let destination = sandbox.path("copy/notes.md");
+    sandbox.track(&source, &[&destination]);
+    
+    let output = sandbox.run_with_input(&["remove", source.to_str().unwrap()], "y\n");
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(!source.exists());
+    assert!(!destination.exists());
+}
+
+#[test]
+fn remove_is_blocked_in_safe_mode() {
+    let sandbox = Sandbox::new();
+    let source = sandbox.write("notes.md", "content\n");
+    let destination = sandbox.path("copy/notes.md");
+    sandbox.track(&source, &[&destination]);
+    sandbox.set_config("safe_mode", true.into());
+    
+    let output = sandbox.run_with_input(&["remove", source.to_str().unwrap()], "y\n");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("mdman untrack"));
+    assert!(source.exists());
+    assert!(destination.exists());
+}
+
+#[test]
+fn remove_is_blocked_by_the_safe_mode_variable() {
+    let sandbox = Sandbox::new();
+    let source = sandbox.write("notes.md", "content\n");
+    let destination = sandbox.path("copy/notes.md");
+    sandbox.track(&source, &[&destination]);
+    
+    let output = sandbox
+        .mdman()
+        .args(["remove", source.to_str().unwrap()])
+        .env("MDMAN_SAFE_MODE", "1")
+        .stdin(std::process::Stdio::null())
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(source.exists());
+    assert!(destination.exists());
+}
+
+/// Watch while `source` is deleted, long enough for the deletion to be told from a rename.
+fn watch_deletion(sandbox: &Sandbox, source: &std::path::Path) {
+    let watcher = sandbox
+        .mdman()
+        .args(["watch", "--exit-after", "5s"])
+        .stdout(std::process::Stdio::null())
+        .spawn()
+        .unwrap();
+    std::thread::sleep(std::time::Duration::from_millis(1500));
+    std::fs::remove_file(source).unwrap();
+    assert!(watcher.wait_with_output().unwrap().status.success());
+}
+
+#[test]
+fn deleted_source_keeps_its_tracking_in_safe_mode() {
+    let sandbox = Sandbox::new();
+    let source = sandbox.write("notes.md", "content\n");
+    let destination = sandbox.path("copy/notes.md");
+    sandbox.track(&source, &[&destination]);
+    sandbox.set_config("safe_mode", true.into());
+    
+    watch_deletion(&sandbox, &source);
+    
+    assert!(sandbox.config()["mappings"].get(source.to_str().unwrap()).is_some());
+    assert!(destination.exists());
+}
+
+#[test]
+fn deleted_source_loses_its_tracking() {
+    let sandbox = Sandbox::new();
+    let source = sandbox.write("notes.md", "content\n");
+    let destination = sandbox.path("copy/notes.md");
+    sandbox.track(&source, &[&destination]);
+    
+    watch_deletion(&sandbox, &source);
+    
+    assert!(sandbox.config()["mappings"].get(source.to_str().unwrap()).is_none());
+    assert!(destination.exists());
+}
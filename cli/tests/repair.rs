@@ -0,0 +1,48 @@
+mod common;
+
+use common::Sandbox;
+
+#[test]
+fn repairing_a_source_repairs_all_its_destinations() {
+    let sandbox = Sandbox::new();
+    let source = sandbox.write("notes.md", "source\n");
+    sandbox.track(&source, &[&sandbox.path("a/notes.md"), &sandbox.path("b/notes.md")]);
+    sandbox.write("a/notes.md", "edited\n");
+    sandbox.write("b/notes.md", "edited\n");
+    
+    let output = sandbox.run_with_input(&["repair", source.to_str().unwrap()], "y\n");
+    
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Repair complete: 2 files synced"));
+    assert_eq!(sandbox.read("a/notes.md"), "source\n");
+    assert_eq!(sandbox.read("b/notes.md"), "source\n");
+}
+
+#[test]
+fn repairing_a_destination_leaves_the_others_alone() {
+    let sandbox = Sandbox::new();
+    let source = sandbox.write("notes.md", "source\n");
+    let destination = sandbox.path("a/notes.md");
+    sandbox.track(&source, &[&destination, &sandbox.path("b/notes.md")]);
+    sandbox.write("a/notes.md", "edited\n");
+    sandbox.write("b/notes.md", "edited\n");
+    
+    let output = sandbox.run_with_input(&["repair", destination.to_str().unwrap()], "y\n");
+    
+    assert!(output.status.success());
+    assert_eq!(sandbox.read("a/notes.md"), "source\n");
+    assert_eq!(sandbox.read("b/notes.md"), "edited\n");
+}
+
+#[test]
+fn declining_the_prompt_repairs_nothing() {
+    let sandbox = Sandbox::new();
+    let source = sandbox.write("notes.md", "source\n");
+    sandbox.track(&source, &[&sandbox.path("a/notes.md")]);
+    sandbox.write("a/notes.md", "edited\n");
+    
+    let output = sandbox.run_with_input(&["repair", source.to_str().unwrap()], "n\n");
+    
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Cancelled"));
+    assert_eq!(sandbox.read("a/notes.md"), "edited\n");
+}
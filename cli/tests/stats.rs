@@ -0,0 +1,37 @@
+mod common;
+
+use common::Sandbox;
+use std::fs;
+
+#[test]
+fn stats_totals_a_fixture_config() {
+    let sandbox = Sandbox::new();
+    let a = sandbox.write("a.md", "0123456789");
+    sandbox.track(&a, &[&sandbox.path("x/a.md"), &sandbox.path("y/a.md"), &sandbox.path("z/a.md")]);
+    let b = sandbox.write("b.md", "01234");
+    sandbox.track(&b, &[&sandbox.path("x/b.md")]);
+    let c = sandbox.write("c.md", "gone");
+    sandbox.track(&c, &[&sandbox.path("x/c.md")]);
+    sandbox.write("y/a.md", "drifted");
+    fs::remove_file(sandbox.path("z/a.md")).unwrap();
+    fs::remove_file(&c).unwrap();
+    
+    let output = sandbox.run(&["stats", "--json"]);
+    
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let stats: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(
+        stats,
+        serde_json::json!({
+            "sources": 3,
+            "destinations": 5,
+            "aggregates": 0,
+            "source_bytes": 15,
+            "in_sync": 2,
+            "drifted": 1,
+            "missing": 1,
+            "missing_sources": 1,
+            "largest_fan_out": { "source": a, "destinations": 3 },
+        })
+    );
+}
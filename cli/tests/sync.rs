@@ -0,0 +1,399 @@
+mod common;
+
+use common::Sandbox;
+use std::fs;
+
+#[test]
+fn from_destination_propagates_to_source_and_siblings() {
+    let sandbox = Sandbox::new();
+    let source = sandbox.write("notes.md", "original\n");
+    let edited = sandbox.path("a/notes.md");
+    let sibling = sandbox.path("b/notes.md");
+    sandbox.track(&source, &[&edited, &sibling]);
+    sandbox.write("a/notes.md", "promoted\n");
+    
+    let output = sandbox.run_with_input(&["sync", "--from-destination", edited.to_str().unwrap()], "y\n");
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(sandbox.read("notes.md"), "promoted\n");
+    assert_eq!(sandbox.read("a/notes.md"), "promoted\n");
+    assert_eq!(sandbox.read("b/notes.md"), "promoted\n");
+}
+
+#[test]
+fn from_destination_changes_nothing_unless_confirmed() {
+    let sandbox = Sandbox::new();
+    let source = sandbox.write("notes.md", "original\n");
+    let edited = sandbox.path("a/notes.md");
+    let sibling = sandbox.path("b/notes.md");
+    sandbox.track(&source, &[&edited, &sibling]);
+    sandbox.write("a/notes.md", "promoted\n");
+    
+    let output = sandbox.run_with_input(&["sync", "--from-destination", edited.to_str().unwrap()], "n\n");
+    assert!(output.status.success());
+    assert_eq!(sandbox.read("notes.md"), "original\n");
+    assert_eq!(sandbox.read("b/notes.md"), "original\n");
+}
+
+#[test]
+fn mapped_sources_sync_like_read_ones() {
+    let sandbox = Sandbox::new();
+    let source = sandbox.write("large.md", "old\n");
+    let destination = sandbox.path("copy/large.md");
+    sandbox.track(&source, &[&destination]);
+    
+    let content: String = (0..20_000).map(|i| format!("line {i}\n")).collect();
+    sandbox.write("large.md", &content);
+    // Only sources nobody can write to are mapped
+    let mut permissions = fs::metadata(&source).unwrap().permissions();
+    permissions.set_readonly(true);
+    fs::set_permissions(&source, permissions).unwrap();
+    sandbox.set_config("mmap_threshold", 1024.into());
+    
+    let output = sandbox.run(&["sync"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(sandbox.read("copy/large.md"), content);
+}
+
+/// Track `count` sources whose destinations are directories, so writing any of them fails.
+fn unwritable_sources(sandbox: &Sandbox, count: usize) {
+    for i in 0..count {
+        let source = sandbox.write(&format!("notes{i}.md"), "one\n");
+        let destination = sandbox.path(&format!("copy/notes{i}.md"));
+        sandbox.track(&source, &[&destination]);
+        fs::remove_file(&destination).unwrap();
+        fs::create_dir(&destination).unwrap();
+        sandbox.write(&format!("notes{i}.md"), "two\n");
+    }
+}
+
+#[test]
+fn sync_fails_when_destinations_fail() {
+    let sandbox = Sandbox::new();
+    unwritable_sources(&sandbox, 2);
+    
+    let output = sandbox.run(&["sync"]);
+    
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("2 errors occurred"));
+}
+
+#[test]
+fn sync_stops_after_max_errors() {
+    let sandbox = Sandbox::new();
+    unwritable_sources(&sandbox, 3);
+    
+    let output = sandbox.run(&["sync", "--threads", "1", "--max-errors", "1"]);
+    
+    assert!(!output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Synchronization aborted after 1 errors"), "{stdout}");
+}
+
+/// Sync ten edited sources with `threads`, returning every destination's content.
+fn sync_with_threads(threads: &str) -> Vec<String> {
+    let sandbox = Sandbox::new();
+    let names: Vec<String> = (0..10).map(|i| format!("notes{i}.md")).collect();
+    for name in &names {
+        let source = sandbox.write(name, "old\n");
+        sandbox.track(&source, &[&sandbox.path(&format!("a/{name}")), &sandbox.path(&format!("b/{name}"))]);
+        sandbox.write(name, &format!("new {name}\n"));
+    }
+    
+    let output = sandbox.run(&["sync", "--threads", threads]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("20 files synced"));
+    
+    names
+        .iter()
+        .flat_map(|name| [sandbox.read(&format!("a/{name}")), sandbox.read(&format!("b/{name}"))])
+        .collect()
+}
+
+#[test]
+fn thread_count_does_not_change_the_result() {
+    let single = sync_with_threads("1");
+    
+    assert_eq!(single, sync_with_threads("4"));
+    assert_eq!(single[0], "new notes0.md\n");
+}
+
+#[test]
+fn hardlinked_destinations_are_skipped() {
+    let sandbox = Sandbox::new();
+    let source = sandbox.write("notes.md", "one\n");
+    let destination = sandbox.path("copy/notes.md");
+    sandbox.track(&source, &[&destination]);
+    fs::remove_file(&destination).unwrap();
+    fs::hard_link(&source, &destination).unwrap();
+    sandbox.write("notes.md", "two\n");
+    
+    let output = sandbox.run(&["sync"]);
+    
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("0 files synced"));
+    assert_eq!(sandbox.read("notes.md"), "two\n");
+    assert_eq!(sandbox.read("copy/notes.md"), "two\n");
+}
+
+#[test]
+fn only_syncs_matching_sources() {
+    let sandbox = Sandbox::new();
+    let docs = sandbox.write("docs/guide.md", "one\n");
+    let notes = sandbox.write("notes/todo.md", "one\n");
+    sandbox.track(&docs, &[&sandbox.path("copy/guide.md")]);
+    sandbox.track(&notes, &[&sandbox.path("copy/todo.md")]);
+    sandbox.write("docs/guide.md", "two\n");
+    sandbox.write("notes/todo.md", "two\n");
+    
+    let output = sandbox.run(&["sync", "--only", "docs/*.md"]);
+    
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(sandbox.read("copy/guide.md"), "two\n");
+    assert_eq!(sandbox.read("copy/todo.md"), "one\n");
+}
+
+#[test]
+fn durable_sync_writes_every_destination() {
+    let sandbox = Sandbox::new();
+    let source = sandbox.write("notes.md", "one\n");
+    sandbox.track(&source, &[&sandbox.path("a/notes.md"), &sandbox.path("b/notes.md")]);
+    sandbox.set_config("durable_writes", true.into());
+    sandbox.write("notes.md", "two\n");
+    
+    let output = sandbox.run(&["sync"]);
+    
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(sandbox.read("a/notes.md"), "two\n");
+    assert_eq!(sandbox.read("b/notes.md"), "two\n");
+}
+
+#[test]
+fn frontmatter_is_stripped_only_when_it_exists() {
+    let sandbox = Sandbox::new();
+    let with = sandbox.write("with.md", "old\n");
+    let without = sandbox.write("without.md", "old\n");
+    sandbox.track(&with, &[&sandbox.path("copy/with.md")]);
+    sandbox.track(&without, &[&sandbox.path("copy/without.md")]);
+    let mut config = sandbox.config();
+    for source in [&with, &without] {
+        config["mappings"][source.to_str().unwrap()]["strip_frontmatter"] = true.into();
+    }
+    sandbox.save_config(&config);
+    sandbox.write("with.md", "---\ntags: [draft]\n---\n# Title\n");
+    sandbox.write("without.md", "# Title\n");
+    
+    let output = sandbox.run(&["sync"]);
+    
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(sandbox.read("copy/with.md"), "# Title\n");
+    assert_eq!(sandbox.read("copy/without.md"), "# Title\n");
+    // Compared against the stripped source, so the copies count as in sync
+    assert_eq!(sandbox.run(&["check"]).status.code(), Some(0));
+}
+
+/// Track `notes.md` into `gone/notes.md`, then remove that directory and change the source.
+fn missing_parent(policy: &str) -> Sandbox {
+    let sandbox = Sandbox::new();
+    let source = sandbox.write("notes.md", "old\n");
+    sandbox.track(&source, &[&sandbox.path("gone/notes.md")]);
+    sandbox.set_config("on_missing_parent", policy.into());
+    fs::remove_dir_all(sandbox.path("gone")).unwrap();
+    sandbox.write("notes.md", "new\n");
+    sandbox
+}
+
+#[test]
+fn missing_parent_is_created_by_default() {
+    let sandbox = missing_parent("create");
+    
+    assert!(sandbox.run(&["sync"]).status.success());
+    
+    assert_eq!(sandbox.read("gone/notes.md"), "new\n");
+}
+
+#[test]
+fn missing_parent_fails_the_sync_under_error() {
+    let sandbox = missing_parent("error");
+    
+    let output = sandbox.run(&["sync"]);
+    
+    assert_eq!(output.status.code(), Some(1));
+    assert!(!sandbox.path("gone").exists());
+}
+
+#[test]
+fn missing_parent_is_retried_under_skip_and_queue() {
+    let sandbox = missing_parent("skip-and-queue");
+    
+    assert!(sandbox.run(&["sync"]).status.success());
+    assert!(!sandbox.path("gone").exists());
+    
+    fs::create_dir(sandbox.path("gone")).unwrap();
+    assert!(sandbox.run(&["sync"]).status.success());
+    assert_eq!(sandbox.read("gone/notes.md"), "new\n");
+}
+
+#[test]
+fn report_has_an_entry_per_destination() {
+    let sandbox = Sandbox::new();
+    unwritable_sources(&sandbox, 1);
+    let source = sandbox.write("good.md", "one\n");
+    sandbox.track(&source, &[&sandbox.path("a/good.md"), &sandbox.path("b/good.md")]);
+    sandbox.write("good.md", "three\n");
+    
+    let output = sandbox.run(&["sync", "--report", sandbox.path("report.json").to_str().unwrap()]);
+    
+    assert!(!output.status.success());
+    let report: serde_json::Value = serde_json::from_str(&sandbox.read("report.json")).unwrap();
+    assert_eq!(report["synced_count"], 2);
+    assert_eq!(report["error_count"], 1);
+    let entries: Vec<_> = report["destinations"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|entry| (entry["destination"].as_str().unwrap().to_string(), entry["status"].clone(), entry["bytes_written"].clone()))
+        .collect();
+    let path = |name: &str| sandbox.path(name).display().to_string();
+    assert_eq!(
+        entries,
+        [
+            (path("a/good.md"), "synced".into(), 6.into()),
+            (path("b/good.md"), "synced".into(), 6.into()),
+            (path("copy/notes0.md"), "failed".into(), 0.into()),
+        ]
+    );
+    assert!(report["destinations"][2]["message"].is_string());
+}
+
+#[test]
+fn json_lines_streams_one_object_per_destination() {
+    let sandbox = Sandbox::new();
+    unwritable_sources(&sandbox, 1);
+    let source = sandbox.write("good.md", "one\n");
+    sandbox.track(&source, &[&sandbox.path("a/good.md"), &sandbox.path("b/good.md")]);
+    sandbox.write("good.md", "two\n");
+    
+    let output = sandbox.run(&["sync", "--json-lines"]);
+    
+    assert!(!output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let mut outcomes: Vec<(String, String)> = stdout
+        .lines()
+        .map(|line| {
+            let outcome: serde_json::Value = serde_json::from_str(line).unwrap();
+            (outcome["destination"].as_str().unwrap().to_string(), outcome["status"].as_str().unwrap().to_string())
+        })
+        .collect();
+    outcomes.sort();
+    let path = |name: &str| sandbox.path(name).display().to_string();
+    assert_eq!(
+        outcomes,
+        [
+            (path("a/good.md"), "synced".to_string()),
+            (path("b/good.md"), "synced".to_string()),
+            (path("copy/notes0.md"), "failed".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn sync_records_when_each_destination_was_written() {
+    let sandbox = Sandbox::new();
+    let source = sandbox.write("notes.md", "one\n");
+    let destination = sandbox.path("copy/notes.md");
+    sandbox.track(&source, &[&destination]);
+    sandbox.write("notes.md", "two\n");
+    
+    assert!(sandbox.run(&["sync"]).status.success());
+    
+    let state: serde_json::Value = serde_json::from_str(&sandbox.read("config/mdman/sync-state.json")).unwrap();
+    let record = &state["destinations"][destination.to_str().unwrap()];
+    assert_eq!(record["source"], source.to_str().unwrap());
+    assert!(record["synced_at"].is_object());
+    let whose = sandbox.run(&["whose", destination.to_str().unwrap()]);
+    assert!(String::from_utf8_lossy(&whose.stdout).contains("in sync, synced "));
+}
+
+/// A tracked source that was deleted, leaving `copy/notes.md` behind.
+fn orphaned_destination() -> Sandbox {
+    let sandbox = Sandbox::new();
+    let source = sandbox.write("notes.md", "one\n");
+    sandbox.track(&source, &[&sandbox.path("copy/notes.md")]);
+    fs::remove_file(&source).unwrap();
+    sandbox
+}
+
+#[test]
+fn orphaned_destinations_are_kept_without_delete() {
+    let sandbox = orphaned_destination();
+    
+    sandbox.run(&["sync"]);
+    
+    assert!(sandbox.path("copy/notes.md").exists());
+}
+
+#[test]
+fn delete_removes_orphaned_destinations_once_confirmed() {
+    let sandbox = orphaned_destination();
+    
+    let output = sandbox.run_with_input(&["sync", "--delete"], "y\n");
+    
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Deleted"));
+    assert!(!sandbox.path("copy/notes.md").exists());
+    assert!(sandbox.config()["mappings"].as_object().unwrap().is_empty());
+}
+
+#[test]
+fn delete_keeps_orphaned_destinations_when_declined() {
+    let sandbox = orphaned_destination();
+    
+    sandbox.run_with_input(&["sync", "--delete"], "n\n");
+    
+    assert!(sandbox.path("copy/notes.md").exists());
+}
+
+#[test]
+fn timings_cover_every_read_and_write() {
+    let sandbox = Sandbox::new();
+    let source = sandbox.write("notes.md", "one\n");
+    sandbox.track(&source, &[&sandbox.path("a/notes.md"), &sandbox.path("b/notes.md")]);
+    sandbox.write("notes.md", "two\n");
+    
+    let output = sandbox.run(&["sync", "--timings", "--report", sandbox.path("report.json").to_str().unwrap()]);
+    
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Timings:") && stdout.contains("Total: reading"), "{stdout}");
+    let report: serde_json::Value = serde_json::from_str(&sandbox.read("report.json")).unwrap();
+    let mut timings: Vec<(String, String)> = report["timings"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .inspect(|timing| assert!(timing["ms"].as_f64().unwrap() >= 0.0))
+        .map(|timing| (timing["phase"].as_str().unwrap().to_string(), timing["path"].as_str().unwrap().to_string()))
+        .collect();
+    timings.sort();
+    let path = |name: &str| sandbox.path(name).display().to_string();
+    assert_eq!(
+        timings,
+        [
+            ("read".into(), path("notes.md")),
+            ("write".into(), path("a/notes.md")),
+            ("write".into(), path("b/notes.md")),
+        ]
+    );
+}
+
+#[test]
+fn timings_are_left_out_unless_asked_for() {
+    let sandbox = Sandbox::new();
+    let source = sandbox.write("notes.md", "one\n");
+    sandbox.track(&source, &[&sandbox.path("a/notes.md")]);
+    
+    let output = sandbox.run(&["sync", "--report", sandbox.path("report.json").to_str().unwrap()]);
+    
+    assert!(!String::from_utf8_lossy(&output.stdout).contains("Timings:"));
+    let report: serde_json::Value = serde_json::from_str(&sandbox.read("report.json")).unwrap();
+    assert!(report.get("timings").is_none());
+}
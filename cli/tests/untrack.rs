@@ -0,0 +1,76 @@
+mod common;
+
+use common::Sandbox;
+use std::io::Write;
+use std::process::Stdio;
+
+/// Track `drafts/a.md`, `drafts/b.md` and `notes/c.md`, returning the sandbox.
+fn three_sources() -> Sandbox {
+    let sandbox = Sandbox::new();
+    for name in ["drafts/a.md", "drafts/b.md", "notes/c.md"] {
+        let source = sandbox.write(name, "content\n");
+        sandbox.track(&source, &[&sandbox.path(&format!("copy/{name}"))]);
+    }
+    sandbox
+}
+
+fn tracked(sandbox: &Sandbox) -> Vec<String> {
+    let config = sandbox.config();
+    config["mappings"].as_object().map(|mappings| mappings.keys().cloned().collect()).unwrap_or_default()
+}
+
+#[test]
+fn all_untracks_every_source_once_confirmed() {
+    let sandbox = three_sources();
+    
+    let output = sandbox.run_with_input(&["untrack", "--all"], "y\n");
+    
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains(&sandbox.path("notes/c.md").display().to_string()), "{stdout}");
+    assert!(stdout.contains("Stopped tracking 3 files"), "{stdout}");
+    assert!(tracked(&sandbox).is_empty());
+    // Only the tracking goes, the files stay
+    assert!(sandbox.path("copy/notes/c.md").exists());
+}
+
+#[test]
+fn glob_untracks_only_matching_sources() {
+    let sandbox = three_sources();
+    
+    let output = sandbox.run_with_input(&["untrack", "--glob", "drafts/*.md"], "y\n");
+    
+    assert!(output.status.success());
+    assert_eq!(tracked(&sandbox), [sandbox.path("notes/c.md").display().to_string()]);
+}
+
+#[test]
+fn declining_untracks_nothing() {
+    let sandbox = three_sources();
+    
+    let output = sandbox.run_with_input(&["untrack", "--all"], "n\n");
+    
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Cancelled"));
+    assert_eq!(tracked(&sandbox).len(), 3);
+}
+
+#[test]
+fn source_given_by_a_relative_path_is_untracked() {
+    let sandbox = three_sources();
+    
+    let mut untrack = sandbox
+        .mdman()
+        .args(["untrack", "notes/c.md"])
+        .current_dir(sandbox.path(""))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    untrack.stdin.take().unwrap().write_all(b"y\n").unwrap();
+    let output = untrack.wait_with_output().unwrap();
+    
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(tracked(&sandbox).len(), 2);
+    assert!(!tracked(&sandbox).contains(&sandbox.path("notes/c.md").display().to_string()));
+}
@@ -0,0 +1,446 @@
+mod common;
+
+use common::Sandbox;
+use std::process::Stdio;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+#[test]
+fn dry_run_leaves_destinations_untouched() {
+    let sandbox = Sandbox::new();
+    let source = sandbox.write("notes.md", "one\n");
+    let destination = sandbox.path("copy/notes.md");
+    sandbox.track(&source, &[&destination]);
+    // Sync notifications can't be shown without a desktop session
+    sandbox.set_config("notifications", serde_json::json!({ "sync_mode": "none" }));
+    
+    let watcher = sandbox
+        .mdman()
+        .args(["watch", "--dry-run", "--exit-after-events", "2", "--exit-after", "20s"])
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    sleep(Duration::from_millis(1500));
+    sandbox.write("notes.md", "two\n");
+    sleep(Duration::from_millis(1500));
+    // Still compared against what the destination has, not the skipped edit before
+    sandbox.write("notes.md", "three\n");
+    let output = watcher.wait_with_output().unwrap();
+    
+    let summary = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success());
+    assert!(summary.contains("2 files would be synced, 0 desyncs, 0 errors"), "{summary}");
+    assert_eq!(sandbox.read("copy/notes.md"), "one\n");
+}
+
+#[test]
+fn desync_runs_on_desync_with_paths_substituted() {
+    let sandbox = Sandbox::new();
+    let source = sandbox.write("notes.md", "one\n");
+    let destination = sandbox.path("copy/notes.md");
+    sandbox.track(&source, &[&destination]);
+    let marker = sandbox.path("marker");
+    sandbox.set_config(
+        "on_desync",
+        format!("printf '%s %s' {{source}} {{destination}} > '{}'", marker.display()).into(),
+    );
+    
+    // No notification daemon here, the command must run anyway
+    let summary = sandbox.watch_desync(&destination, &[]);
+    
+    assert!(summary.contains("1 desyncs, 0 errors"), "{summary}");
+    let expected = format!("{} {}", source.display(), destination.display());
+    assert_eq!(sandbox.wait_for("marker").as_deref(), Some(expected.as_str()));
+}
+
+#[test]
+fn mapping_on_desync_takes_precedence() {
+    let sandbox = Sandbox::new();
+    let source = sandbox.write("notes.md", "one\n");
+    let destination = sandbox.path("copy/notes.md");
+    sandbox.track(&source, &[&destination]);
+    let mut config = sandbox.config();
+    config["on_desync"] = format!("touch '{}'", sandbox.path("global").display()).into();
+    config["mappings"][source.to_str().unwrap()]["on_desync"] =
+        format!("printf %s {{dest}} > '{}'", sandbox.path("mapping").display()).into();
+    sandbox.save_config(&config);
+    
+    let flag = sandbox.path("flag");
+    sandbox.watch_desync(&destination, &["--exec-on-desync", &format!("touch '{}'", flag.display())]);
+    
+    assert_eq!(sandbox.wait_for("mapping").as_deref(), Some(destination.to_str().unwrap()));
+    assert!(!sandbox.path("global").exists());
+    assert!(!flag.exists());
+}
+
+#[test]
+fn read_only_does_not_run_on_desync() {
+    let sandbox = Sandbox::new();
+    let source = sandbox.write("notes.md", "one\n");
+    let destination = sandbox.path("copy/notes.md");
+    sandbox.track(&source, &[&destination]);
+    let marker = sandbox.path("marker");
+    sandbox.set_config("on_desync", format!("touch '{}'", marker.display()).into());
+    
+    let summary = sandbox.watch_desync(&destination, &["--read-only"]);
+    
+    assert!(summary.contains(&format!("Would run touch '{}'", marker.display())), "{summary}");
+    assert_eq!(sandbox.wait_for("marker"), None);
+}
+
+#[test]
+fn dry_run_does_not_run_on_desync() {
+    let sandbox = Sandbox::new();
+    let source = sandbox.write("notes.md", "one\n");
+    let destination = sandbox.path("copy/notes.md");
+    sandbox.track(&source, &[&destination]);
+    let marker = sandbox.path("marker");
+    
+    let command = format!("touch '{}'", marker.display());
+    let summary = sandbox.watch_desync(&destination, &["--dry-run", "--exec-on-desync", &command]);
+    
+    assert!(summary.contains("1 desyncs"), "{summary}");
+    assert_eq!(sandbox.wait_for("marker"), None);
+}
+
+#[test]
+fn burst_of_edits_is_synced_once_with_a_batch_window() {
+    let sandbox = Sandbox::new();
+    let source = sandbox.write("notes.md", "one\n");
+    let destination = sandbox.path("copy/notes.md");
+    sandbox.track(&source, &[&destination]);
+    sandbox.set_config("notifications", serde_json::json!({ "sync_mode": "none" }));
+    
+    let watcher = sandbox
+        .mdman()
+        .args(["watch", "--batch-window", "300", "--exit-after", "5s"])
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    sleep(Duration::from_millis(1500));
+    for i in 0..5 {
+        sandbox.write("notes.md", &format!("edit {i}\n"));
+        sleep(Duration::from_millis(20));
+    }
+    let output = watcher.wait_with_output().unwrap();
+    
+    let summary = String::from_utf8_lossy(&output.stdout);
+    assert!(summary.contains("1 files synced"), "{summary}");
+    assert_eq!(sandbox.read("copy/notes.md"), "edit 4\n");
+}
+
+#[test]
+fn state_file_describes_the_watcher_without_file_contents() {
+    let sandbox = Sandbox::new();
+    let source = sandbox.write("notes.md", "secret one\n");
+    let destination = sandbox.path("copy/notes.md");
+    sandbox.track(&source, &[&destination]);
+    sandbox.set_config("notifications", serde_json::json!({ "sync_mode": "none" }));
+    let state_file = sandbox.path("state.json");
+    
+    let watcher = sandbox
+        .mdman()
+        .args(["watch", "--exit-after", "4s", "--state-file", state_file.to_str().unwrap()])
+        .stdout(Stdio::null())
+        .spawn()
+        .unwrap();
+    sleep(Duration::from_millis(1500));
+    sandbox.write("notes.md", "secret two\n");
+    assert!(watcher.wait_with_output().unwrap().status.success());
+    
+    let dump = sandbox.read("state.json");
+    let state: serde_json::Value = serde_json::from_str(&dump).unwrap();
+    assert_eq!(state["reverse_mappings"][destination.to_str().unwrap()], source.to_str().unwrap());
+    assert!(state["watched"].as_array().is_some_and(|watched| !watched.is_empty()), "{dump}");
+    assert!(!dump.contains("secret"));
+}
+
+#[cfg(feature = "control")]
+#[test]
+fn control_socket_reports_status() {
+    use std::io::{BufRead, BufReader, Write};
+    use std::os::unix::net::UnixStream;
+    
+    let sandbox = Sandbox::new();
+    let source = sandbox.write("notes.md", "one\n");
+    sandbox.track(&source, &[&sandbox.path("copy/notes.md")]);
+    let socket = sandbox.path("control.sock");
+    sandbox.set_config("control_socket", socket.to_str().unwrap().into());
+    
+    let watcher = sandbox
+        .mdman()
+        .args(["watch", "--exit-after", "4s"])
+        .stdout(Stdio::null())
+        .spawn()
+        .unwrap();
+    sleep(Duration::from_millis(1500));
+    let mut stream = UnixStream::connect(&socket).unwrap();
+    writeln!(stream, r#"{{"cmd":"status"}}"#).unwrap();
+    writeln!(stream, r#"{{"cmd":"explode"}}"#).unwrap();
+    let mut replies = BufReader::new(stream).lines();
+    let status: serde_json::Value = serde_json::from_str(&replies.next().unwrap().unwrap()).unwrap();
+    let invalid: serde_json::Value = serde_json::from_str(&replies.next().unwrap().unwrap()).unwrap();
+    assert!(watcher.wait_with_output().unwrap().status.success());
+    
+    assert_eq!(status["ok"], true);
+    assert_eq!(status["mappings"], 1);
+    assert_eq!(invalid["ok"], false);
+}
+
+#[test]
+fn watch_config_only_survives_bad_configs_and_syncs_nothing() {
+    let sandbox = Sandbox::new();
+    let source = sandbox.write("notes.md", "one\n");
+    sandbox.track(&source, &[&sandbox.path("copy/notes.md")]);
+    
+    let mut watcher = sandbox
+        .mdman()
+        .args(["watch", "--watch-config-only"])
+        .stdout(Stdio::null())
+        .spawn()
+        .unwrap();
+    sleep(Duration::from_millis(1000));
+    sandbox.write("notes.md", "two\n");
+    std::fs::write(sandbox.config_file(), "{").unwrap();
+    sleep(Duration::from_millis(1000));
+    
+    let still_running = watcher.try_wait().unwrap().is_none();
+    watcher.kill().unwrap();
+    watcher.wait().unwrap();
+    assert!(still_running);
+    assert_eq!(sandbox.read("copy/notes.md"), "one\n");
+}
+
+#[test]
+fn queued_destination_is_written_once_its_directory_exists() {
+    let sandbox = Sandbox::new();
+    let source = sandbox.write("notes.md", "one\n");
+    sandbox.track(&source, &[&sandbox.path("gone/notes.md")]);
+    let other = sandbox.write("other.md", "one\n");
+    sandbox.track(&other, &[&sandbox.path("copy/other.md")]);
+    sandbox.set_config("on_missing_parent", "skip-and-queue".into());
+    sandbox.set_config("notifications", serde_json::json!({ "sync_mode": "none" }));
+    std::fs::remove_dir_all(sandbox.path("gone")).unwrap();
+    
+    let watcher = sandbox
+        .mdman()
+        .args(["watch", "--exit-after", "5s"])
+        .stdout(Stdio::null())
+        .spawn()
+        .unwrap();
+    sleep(Duration::from_millis(1500));
+    sandbox.write("notes.md", "two\n");
+    sleep(Duration::from_millis(1000));
+    assert!(!sandbox.path("gone").exists());
+    
+    // Any later event retries the queued destination
+    std::fs::create_dir(sandbox.path("gone")).unwrap();
+    sandbox.write("other.md", "two\n");
+    assert!(watcher.wait_with_output().unwrap().status.success());
+    
+    assert_eq!(sandbox.read("gone/notes.md"), "two\n");
+}
+
+/// Run a watcher with `detect_renames` while `mv` moves `copy/notes.md` to `copy/moved.md`.
+fn assert_move_is_followed(mv: impl FnOnce(&Sandbox)) {
+    let sandbox = Sandbox::new();
+    let source = sandbox.write("notes.md", "one\n");
+    sandbox.track(&source, &[&sandbox.path("copy/notes.md")]);
+    sandbox.set_config("detect_renames", true.into());
+    
+    let watcher = sandbox
+        .mdman()
+        .args(["watch", "--exit-after", "4s"])
+        .stdout(Stdio::null())
+        .spawn()
+        .unwrap();
+    sleep(Duration::from_millis(1500));
+    mv(&sandbox);
+    assert!(watcher.wait_with_output().unwrap().status.success());
+    
+    let destinations = &sandbox.config()["mappings"][source.to_str().unwrap()]["destinations"];
+    assert_eq!(*destinations, serde_json::json!([sandbox.path("copy/moved.md")]));
+    assert_eq!(sandbox.read("copy/moved.md"), "one\n");
+}
+
+#[test]
+fn renamed_destination_is_followed() {
+    assert_move_is_followed(|sandbox| std::fs::rename(sandbox.path("copy/notes.md"), sandbox.path("copy/moved.md")).unwrap());
+}
+
+#[test]
+fn removed_and_recreated_destination_is_followed() {
+    assert_move_is_followed(|sandbox| {
+        std::fs::remove_file(sandbox.path("copy/notes.md")).unwrap();
+        sandbox.write("copy/moved.md", "one\n");
+    });
+}
+
+#[test]
+fn dedupe_destinations_collapses_duplicates_at_startup() {
+    let sandbox = Sandbox::new();
+    let source = sandbox.write("notes.md", "one\n");
+    let destination = sandbox.path("copy/notes.md");
+    sandbox.track(&source, &[&destination]);
+    let mut config = sandbox.config();
+    config["mappings"][source.to_str().unwrap()]["destinations"] = serde_json::json!([destination, destination]);
+    sandbox.save_config(&config);
+    
+    let output = sandbox.run(&["watch", "--dedupe-destinations", "--exit-after", "1s"]);
+    
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Removed 1 duplicate destinations"));
+    assert_eq!(sandbox.config()["mappings"][source.to_str().unwrap()]["destinations"], serde_json::json!([destination]));
+}
+
+#[test]
+fn exit_after_stops_an_idle_watcher_with_a_summary() {
+    let sandbox = Sandbox::new();
+    let source = sandbox.write("notes.md", "one\n");
+    sandbox.track(&source, &[&sandbox.path("copy/notes.md")]);
+    
+    let started = Instant::now();
+    let output = sandbox.run(&["watch", "--exit-after", "1s"]);
+    
+    assert!(output.status.success());
+    assert!(started.elapsed() < Duration::from_secs(10));
+    let summary = String::from_utf8_lossy(&output.stdout);
+    assert!(summary.contains("Watched for 1s: 0 events, 0 files synced, 0 desyncs, 0 errors"), "{summary}");
+}
+
+#[test]
+fn exit_after_events_stops_once_enough_events_happened() {
+    let sandbox = Sandbox::new();
+    let source = sandbox.write("notes.md", "one\n");
+    let destination = sandbox.path("copy/notes.md");
+    sandbox.track(&source, &[&destination]);
+    
+    let started = Instant::now();
+    let summary = sandbox.watch_desync(&destination, &[]);
+    
+    // Well before the 20 second limit `watch_desync` sets
+    assert!(started.elapsed() < Duration::from_secs(10));
+    assert!(summary.contains("1 events"), "{summary}");
+}
+
+#[cfg(unix)]
+#[test]
+fn follow_renames_renames_destinations_with_their_source() {
+    use std::os::unix::fs::MetadataExt;
+    
+    let sandbox = Sandbox::new();
+    let source = sandbox.write("notes.md", "one\n");
+    sandbox.track(&source, &[&sandbox.path("copy/notes.md")]);
+    sandbox.set_config("notifications", serde_json::json!({ "sync_mode": "none" }));
+    let inode = std::fs::metadata(sandbox.path("copy/notes.md")).unwrap().ino();
+    
+    let watcher = sandbox
+        .mdman()
+        .args(["watch", "--follow-renames", "--exit-after", "4s"])
+        .stdout(Stdio::null())
+        .spawn()
+        .unwrap();
+    sleep(Duration::from_millis(1500));
+    std::fs::rename(&source, sandbox.path("renamed.md")).unwrap();
+    assert!(watcher.wait_with_output().unwrap().status.success());
+    
+    let renamed = sandbox.path("renamed.md");
+    let config = sandbox.config();
+    assert!(config["mappings"].get(source.to_str().unwrap()).is_none());
+    assert_eq!(config["mappings"][renamed.to_str().unwrap()]["destinations"], serde_json::json!([sandbox.path("copy/renamed.md")]));
+    assert!(!sandbox.path("copy/notes.md").exists());
+    // Renamed rather than written again
+    assert_eq!(std::fs::metadata(sandbox.path("copy/renamed.md")).unwrap().ino(), inode);
+}
+
+#[test]
+fn sync_notifications_off_still_syncs() {
+    let sandbox = Sandbox::new();
+    let source = sandbox.write("notes.md", "one\n");
+    let destination = sandbox.path("copy/notes.md");
+    sandbox.track(&source, &[&destination]);
+    // Showing one would fail here, without a desktop session
+    sandbox.set_config("notifications", serde_json::json!({ "sync_mode": "none" }));
+    
+    let watcher = sandbox
+        .mdman()
+        .args(["watch", "--exit-after-events", "1", "--exit-after", "20s"])
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    sleep(Duration::from_millis(1500));
+    sandbox.write("notes.md", "two\n");
+    let output = watcher.wait_with_output().unwrap();
+    
+    let summary = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success());
+    assert!(summary.contains("1 files synced, 0 desyncs, 0 errors"), "{summary}");
+    assert_eq!(sandbox.read("copy/notes.md"), "two\n");
+}
+
+#[test]
+fn destination_created_after_startup_is_checked_with_watch_new_destinations() {
+    let sandbox = Sandbox::new();
+    let source = sandbox.write("notes.md", "one\n");
+    let destination = sandbox.path("copy/notes.md");
+    sandbox.track(&source, &[&destination]);
+    std::fs::remove_file(&destination).unwrap();
+    
+    let summary = sandbox.watch_desync(&destination, &["--watch-new-destinations"]);
+    
+    assert!(summary.contains("1 desyncs, 0 errors"), "{summary}");
+}
+
+#[test]
+fn debounce_override_delays_only_its_source() {
+    let sandbox = Sandbox::new();
+    let slow = sandbox.write("slow.md", "one\n");
+    let quick = sandbox.write("quick.md", "one\n");
+    sandbox.track(&slow, &[&sandbox.path("copy/slow.md")]);
+    sandbox.track(&quick, &[&sandbox.path("copy/quick.md")]);
+    sandbox.set_config("notifications", serde_json::json!({ "sync_mode": "none" }));
+    sandbox.set_config("debounce_overrides", serde_json::json!({ slow.to_str().unwrap(): 3000 }));
+    
+    let watcher = sandbox
+        .mdman()
+        .args(["watch", "--exit-after-events", "2", "--exit-after", "20s"])
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    sleep(Duration::from_millis(1500));
+    sandbox.write("slow.md", "two\n");
+    sandbox.write("quick.md", "two\n");
+    sleep(Duration::from_millis(1500));
+    
+    assert_eq!(sandbox.read("copy/quick.md"), "two\n");
+    assert_eq!(sandbox.read("copy/slow.md"), "one\n");
+    assert!(watcher.wait_with_output().unwrap().status.success());
+    assert_eq!(sandbox.read("copy/slow.md"), "two\n");
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn nice_and_io_class_are_applied_to_the_watcher() {
+    let sandbox = Sandbox::new();
+    let source = sandbox.write("notes.md", "one\n");
+    sandbox.track(&source, &[&sandbox.path("copy/notes.md")]);
+    
+    let mut watcher = sandbox
+        .mdman()
+        .args(["watch", "--nice", "19", "--io-class", "idle", "--exit-after", "3s"])
+        .stdout(Stdio::null())
+        .spawn()
+        .unwrap();
+    sleep(Duration::from_millis(1000));
+    let pid = watcher.id();
+    // Niceness is field 19 of the stat line, the 17th after the command's name
+    let stat = std::fs::read_to_string(format!("/proc/{pid}/stat")).unwrap();
+    let fields: Vec<&str> = stat.rsplit_once(") ").unwrap().1.split(' ').collect();
+    const IOPRIO_WHO_PROCESS: libc::c_int = 1;
+    let ioprio = unsafe { libc::syscall(libc::SYS_ioprio_get, IOPRIO_WHO_PROCESS, pid) };
+    assert!(watcher.wait().unwrap().success());
+    
+    assert_eq!(fields[16], "19");
+    // Idle is class 3, kept in the bits above the level
+    assert_eq!(ioprio >> 13, 3);
+}
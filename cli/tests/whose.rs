@@ -0,0 +1,55 @@
+mod common;
+
+use common::Sandbox;
+
+fn whose(sandbox: &Sandbox, name: &str) -> String {
+    let output = sandbox.run(&["whose", sandbox.path(name).to_str().unwrap()]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+#[test]
+fn source_lists_its_destinations_with_their_state() {
+    let sandbox = Sandbox::new();
+    let source = sandbox.write("notes.md", "one\n");
+    sandbox.track(&source, &[&sandbox.path("a/notes.md"), &sandbox.path("b/notes.md")]);
+    sandbox.write("b/notes.md", "edited\n");
+    
+    let output = whose(&sandbox, "notes.md");
+    
+    assert!(output.contains("is a tracked source with 2 destination(s)"), "{output}");
+    assert!(output.contains(&format!("→ {} (in sync", sandbox.path("a/notes.md").display())), "{output}");
+    assert!(output.contains(&format!("→ {} (differs", sandbox.path("b/notes.md").display())), "{output}");
+}
+
+#[test]
+fn destination_names_its_source() {
+    let sandbox = Sandbox::new();
+    let source = sandbox.write("notes.md", "one\n");
+    sandbox.track(&source, &[&sandbox.path("a/notes.md")]);
+    
+    let output = whose(&sandbox, "a/notes.md");
+    
+    assert!(output.contains("is a destination of:"), "{output}");
+    assert!(output.contains(&format!("← {} (in sync", source.display())), "{output}");
+}
+
+#[test]
+fn untracked_file_is_reported_as_such() {
+    let sandbox = Sandbox::new();
+    sandbox.write("loose.md", "one\n");
+    
+    assert!(whose(&sandbox, "loose.md").ends_with("is not tracked\n"));
+}
+
+#[test]
+fn relative_and_absolute_paths_resolve_to_the_same_source() {
+    let sandbox = Sandbox::new();
+    let source = sandbox.write("notes/today.md", "one\n");
+    sandbox.track(&source, &[&sandbox.path("a/today.md")]);
+    
+    let relative = sandbox.mdman().args(["whose", "today.md"]).current_dir(sandbox.path("notes")).output().unwrap();
+    
+    assert!(relative.status.success());
+    assert_eq!(String::from_utf8_lossy(&relative.stdout), whose(&sandbox, "notes/today.md"));
+}
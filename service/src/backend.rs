@@ -0,0 +1,172 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::{Arc, OnceLock, PoisonError, RwLock};
+use tracing::warn;
+
+use crate::fsutil;
+
+/// Where destination content is written to and read back from.
+///
+/// Destinations written as `scheme://...` are handled by the backend registered for that
+/// scheme with [`register`]; every other destination is a local file handled by [`LocalBackend`].
+pub trait DestinationBackend: Send + Sync {
+    /// Replace the content of `path`. `durable` asks for it to survive a crash right after.
+    fn write(&self, path: &Path, content: &[u8], durable: bool) -> io::Result<()>;
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>>;
+    fn exists(&self, path: &Path) -> bool;
+}
+
+/// Destinations on the local filesystem.
+pub struct LocalBackend;
+
+impl DestinationBackend for LocalBackend {
+    fn write(&self, path: &Path, content: &[u8], durable: bool) -> io::Result<()> {
+        fsutil::ensure_regular_file(path)?;
+        fsutil::write_file(path, content, durable)
+    }
+    
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        fsutil::ensure_regular_file(path)?;
+        fs::read(path)
+    }
+    
+    fn exists(&self, path: &Path) -> bool {
+        path.exists()
+    }
+}
+
+/// Write `content` to `path` with `backend`. With `verify` it is read back afterwards and
+/// written again if it doesn't match, see [`crate::Config::verify_after_write`].
+pub fn write_verified(backend: &dyn DestinationBackend, path: &Path, content: &[u8], durable: bool, verify: bool) -> io::Result<()> {
+    // Nothing is written in read-only mode, so there is nothing to read back either
+    if !verify || fsutil::read_only() {
+        return backend.write(path, content, durable);
+    }
+    
+    for attempt in 1..=fsutil::VERIFY_ATTEMPTS {
+        backend.write(path, content, durable)?;
+        if backend.read(path)? == content {
+            return Ok(());
+        }
+        warn!("{} read back differently than written (attempt {} of {})", path.display(), attempt, fsutil::VERIFY_ATTEMPTS);
+    }
+    Err(fsutil::corrupted_write(path))
+}
+
+type Registry = RwLock<HashMap<String, Arc<dyn DestinationBackend>>>;
+
+fn registry() -> &'static Registry {
+    static REGISTRY: OnceLock<Registry> = OnceLock::new();
+    REGISTRY.get_or_init(Registry::default)
+}
+
+/// Handle destinations starting with `scheme://` with `backend`, replacing any backend
+/// registered for the scheme before.
+pub fn register(scheme: &str, backend: Arc<dyn DestinationBackend>) {
+    registry()
+        .write()
+        .unwrap_or_else(PoisonError::into_inner)
+        .insert(scheme.to_ascii_lowercase(), backend);
+}
+
+/// The backend responsible for `path`. Fails for URLs whose scheme has no backend.
+pub fn resolve(path: &Path) -> io::Result<Arc<dyn DestinationBackend>> {
+    let Some(scheme) = scheme(path) else {
+        return Ok(Arc::new(LocalBackend));
+    };
+    registry()
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .get(&scheme.to_ascii_lowercase())
+        .cloned()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::Unsupported, format!("no backend registered for {}://", scheme)))
+}
+
+/// Whether `path` is a URL rather than a local file, so local-only handling such as
+/// creating missing directories doesn't apply to it.
+pub fn is_remote(path: &Path) -> bool {
+    scheme(path).is_some()
+}
+
+/// The scheme of a `scheme://...` URL, as defined by RFC 3986.
+fn scheme(path: &Path) -> Option<&str> {
+    let (scheme, _) = path.to_str()?.split_once("://")?;
+    let mut chars = scheme.chars();
+    let valid = chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+        && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'));
+    valid.then_some(scheme)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+    
+    /// Storage that flips the first byte of its first `corrupt` writes.
+    struct FlakyBackend {
+        corrupt: usize,
+        writes: Mutex<Vec<Vec<u8>>>,
+    }
+    
+    impl FlakyBackend {
+        fn new(corrupt: usize) -> Self {
+            Self { corrupt, writes: Mutex::new(Vec::new()) }
+        }
+        
+        fn write_count(&self) -> usize {
+            self.writes.lock().unwrap().len()
+        }
+    }
+    
+    impl DestinationBackend for FlakyBackend {
+        fn write(&self, _path: &Path, content: &[u8], _durable: bool) -> io::Result<()> {
+            let mut writes = self.writes.lock().unwrap();
+            let mut stored = content.to_vec();
+            if writes.len() < self.corrupt {
+                stored[0] ^= 0xff;
+            }
+            writes.push(stored);
+            Ok(())
+        }
+        
+        fn read(&self, _path: &Path) -> io::Result<Vec<u8>> {
+            self.writes.lock().unwrap().last().cloned().ok_or_else(|| io::ErrorKind::NotFound.into())
+        }
+        
+        fn exists(&self, _path: &Path) -> bool {
+            self.write_count() > 0
+        }
+    }
+    
+    #[test]
+    fn persistent_corruption_is_reported() {
+        let backend = FlakyBackend::new(usize::MAX);
+        
+        let error = write_verified(&backend, Path::new("/copy/a.md"), b"content", false, true).unwrap_err();
+        
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(error.to_string().contains("read back differently than written"), "{error}");
+        assert_eq!(backend.write_count(), fsutil::VERIFY_ATTEMPTS);
+    }
+    
+    #[test]
+    fn corrupted_write_is_retried() {
+        let backend = FlakyBackend::new(1);
+        
+        write_verified(&backend, Path::new("/copy/a.md"), b"content", false, true).unwrap();
+        
+        assert_eq!(backend.write_count(), 2);
+        assert_eq!(backend.read(Path::new("/copy/a.md")).unwrap(), b"content");
+    }
+    
+    #[test]
+    fn writes_are_not_read_back_unless_verifying() {
+        let backend = FlakyBackend::new(1);
+        
+        write_verified(&backend, Path::new("/copy/a.md"), b"content", false, false).unwrap();
+        
+        assert_eq!(backend.write_count(), 1);
+    }
+}
@@ -0,0 +1,174 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::debug;
+
+use crate::fsutil;
+
+/// Bumped whenever the hash function or entry format changes, discarding older caches.
+const CACHE_VERSION: u32 = 2;
+
+/// Files modified less than this long before they were hashed are not cached.
+///
+/// Their mtime may not change on a second write within the filesystem's timestamp
+/// granularity (up to 2s on FAT), so a cached hash could silently go stale.
+const RACY_WINDOW: Duration = Duration::from_secs(2);
+
+/// Content hashes of files, reused as long as their size and modification time are unchanged.
+///
+/// Lets repeated `mdman diff` runs skip reading files that didn't change since the last run.
+/// Stored in the user's cache directory; a missing or unreadable cache is simply rebuilt.
+pub(crate) struct ChecksumCache {
+    path: Option<PathBuf>,
+    entries: BTreeMap<PathBuf, CacheEntry>,
+    dirty: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CacheFile {
+    version: u32,
+    entries: BTreeMap<PathBuf, CacheEntry>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct CacheEntry {
+    size: u64,
+    modified_secs: u64,
+    modified_nanos: u32,
+    hash: String,
+}
+
+impl ChecksumCache {
+    pub(crate) fn load() -> Self {
+        let path = dirs::cache_dir().map(|dir| dir.join("mdman").join("checksums.json"));
+        let entries = path
+            .as_ref()
+            .and_then(|path| fs::read(path).ok())
+            .and_then(|content| serde_json::from_slice::<CacheFile>(&content).ok())
+            .filter(|cache| cache.version == CACHE_VERSION)
+            .map(|cache| cache.entries)
+            .unwrap_or_default();
+        
+        Self { path, entries, dirty: false }
+    }
+    
+    /// Hash of the content of `path`, read from disk only if it changed since it was cached.
+    pub(crate) fn hash(&mut self, path: &Path) -> io::Result<String> {
+        fsutil::ensure_regular_file(path)?;
+        let metadata = fs::metadata(path)?;
+        let modified = metadata.modified()?;
+        let since_epoch = modified.duration_since(UNIX_EPOCH).unwrap_or_default();
+        
+        if let Some(entry) = self.entries.get(path)
+            && entry.size == metadata.len()
+            && entry.modified_secs == since_epoch.as_secs()
+            && entry.modified_nanos == since_epoch.subsec_nanos()
+        {
+            debug!("Checksum cache hit for {}", path.display());
+            return Ok(entry.hash.clone());
+        }
+        
+        let content = fs::read(path)?;
+        let hash = fsutil::content_hash(&content);
+        
+        // Also distrusts mtimes in the future, e.g. from clock skew on network shares
+        let settled = SystemTime::now()
+            .duration_since(modified)
+            .is_ok_and(|age| age >= RACY_WINDOW);
+        if settled {
+            self.entries.insert(path.to_path_buf(), CacheEntry {
+                size: content.len() as u64,
+                modified_secs: since_epoch.as_secs(),
+                modified_nanos: since_epoch.subsec_nanos(),
+                hash: hash.clone(),
+            });
+        } else {
+            self.entries.remove(path);
+        }
+        self.dirty = true;
+        
+        Ok(hash)
+    }
+    
+    /// Write the cache back if anything changed, dropping entries of files that no longer exist.
+    pub(crate) fn save(mut self) {
+        let Some(path) = self.path.take() else {
+            return;
+        };
+        if !self.dirty || fsutil::read_only() {
+            return;
+        }
+        
+        self.entries.retain(|file, _| file.exists());
+        let cache = CacheFile { version: CACHE_VERSION, entries: self.entries };
+        let written = path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::write(&path, serde_json::to_vec(&cache).unwrap_or_default()));
+        if let Err(e) = written {
+            debug!("Failed to write checksum cache {}: {}", path.display(), e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn empty() -> ChecksumCache {
+        ChecksumCache { path: None, entries: BTreeMap::new(), dirty: false }
+    }
+    
+    /// Write `content` to `path` with an old modification time, so it can be cached.
+    fn write_settled(path: &Path, content: &str, age_secs: u64) {
+        fs::write(path, content).unwrap();
+        let modified = SystemTime::now() - Duration::from_secs(age_secs);
+        fs::File::options().write(true).open(path).unwrap().set_modified(modified).unwrap();
+    }
+    
+    fn scratch_file(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("mdman-checksum-{}-{}", std::process::id(), name))
+    }
+    
+    #[test]
+    fn unchanged_files_are_not_read_again() {
+        let path = scratch_file("hit");
+        write_settled(&path, "one", 60);
+        let mut cache = empty();
+        
+        assert_eq!(cache.hash(&path).unwrap(), fsutil::content_hash(b"one"));
+        // A hash that reading the file can't produce shows the cached entry was used
+        cache.entries.get_mut(&path).unwrap().hash = "cached".to_string();
+        assert_eq!(cache.hash(&path).unwrap(), "cached");
+        
+        fs::remove_file(&path).unwrap();
+    }
+    
+    #[test]
+    fn modified_files_bust_their_entry() {
+        let path = scratch_file("bust");
+        write_settled(&path, "one", 60);
+        let mut cache = empty();
+        cache.hash(&path).unwrap();
+        
+        write_settled(&path, "two", 30);
+        
+        assert_eq!(cache.hash(&path).unwrap(), fsutil::content_hash(b"two"));
+        fs::remove_file(&path).unwrap();
+    }
+    
+    #[test]
+    fn recently_modified_files_are_not_cached() {
+        let path = scratch_file("racy");
+        fs::write(&path, "one").unwrap();
+        let mut cache = empty();
+        
+        assert_eq!(cache.hash(&path).unwrap(), fsutil::content_hash(b"one"));
+        
+        assert!(cache.entries.is_empty());
+        fs::remove_file(&path).unwrap();
+    }
+}
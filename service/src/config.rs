@@ -1,13 +1,575 @@
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
 use std::fs;
 use std::path::{Path, PathBuf};
-use tracing::{debug, instrument};
+use std::sync::OnceLock;
+use tracing::{debug, info, instrument, warn};
 
-#[derive(Debug, Serialize, Deserialize)]
+use crate::fsutil;
+use crate::markdown;
+use crate::migration::{self, CURRENT_VERSION};
+use crate::notifications::NotificationSettings;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
-    pub mappings: HashMap<PathBuf, Vec<PathBuf>>,
+    #[serde(default)]
+    pub version: u64,
+    pub mappings: BTreeMap<PathBuf, Mapping>,
+    /// Shell command run when the watcher detects a desynced destination.
+    /// `{source}` and `{dest}` (or `{destination}`) are replaced with the quoted paths.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub on_desync: Option<String>,
+    /// Default number of sources `mdman sync` processes concurrently.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sync_threads: Option<usize>,
+    /// Treat files that differ only in markdown-insignificant whitespace as in sync.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub semantic_md_compare: bool,
+    /// Write the destinations of a single source concurrently.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub parallel_destinations: bool,
+    /// Unix socket the watcher listens on for control commands (requires the `control` feature).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub control_socket: Option<PathBuf>,
+    /// Compare paths case-insensitively. Detected from the filesystem when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub case_insensitive_paths: Option<bool>,
+    /// fsync destinations after writing them. Safer against power loss, slower to sync.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub durable_writes: bool,
+    /// Read every destination back after writing it and rewrite it if the content doesn't
+    /// match, failing the write if it keeps coming back wrong. Catches storage that
+    /// silently corrupts writes, at the cost of reading everything written once more.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub verify_after_write: bool,
+    /// Write all destinations of a source or none of them: each is staged in a temp file
+    /// and only moved into place once every write succeeded. Remote destinations are
+    /// written afterwards, only if the local ones were.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub transactional: bool,
+    /// Let the watcher follow destinations that were moved within the directories it watches,
+    /// updating the mapping instead of treating the move as a deletion.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub detect_renames: bool,
+    /// Let the watcher sync on metadata-only changes such as permission or timestamp updates.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub sync_on_metadata_changes: bool,
+    /// What to do when a destination's directory doesn't exist, e.g. because its drive isn't mounted.
+    #[serde(default, skip_serializing_if = "MissingParentPolicy::is_default")]
+    pub on_missing_parent: MissingParentPolicy,
+    /// Refuse operations that delete files, and keep mappings of deleted sources.
+    /// Can also be enabled with the `MDMAN_SAFE_MODE` environment variable.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub safe_mode: bool,
+    /// Store paths as given (made absolute) instead of resolving symlinks, for filesystems
+    /// such as some FUSE mounts where canonicalizing fails or gives surprising paths.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub no_canonicalize: bool,
+    /// Sources of at least this many bytes are memory-mapped by `mdman sync` instead of
+    /// being read into memory, if they have no write permission. Unset disables mapping.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mmap_threshold: Option<u64>,
+    /// How the watcher learns about file changes.
+    #[serde(default, skip_serializing_if = "WatchBackend::is_default")]
+    pub watch_backend: WatchBackend,
+    /// How often the `poll` backend rescans watched files, in milliseconds.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub poll_interval_ms: Option<u64>,
+    /// Most file events the watcher queues before it has handled them. Unset means no limit.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub event_queue_capacity: Option<usize>,
+    /// What happens to new events while the event queue is full.
+    #[serde(default, skip_serializing_if = "QueueFullPolicy::is_default")]
+    pub on_event_queue_full: QueueFullPolicy,
+    /// Milliseconds the watcher waits after the last change of these sources before syncing
+    /// them, instead of its batch window. Keyed by source path as `mdman list` shows it.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub debounce_overrides: BTreeMap<PathBuf, u64>,
+    /// Save the config as compact JSON, which is smaller and faster to load with thousands
+    /// of mappings but hard to edit by hand.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub compact_config: bool,
+    /// Destinations generated by concatenating several sources, keyed by destination.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub aggregates: BTreeMap<PathBuf, Aggregate>,
+    /// Per-event style of desktop notifications.
+    #[serde(default, skip_serializing_if = "NotificationSettings::is_default")]
+    pub notifications: NotificationSettings,
+    /// Set while a [`Config::transaction`] runs, turning [`Config::save`] into a no-op.
+    #[serde(skip)]
+    in_transaction: bool,
+    /// Whether the config directory's filesystem ignores case, probed on first use.
+    #[serde(skip)]
+    case_probe: OnceLock<bool>,
+}
+
+/// Options `mdman config get/set` can change: the top-level scalars, leaving out the
+/// mappings and nested settings such as notifications.
+pub const SCALAR_OPTIONS: &[&str] = &[
+    "on_desync",
+    "sync_threads",
+    "semantic_md_compare",
+    "parallel_destinations",
+    "control_socket",
+    "case_insensitive_paths",
+    "durable_writes",
+    "verify_after_write",
+    "transactional",
+    "detect_renames",
+    "sync_on_metadata_changes",
+    "on_missing_parent",
+    "safe_mode",
+    "no_canonicalize",
+    "mmap_threshold",
+    "watch_backend",
+    "poll_interval_ms",
+    "event_queue_capacity",
+    "on_event_queue_full",
+    "compact_config",
+];
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            version: CURRENT_VERSION,
+            mappings: BTreeMap::new(),
+            on_desync: None,
+            sync_threads: None,
+            semantic_md_compare: false,
+            parallel_destinations: false,
+            control_socket: None,
+            case_insensitive_paths: None,
+            durable_writes: false,
+            verify_after_write: false,
+            transactional: false,
+            detect_renames: false,
+            sync_on_metadata_changes: false,
+            on_missing_parent: MissingParentPolicy::default(),
+            safe_mode: false,
+            no_canonicalize: false,
+            mmap_threshold: None,
+            watch_backend: WatchBackend::default(),
+            poll_interval_ms: None,
+            event_queue_capacity: None,
+            on_event_queue_full: QueueFullPolicy::default(),
+            debounce_overrides: BTreeMap::new(),
+            compact_config: false,
+            aggregates: BTreeMap::new(),
+            notifications: NotificationSettings::default(),
+            in_transaction: false,
+            case_probe: OnceLock::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum WatchBackend {
+    /// The platform's change notifications: inotify, FSEvents, kqueue or ReadDirectoryChangesW.
+    #[default]
+    Native,
+    /// Periodically rescan watched files, for network and FUSE filesystems that don't report changes.
+    Poll,
+}
+
+impl WatchBackend {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum QueueFullPolicy {
+    /// Hold up the notification thread until there is room, so no event is lost.
+    #[default]
+    Block,
+    /// Discard the event and log a warning, keeping latency bounded.
+    Drop,
+}
+
+impl QueueFullPolicy {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Everything mdman knows about one tracked source file.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Mapping {
+    pub destinations: Vec<PathBuf>,
+    /// Directory that paths of this mapping are displayed relative to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display_base: Option<PathBuf>,
+    /// Sources are synced in ascending priority, so lower numbers go first.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub priority: i32,
+    /// Free-form note on why this mapping exists.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Leave the source's YAML frontmatter out of the destinations.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub strip_frontmatter: bool,
+    /// Regions of the source left out of the destinations, such as internal notes.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub strip_regions: Vec<MarkedRegion>,
+    /// Destinations that are links to the source rather than copies of it.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub links: BTreeMap<PathBuf, LinkKind>,
+    /// Destinations maintained by hand: checked for drift, but never written automatically.
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub manual: BTreeSet<PathBuf>,
+    /// Destinations temporarily written somewhere else, e.g. while their drive is serviced,
+    /// keyed by their tracked path.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub redirects: BTreeMap<PathBuf, PathBuf>,
+    /// Shell command run when a destination of this mapping is desynced, instead of the
+    /// watcher's or the config's `on_desync`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub on_desync: Option<String>,
+}
+
+/// How a linked destination refers to its source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LinkKind {
+    /// Shares the source's inode, so both always have the same content.
+    Hardlink,
+    /// Points to the source's path.
+    Symlink,
+}
+
+impl LinkKind {
+    pub fn create(self, source: &Path, dest: &Path) -> std::io::Result<()> {
+        match self {
+            LinkKind::Hardlink => fs::hard_link(source, dest),
+            LinkKind::Symlink => fsutil::symlink(source, dest),
+        }
+    }
+    
+    /// Whether `dest` still is this kind of link to `source`. Editors that save by replacing
+    /// the file break hardlinks, and symlinks can be replaced by a copy.
+    pub fn is_intact(self, source: &Path, dest: &Path) -> bool {
+        match self {
+            LinkKind::Hardlink => fsutil::same_file(source, dest),
+            LinkKind::Symlink => fs::read_link(dest).is_ok_and(|target| target == source),
+        }
+    }
+    
+    /// Recreate the link if it is no longer intact, replacing whatever is at `dest`.
+    /// Returns whether it had to be recreated.
+    pub fn restore(self, source: &Path, dest: &Path) -> std::io::Result<bool> {
+        if self.is_intact(source, dest) {
+            return Ok(false);
+        }
+        if !fsutil::may_write("relink", dest) {
+            return Ok(true);
+        }
+        match fs::symlink_metadata(dest) {
+            Ok(_) => fs::remove_file(dest)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+        self.create(source, dest)?;
+        Ok(true)
+    }
+}
+
+/// Lines marking the start and end of a region, see [`markdown::strip_marked_regions`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MarkedRegion {
+    #[serde(default = "default_region_start")]
+    pub start: String,
+    #[serde(default = "default_region_end")]
+    pub end: String,
+}
+
+impl Default for MarkedRegion {
+    fn default() -> Self {
+        Self {
+            start: default_region_start(),
+            end: default_region_end(),
+        }
+    }
+}
+
+fn default_region_start() -> String {
+    "<!-- mdman:internal-start -->".to_string()
+}
+
+fn default_region_end() -> String {
+    "<!-- mdman:internal-end -->".to_string()
+}
+
+/// Set by [`Config::set_config_dir`], e.g. from `mdman --config`.
+static CONFIG_DIR_OVERRIDE: OnceLock<PathBuf> = OnceLock::new();
+
+fn is_zero(value: &i32) -> bool {
+    *value == 0
+}
+
+impl Mapping {
+    /// Render `path` relative to `base`, or to the mapping's own display base if none is given.
+    ///
+    /// Paths are stored canonically; this only affects presentation.
+    pub fn display_path(&self, path: &Path, base: Option<&Path>) -> PathBuf {
+        match base.or(self.display_base.as_deref()) {
+            Some(base) => relative_to(path, base),
+            None => path.to_path_buf(),
+        }
+    }
+    
+    /// What the destinations of this mapping should contain, given the source's content.
+    pub fn destination_content<'a>(&self, source: &'a [u8]) -> Cow<'a, [u8]> {
+        let mut content = Cow::Borrowed(source);
+        if self.strip_frontmatter {
+            content = Cow::Borrowed(markdown::strip_frontmatter(source));
+        }
+        for region in &self.strip_regions {
+            if let Cow::Owned(stripped) = markdown::strip_marked_regions(&content, &region.start, &region.end) {
+                content = Cow::Owned(stripped);
+            }
+        }
+        content
+    }
+    
+    pub fn link_kind(&self, dest: &Path) -> Option<LinkKind> {
+        self.links.get(dest).copied()
+    }
+    
+    /// Whether `dest` is written when the source changes, see [`Mapping::manual`].
+    pub fn auto_syncs(&self, dest: &Path) -> bool {
+        !self.manual.contains(self.redirected_from(dest))
+    }
+    
+    /// Where `dest` is currently written, which is elsewhere while it is redirected.
+    pub fn target<'a>(&'a self, dest: &'a Path) -> &'a Path {
+        self.redirects.get(dest).map_or(dest, PathBuf::as_path)
+    }
+    
+    /// The destinations as they are currently written, with redirects applied.
+    pub fn targets(&self) -> Vec<PathBuf> {
+        self.destinations.iter().map(|dest| self.target(dest).to_path_buf()).collect()
+    }
+    
+    /// The tracked destination that `path` is written in place of, or `path` itself.
+    fn redirected_from<'a>(&'a self, path: &'a Path) -> &'a Path {
+        self.redirects
+            .iter()
+            .find(|(_, target)| target.as_path() == path)
+            .map_or(path, |(dest, _)| dest.as_path())
+    }
+    
+    /// Forget link kinds, manual flags and redirects of paths that are no longer destinations.
+    fn prune_destination_settings(&mut self) {
+        let destinations = &self.destinations;
+        self.links.retain(|dest, _| destinations.contains(dest));
+        self.manual.retain(|dest| destinations.contains(dest));
+        self.redirects.retain(|dest, _| destinations.contains(dest));
+    }
+    
+    /// Whether destinations differ from the source by design, rather than being copies.
+    pub fn transforms_content(&self) -> bool {
+        self.strip_frontmatter || !self.strip_regions.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum MissingParentPolicy {
+    /// Create the missing directories.
+    #[default]
+    Create,
+    /// Fail the write.
+    Error,
+    /// Skip the destination for now and retry on the next sync or watcher event.
+    SkipAndQueue,
+}
+
+impl MissingParentPolicy {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+    
+    /// Get `path`'s directory ready for writing according to the policy.
+    ///
+    /// Returns `false` if the write should be put off until the directory shows up.
+    pub fn prepare(self, path: &Path) -> std::io::Result<bool> {
+        let parent = match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() && !parent.exists() => parent,
+            _ => return Ok(true),
+        };
+        
+        match self {
+            MissingParentPolicy::Create if !fsutil::may_write("create", parent) => Ok(true),
+            MissingParentPolicy::Create => fs::create_dir_all(parent).map(|_| true),
+            MissingParentPolicy::Error => Err(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("directory {} does not exist", parent.display()),
+            )),
+            MissingParentPolicy::SkipAndQueue => Ok(false),
+        }
+    }
+}
+
+/// A destination built from several sources, concatenated in order.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Aggregate {
+    pub sources: Vec<PathBuf>,
+    /// Inserted between consecutive sources. Defaults to a newline, which leaves a blank
+    /// line between sources that end with a line break.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub separator: Option<String>,
+}
+
+impl Aggregate {
+    pub fn separator(&self) -> &str {
+        self.separator.as_deref().unwrap_or("\n")
+    }
+    
+    /// The destination's content: every source in order, joined by the separator.
+    pub fn render(&self) -> Result<Vec<u8>> {
+        let mut content = Vec::new();
+        for (i, source) in self.sources.iter().enumerate() {
+            if i > 0 {
+                content.extend_from_slice(self.separator().as_bytes());
+            }
+            let source_content = fs::read(source)
+                .with_context(|| format!("Failed to read {}", source.display()))?;
+            content.extend_from_slice(&source_content);
+        }
+        Ok(content)
+    }
+}
+
+fn path_key(path: &Path) -> String {
+    path.to_string_lossy().to_lowercase()
+}
+
+/// What [`Config::canonicalize_all`] changed.
+#[derive(Debug, Default)]
+pub struct CanonicalizeReport {
+    /// Paths rewritten to their canonical form, as `(before, after)`.
+    pub changed: Vec<(PathBuf, PathBuf)>,
+    /// Sources that several entries collapsed into.
+    pub merged: Vec<PathBuf>,
+    /// Paths that could not be canonicalized, usually because they don't exist.
+    pub unresolved: Vec<PathBuf>,
+}
+
+/// Totals over everything tracked, see [`Config::stats`].
+#[derive(Debug, Default, Serialize)]
+pub struct ConfigStats {
+    pub sources: usize,
+    pub destinations: usize,
+    pub aggregates: usize,
+    /// Combined size of all sources that exist.
+    pub source_bytes: u64,
+    /// The source with the most destinations and how many it has.
+    pub largest_fan_out: Option<(PathBuf, usize)>,
+}
+
+/// Why the config file could not be read or written.
+///
+/// Returned inside the `anyhow::Error`, so callers can downcast to tell the cases apart.
+#[derive(Debug)]
+pub enum ConfigError {
+    /// The file exists but could not be read, usually because of its permissions.
+    Unreadable { path: PathBuf, source: std::io::Error },
+    /// The file is not valid JSON or doesn't match the config format.
+    Invalid { path: PathBuf, source: serde_json::Error },
+    /// The config directory can't be written, e.g. on a read-only filesystem.
+    ReadOnly { path: PathBuf, source: std::io::Error },
+}
+
+impl ConfigError {
+    fn is_read_only(error: &std::io::Error) -> bool {
+        matches!(error.kind(), std::io::ErrorKind::PermissionDenied | std::io::ErrorKind::ReadOnlyFilesystem)
+    }
+}
+
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ConfigError::Unreadable { path, source } if source.kind() == std::io::ErrorKind::PermissionDenied => {
+                write!(f, "Permission denied reading config {}, check the file's permissions", path.display())
+            }
+            ConfigError::Unreadable { path, .. } => write!(f, "Could not read config {}", path.display()),
+            ConfigError::Invalid { path, .. } => write!(
+                f,
+                "Config {} is invalid. Fix the file by hand, or move it aside and run 'mdman init' to start over",
+                path.display()
+            ),
+            ConfigError::ReadOnly { path, .. } => write!(
+                f,
+                "Config directory {} is not writable, so only read-only commands work. Use --config DIR to keep the config elsewhere",
+                path.display()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ConfigError::Unreadable { source, .. } => Some(source),
+            ConfigError::Invalid { source, .. } => Some(source),
+            ConfigError::ReadOnly { source, .. } => Some(source),
+        }
+    }
+}
+
+/// Check that a set of mappings can be synced unambiguously:
+///
+/// - no source lists itself as a destination,
+/// - no destination is written by more than one mapping,
+/// - no chain of mappings leads back to where it started, since syncing would never settle.
+pub fn validate_mappings(mappings: &BTreeMap<PathBuf, Mapping>) -> Result<()> {
+    let mut owners: BTreeMap<&Path, &Path> = BTreeMap::new();
+    for (source, mapping) in mappings {
+        for dest in &mapping.destinations {
+            if dest == source {
+                anyhow::bail!("{} is listed as its own destination", source.display());
+            }
+            if let Some(other) = owners.insert(dest, source) {
+                anyhow::bail!(
+                    "{} is a destination of both {} and {}",
+                    dest.display(),
+                    other.display(),
+                    source.display()
+                );
+            }
+        }
+    }
+    
+    // Each destination has a single owner, so following owners from any path is a simple walk
+    for start in mappings.keys() {
+        let mut current = start.as_path();
+        let mut steps = 0;
+        while let Some(owner) = owners.get(current) {
+            if *owner == start.as_path() {
+                anyhow::bail!("{} is synced back into itself through other mappings", start.display());
+            }
+            current = owner;
+            steps += 1;
+            if steps > mappings.len() {
+                break;
+            }
+        }
+    }
+    
+    Ok(())
+}
+
+/// Express `path` relative to `base`, falling back to `path` itself when that's not possible.
+pub fn relative_to(path: &Path, base: &Path) -> PathBuf {
+    match pathdiff::diff_paths(path, base) {
+        Some(relative) if relative.as_os_str().is_empty() => PathBuf::from("."),
+        Some(relative) => relative,
+        None => path.to_path_buf(),
+    }
 }
 
 impl Config {
@@ -17,58 +579,535 @@ impl Config {
         
         if !config_path.exists() {
             debug!("No config file found at {:?}, creating new config", config_path);
-            return Ok(Self {
-                mappings: HashMap::new(),
-            });
+            return Ok(Self::default());
         }
         
         debug!("Loading config from {:?}", config_path);
-        let content = fs::read_to_string(&config_path)?;
-        let config: Self = serde_json::from_str(&content)?;
+        let content = fs::read_to_string(&config_path).map_err(|source| ConfigError::Unreadable {
+            path: config_path.clone(),
+            source,
+        })?;
+        let mut value: serde_json::Value = serde_json::from_str(&content).map_err(|source| ConfigError::Invalid {
+            path: config_path.clone(),
+            source,
+        })?;
+        let original_version = migration::migrate(&mut value)?;
+        
+        let mut config: Self = serde_json::from_value(value).map_err(|source| ConfigError::Invalid {
+            path: config_path.clone(),
+            source,
+        })?;
+        for mapping in config.mappings.values_mut() {
+            mapping.destinations.sort();
+        }
+        
+        if original_version < CURRENT_VERSION && (fsutil::read_only() || Self::ensure_writable().is_err()) {
+            // Reading still works with the upgrade kept in memory
+            warn!(
+                "Config {} is from version {} but can't be upgraded in place",
+                config_path.display(),
+                original_version
+            );
+        } else if original_version < CURRENT_VERSION {
+            let backup_path = config_path.with_extension(format!("json.v{original_version}.bak"));
+            fs::copy(&config_path, &backup_path)
+                .with_context(|| format!("Failed to back up config to {}", backup_path.display()))?;
+            info!(
+                "Upgraded config from version {} to {}, previous file kept at {}",
+                original_version,
+                CURRENT_VERSION,
+                backup_path.display()
+            );
+            config.save()?;
+        }
+        
         debug!("Loaded {} mappings", config.mappings.len());
         Ok(config)
     }
     
     #[instrument(skip(self))]
     pub fn save(&self) -> Result<()> {
+        if self.in_transaction {
+            return Ok(());
+        }
         let config_path = Self::config_file_path()?;
+        if !fsutil::may_write("save", &config_path) {
+            return Ok(());
+        }
+        let read_only = |source| {
+            if ConfigError::is_read_only(&source) {
+                anyhow::Error::new(ConfigError::ReadOnly { path: Self::config_dir().unwrap_or_default(), source })
+            } else {
+                anyhow::Error::new(source)
+            }
+        };
         
         if let Some(parent) = config_path.parent() {
-            fs::create_dir_all(parent)?;
+            fs::create_dir_all(parent).map_err(read_only)?;
         }
         
-        let content = serde_json::to_string_pretty(self)?;
-        fs::write(&config_path, content)?;
+        let content = if self.compact_config {
+            serde_json::to_string(self)?
+        } else {
+            serde_json::to_string_pretty(self)?
+        };
+        fs::write(&config_path, content).map_err(read_only)?;
         debug!("Saved config with {} mappings to {:?}", self.mappings.len(), config_path);
         Ok(())
     }
     
+    /// The value of the option `key`, or `None` while it has its default.
+    pub fn get_option(&self, key: &str) -> Result<Option<serde_json::Value>> {
+        Self::check_option(key)?;
+        Ok(serde_json::to_value(self)?.get(key).cloned())
+    }
+    
+    /// Set the option `key` from its command-line form such as `true`, `4` or `poll`, checking
+    /// it has the option's type. `null` restores the default.
+    #[instrument(skip(self))]
+    pub fn set_option(&mut self, key: &str, value: &str) -> Result<()> {
+        Self::check_option(key)?;
+        
+        let with_option = |parsed: serde_json::Value| -> Result<Self> {
+            let mut config = serde_json::to_value(&*self)?;
+            if let Some(options) = config.as_object_mut() {
+                if parsed.is_null() {
+                    options.remove(key);
+                } else {
+                    options.insert(key.to_string(), parsed);
+                }
+            }
+            Ok(serde_json::from_value(config)?)
+        };
+        
+        // Anything that isn't JSON, like a command or an enum variant, is taken as a string,
+        // and so is JSON of the wrong type, like a command named `true` or `123`
+        let string = serde_json::Value::String(value.to_string());
+        let updated = match serde_json::from_str::<serde_json::Value>(value) {
+            Ok(parsed) => with_option(parsed).or_else(|e| with_option(string).map_err(|_| e)),
+            Err(_) => with_option(string),
+        }
+        .with_context(|| format!("Invalid value {} for {}", value, key))?;
+        *self = Self { in_transaction: self.in_transaction, ..updated };
+        self.save()
+    }
+    
+    fn check_option(key: &str) -> Result<()> {
+        if !SCALAR_OPTIONS.contains(&key) {
+            anyhow::bail!("Unknown option {}. Valid options are: {}", key, SCALAR_OPTIONS.join(", "));
+        }
+        Ok(())
+    }
+    
+    /// Make several changes with a single save at the end.
+    ///
+    /// The methods `edit` calls don't save on their own. If it fails, nothing is saved and
+    /// the config is left as it was before. Nested transactions are part of the outer one.
+    pub fn transaction<T>(&mut self, edit: impl FnOnce(&mut Config) -> Result<T>) -> Result<T> {
+        if self.in_transaction {
+            return edit(self);
+        }
+        
+        let before = self.clone();
+        self.in_transaction = true;
+        let result = edit(self);
+        self.in_transaction = false;
+        
+        match result {
+            Ok(value) => {
+                self.save()?;
+                Ok(value)
+            }
+            Err(e) => {
+                *self = before;
+                Err(e)
+            }
+        }
+    }
+    
+    /// Destinations of the tracked `source`, for edits the other methods don't cover.
+    /// Changes are only kept once saved, e.g. by running them in a [`Config::transaction`].
+    pub fn destinations_mut(&mut self, source: &Path) -> Option<&mut Vec<PathBuf>> {
+        let (source, _) = self.find_by_path(source)?;
+        self.mappings.get_mut(&source).map(|mapping| &mut mapping.destinations)
+    }
+    
     #[instrument(skip(self), fields(source = %source.display(), destination = %destination.display()))]
     pub fn add_mapping(&mut self, source: PathBuf, destination: PathBuf) -> Result<()> {
-        let source = source.canonicalize()?;
+        let source = self.resolve_path(&source)?;
         let dest_file = if destination.is_dir() {
             destination.join(source.file_name().context("Invalid source filename")?)
         } else {
             destination
         };
-        let dest_file = dest_file.canonicalize().unwrap_or(dest_file);
+        let dest_file = self.resolve_path(&dest_file).unwrap_or(dest_file);
+        fsutil::ensure_regular_file(&source)?;
+        fsutil::ensure_regular_file(&dest_file)?;
         
-        self.mappings
-            .entry(source)
-            .or_default()
-            .push(dest_file);
+        if fsutil::same_file(&source, &dest_file) {
+            anyhow::bail!(
+                "{} is the same file as {} (hardlink), it cannot be tracked as its destination",
+                dest_file.display(),
+                source.display()
+            );
+        }
+        
+        // On case-insensitive filesystems a case variant must resolve to the existing entry
+        let source = self.stored_path(&source);
+        let dest_file = self.stored_path(&dest_file);
+        
+        let mapping = self.mappings.entry(source).or_default();
+        if !mapping.destinations.contains(&dest_file) {
+            mapping.destinations.push(dest_file);
+            mapping.destinations.sort();
+        }
         
         self.save()?;
         Ok(())
     }
     
+    /// Track the link `destination` to `source`, which must already exist. Unlike other
+    /// destinations, the link itself is stored rather than the file it resolves to.
+    #[instrument(skip(self), fields(source = %source.display(), destination = %destination.display()))]
+    pub fn add_link(&mut self, source: &Path, destination: &Path, kind: LinkKind) -> Result<()> {
+        let source = self.stored_path(&self.resolve_path(source)?);
+        fsutil::ensure_regular_file(&source)?;
+        let dest_file = self.stored_path(&fsutil::resolve(destination, false)?);
+        
+        let mapping = self.mappings.entry(source).or_default();
+        if !mapping.destinations.contains(&dest_file) {
+            mapping.destinations.push(dest_file.clone());
+            mapping.destinations.sort();
+        }
+        mapping.links.insert(dest_file, kind);
+        
+        self.save()
+    }
+    
+    /// Generate `destination` by concatenating `sources` in the given order.
+    ///
+    /// Replaces any existing aggregate for the same destination.
+    #[instrument(skip(self, sources), fields(destination = %destination.display(), source_count = sources.len()))]
+    pub fn add_aggregate(&mut self, destination: &Path, sources: &[PathBuf], separator: Option<String>) -> Result<PathBuf> {
+        let sources = sources
+            .iter()
+            .map(|source| {
+                let source = self.resolve_path(source)
+                    .with_context(|| format!("Source {} does not exist", source.display()))?;
+                Ok(self.stored_path(&source))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let destination = match self.resolve_path(destination) {
+            Ok(destination) => destination,
+            // Not generated yet, so only its directory can be resolved
+            Err(_) => {
+                let name = destination.file_name().context("Invalid destination filename")?;
+                let parent = match destination.parent() {
+                    Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+                    _ => PathBuf::from("."),
+                };
+                self.resolve_path(&parent)
+                    .with_context(|| format!("Directory {} does not exist", parent.display()))?
+                    .join(name)
+            }
+        };
+        let destination = self.stored_path(&destination);
+        
+        if sources.contains(&destination) {
+            anyhow::bail!("{} cannot be both a source and the aggregated destination", destination.display());
+        }
+        if self.mappings.contains_key(&destination) || self.find_source_of(&destination).is_some() {
+            anyhow::bail!("{} is already tracked", destination.display());
+        }
+        
+        self.aggregates.insert(destination.clone(), Aggregate { sources, separator });
+        self.save()?;
+        Ok(destination)
+    }
+    
+    /// Destinations of the aggregates that include `source`.
+    pub fn aggregates_containing(&self, source: &Path) -> Vec<PathBuf> {
+        self.aggregates
+            .iter()
+            .filter(|(_, aggregate)| aggregate.sources.iter().any(|s| s == source))
+            .map(|(destination, _)| destination.clone())
+            .collect()
+    }
+    
+    /// Whether tracked paths should be compared without regard to case.
+    pub fn case_insensitive(&self) -> bool {
+        self.case_insensitive_paths.unwrap_or_else(|| {
+            *self.case_probe.get_or_init(|| {
+                Self::config_dir()
+                    .ok()
+                    .and_then(|dir| dir.parent().map(fsutil::is_case_insensitive))
+                    .unwrap_or(false)
+            })
+        })
+    }
+    
+    /// Return the tracked path (source or destination) that `path` refers to, matching
+    /// case-insensitively where the filesystem is; otherwise `path` itself.
+    pub fn stored_path(&self, path: &Path) -> PathBuf {
+        if !self.case_insensitive() {
+            return path.to_path_buf();
+        }
+        
+        let key = path_key(path);
+        self.mappings
+            .iter()
+            .flat_map(|(source, mapping)| std::iter::once(source).chain(&mapping.destinations))
+            .find(|stored| path_key(stored) == key)
+            .cloned()
+            .unwrap_or_else(|| path.to_path_buf())
+    }
+    
+    #[instrument(skip(self), fields(source = %source.display(), base = %base.display()))]
+    pub fn set_display_base(&mut self, source: &Path, base: &Path) -> Result<()> {
+        let source = self.resolve_path(source)?;
+        let base = self.resolve_path(base)?;
+        
+        let mapping = self.mappings
+            .get_mut(&source)
+            .with_context(|| format!("{} is not a tracked source", source.display()))?;
+        mapping.display_base = Some(base);
+        
+        self.save()
+    }
+    
+    #[instrument(skip(self), fields(source = %source.display()))]
+    pub fn set_priority(&mut self, source: &Path, priority: i32) -> Result<()> {
+        let (source, _) = self
+            .find_by_path(source)
+            .with_context(|| format!("{} is not a tracked source", source.display()))?;
+        
+        if let Some(mapping) = self.mappings.get_mut(&source) {
+            mapping.priority = priority;
+        }
+        
+        self.save()
+    }
+    
+    /// Attach a description to a mapping, or clear it with `None`.
+    #[instrument(skip(self), fields(source = %source.display()))]
+    pub fn set_description(&mut self, source: &Path, description: Option<String>) -> Result<()> {
+        let (source, _) = self
+            .find_by_path(source)
+            .with_context(|| format!("{} is not a tracked source", source.display()))?;
+        
+        if let Some(mapping) = self.mappings.get_mut(&source) {
+            mapping.description = description.filter(|d| !d.trim().is_empty());
+        }
+        
+        self.save()
+    }
+    
+    /// Turn automatic syncing of the tracked `destination` on or off. It is checked for drift either way.
+    #[instrument(skip(self), fields(destination = %destination.display()))]
+    pub fn set_auto_sync(&mut self, destination: &Path, enabled: bool) -> Result<()> {
+        let (source, stored) = self.destination_entry(destination)?;
+        
+        if let Some(mapping) = self.mappings.get_mut(&source) {
+            if enabled {
+                mapping.manual.remove(&stored);
+            } else {
+                mapping.manual.insert(stored);
+            }
+        }
+        
+        self.save()
+    }
+    
+    /// Write the tracked `destination` to `target` until [`Config::clear_redirect`] is called,
+    /// keeping its mapping and settings as they are.
+    #[instrument(skip(self), fields(destination = %destination.display(), target = %target.display()))]
+    pub fn redirect_destination(&mut self, destination: &Path, target: &Path) -> Result<()> {
+        let (source, stored) = self.destination_entry(destination)?;
+        let target = std::path::absolute(target)?;
+        let target = self.stored_path(&self.resolve_path(&target).unwrap_or(target));
+        
+        if self.mappings[&source].link_kind(&stored).is_some() {
+            anyhow::bail!("{} is a link to its source and can't be redirected", destination.display());
+        }
+        if self.mappings.contains_key(&target) || self.find_source_of(&target).is_some() {
+            anyhow::bail!("{} is already being tracked", target.display());
+        }
+        let taken = self.mappings.values().any(|mapping| mapping.redirects.values().any(|t| *t == target));
+        if taken {
+            anyhow::bail!("Another destination is already redirected to {}", target.display());
+        }
+        fsutil::ensure_regular_file(&target)?;
+        
+        if let Some(mapping) = self.mappings.get_mut(&source) {
+            mapping.redirects.insert(stored, target);
+        }
+        self.save()
+    }
+    
+    /// Write the tracked `destination` to its own path again, returning where it was redirected to.
+    #[instrument(skip(self), fields(destination = %destination.display()))]
+    pub fn clear_redirect(&mut self, destination: &Path) -> Result<PathBuf> {
+        let (source, stored) = self.destination_entry(destination)?;
+        let target = self
+            .mappings
+            .get_mut(&source)
+            .and_then(|mapping| mapping.redirects.remove(&stored))
+            .with_context(|| format!("{} is not redirected", destination.display()))?;
+        
+        self.save()?;
+        Ok(target)
+    }
+    
+    /// The source of the tracked `destination` and the destination as its mapping stores it.
+    fn destination_entry(&self, destination: &Path) -> Result<(PathBuf, PathBuf)> {
+        let source = self
+            .find_source_of(destination)
+            .with_context(|| format!("{} is not a tracked destination", destination.display()))?;
+        let canonical = self.stored_path(&self.resolve_path(destination).unwrap_or_else(|_| destination.to_path_buf()));
+        
+        // Stored the way the mapping lists it, which for links is the unresolved path
+        let stored = self.mappings[&source]
+            .destinations
+            .iter()
+            .find(|dest| dest.as_path() == destination || **dest == canonical)
+            .cloned()
+            .unwrap_or(canonical);
+        Ok((source, stored))
+    }
+    
+    /// One line describing what the watcher will watch with this config.
+    pub fn summary(&self) -> String {
+        let sources = self.mappings.len();
+        if sources == 0 {
+            return "No files are tracked yet, so the watcher has nothing to do. Use 'mdman copy' to track a file".to_string();
+        }
+        
+        let destinations: usize = self.mappings.values().map(|m| m.destinations.len()).sum();
+        format!(
+            "Watching {} source file{} synced to {} destination{}",
+            sources,
+            if sources == 1 { "" } else { "s" },
+            destinations,
+            if destinations == 1 { "" } else { "s" }
+        )
+    }
+    
+    /// Totals over the tracked files, reading only the sizes of sources from disk.
+    pub fn stats(&self) -> ConfigStats {
+        let largest_fan_out = self
+            .mappings
+            .iter()
+            .max_by_key(|(_, mapping)| mapping.destinations.len())
+            .map(|(source, mapping)| (source.clone(), mapping.destinations.len()));
+        
+        ConfigStats {
+            sources: self.mappings.len(),
+            destinations: self.mappings.values().map(|m| m.destinations.len()).sum(),
+            aggregates: self.aggregates.len(),
+            source_bytes: self.mappings.keys().filter_map(|source| fs::metadata(source).ok()).map(|m| m.len()).sum(),
+            largest_fan_out,
+        }
+    }
+    
+    /// Replace every mapping at once, after checking the new set is consistent.
+    ///
+    /// Nothing is changed if validation fails. See [`validate_mappings`] for the rules.
+    #[instrument(skip(self, mappings), fields(count = mappings.len()))]
+    pub fn replace_all(&mut self, mappings: BTreeMap<PathBuf, Mapping>) -> Result<()> {
+        validate_mappings(&mappings)?;
+        self.mappings = mappings;
+        self.prune_debounce_overrides();
+        self.save()
+    }
+    
+    /// Point the mapping that writes `from` at `to` instead, returning whether `from` was a destination.
+    #[instrument(skip(self), fields(from = %from.display(), to = %to.display()))]
+    pub fn move_destination(&mut self, from: &Path, to: &Path) -> Result<bool> {
+        let Some(source) = self.find_source_of(from) else {
+            return Ok(false);
+        };
+        
+        let to = self.stored_path(to);
+        if let Some(mapping) = self.mappings.get_mut(&source) {
+            for dest in mapping.destinations.iter_mut().filter(|dest| dest.as_path() == from) {
+                *dest = to.clone();
+            }
+            mapping.destinations.sort();
+            mapping.destinations.dedup();
+            if let Some(kind) = mapping.links.remove(from) {
+                mapping.links.insert(to.clone(), kind);
+            }
+            if mapping.manual.remove(from) {
+                mapping.manual.insert(to.clone());
+            }
+            if let Some(target) = mapping.redirects.remove(from) {
+                mapping.redirects.insert(to.clone(), target);
+            }
+        }
+        
+        self.save()?;
+        Ok(true)
+    }
+    
+    /// Stop generating the aggregated `destination`, returning whether it was one.
+    #[instrument(skip(self), fields(destination = %destination.display()))]
+    pub fn remove_aggregate(&mut self, destination: &Path) -> Result<bool> {
+        let canonical = self.resolve_path(destination).unwrap_or_else(|_| destination.to_path_buf());
+        let canonical = self.stored_path(&canonical);
+        
+        if self.aggregates.remove(destination).is_none() && self.aggregates.remove(&canonical).is_none() {
+            return Ok(false);
+        }
+        self.save()?;
+        Ok(true)
+    }
+    
+    /// Stop tracking `source` and all its destinations, returning whether it was tracked.
+    ///
+    /// The source is resolved like [`Config::find_by_path`], so it may be given in
+    /// any form that refers to the stored path, even after the file was deleted.
+    #[instrument(skip(self), fields(source = %source.display()))]
+    pub fn remove_source(&mut self, source: &Path) -> Result<bool> {
+        let Some((source, _)) = self.find_by_path(source) else {
+            return Ok(false);
+        };
+        
+        self.mappings.remove(&source);
+        self.prune_debounce_overrides();
+        self.save()?;
+        Ok(true)
+    }
+    
+    /// Drop the debounce overrides of sources that are no longer tracked.
+    fn prune_debounce_overrides(&mut self) {
+        let mappings = &self.mappings;
+        self.debounce_overrides.retain(|source, _| mappings.contains_key(source));
+    }
+    
+    /// Stop tracking every source for which `predicate` returns true, returning the removed
+    /// mappings. Saves the config if anything was removed.
+    #[instrument(skip_all)]
+    pub fn remove_matching(&mut self, predicate: impl Fn(&Path, &Mapping) -> bool) -> Result<Vec<(PathBuf, Mapping)>> {
+        let (removed, kept) = std::mem::take(&mut self.mappings)
+            .into_iter()
+            .partition(|(source, mapping)| predicate(source, mapping));
+        self.mappings = kept;
+        self.prune_debounce_overrides();
+        
+        let removed: Vec<(PathBuf, Mapping)> = removed.into_iter().collect();
+        if !removed.is_empty() {
+            self.save()?;
+        }
+        Ok(removed)
+    }
+    
     #[instrument(skip(self), fields(file = %file.display()))]
     pub fn remove_mapping(&mut self, file: &Path) -> Result<bool> {
-        let file = file.canonicalize()?;
+        let file = self.resolve_path(file)?;
         let mut removed = false;
         
-        self.mappings.retain(|_source, destinations| {
-            destinations.retain(|dest| {
+        self.mappings.retain(|_source, mapping| {
+            mapping.destinations.retain(|dest| {
                 if dest == &file {
                     removed = true;
                     false
@@ -76,16 +1115,18 @@ impl Config {
                     true
                 }
             });
-            !destinations.is_empty()
+            !mapping.destinations.is_empty()
         });
         
-        for (_, destinations) in self.mappings.iter_mut() {
-            let initial_len = destinations.len();
-            destinations.retain(|dest| dest != &file);
-            if destinations.len() < initial_len {
+        for mapping in self.mappings.values_mut() {
+            let initial_len = mapping.destinations.len();
+            mapping.destinations.retain(|dest| dest != &file);
+            if mapping.destinations.len() < initial_len {
                 removed = true;
             }
+            mapping.prune_destination_settings();
         }
+        self.prune_debounce_overrides();
         
         if removed {
             self.save()?;
@@ -94,44 +1135,390 @@ impl Config {
         Ok(removed)
     }
     
+    /// Every source with where its destinations are currently written, see [`Mapping::targets`].
     pub fn list_mappings(&self) -> Vec<(PathBuf, Vec<PathBuf>)> {
         self.mappings
             .iter()
-            .map(|(k, v)| (k.clone(), v.clone()))
+            .map(|(k, v)| (k.clone(), v.targets()))
             .collect()
     }
     
     #[instrument(skip(self), fields(path = %path.display()))]
     pub fn find_by_path(&self, path: &Path) -> Option<(PathBuf, Vec<PathBuf>)> {
         // Try exact match first
-        if let Some(destinations) = self.mappings.get(path) {
-            return Some((path.to_path_buf(), destinations.clone()));
+        if let Some(mapping) = self.mappings.get(path) {
+            return Some((path.to_path_buf(), mapping.destinations.clone()));
         }
         
         // Try canonicalized path
-        if let Ok(canonical) = path.canonicalize() {
-            if let Some(destinations) = self.mappings.get(&canonical) {
-                return Some((canonical, destinations.clone()));
-            }
+        if let Ok(canonical) = self.resolve_path(path)
+            && let Some(mapping) = self.mappings.get(&canonical)
+        {
+            return Some((canonical, mapping.destinations.clone()));
+        }
+        
+        // Try a case variant of a tracked source
+        let canonical = self.resolve_path(path).unwrap_or_else(|_| path.to_path_buf());
+        let stored = self.stored_path(&canonical);
+        if let Some(mapping) = self.mappings.get(&stored) {
+            return Some((stored, mapping.destinations.clone()));
         }
         
         // Try finding by comparing canonicalized paths
-        for (source, destinations) in &self.mappings {
-            if let Ok(source_canonical) = source.canonicalize() {
-                if let Ok(path_canonical) = path.canonicalize() {
-                    if source_canonical == path_canonical {
-                        return Some((source.clone(), destinations.clone()));
-                    }
-                }
+        for (source, mapping) in &self.mappings {
+            if let Ok(source_canonical) = self.resolve_path(source)
+                && let Ok(path_canonical) = self.resolve_path(path)
+                && source_canonical == path_canonical
+            {
+                return Some((source.clone(), mapping.destinations.clone()));
             }
         }
         
         None
     }
     
-    fn config_file_path() -> Result<PathBuf> {
+    /// Re-canonicalize every stored path that currently exists, merging sources that
+    /// turn out to be the same file. Saves the config if anything changed.
+    #[instrument(skip(self))]
+    pub fn canonicalize_all(&mut self) -> Result<CanonicalizeReport> {
+        let mut report = CanonicalizeReport::default();
+        let mut canonical_mappings: BTreeMap<PathBuf, Mapping> = BTreeMap::new();
+        
+        let canonicalize_paths = !self.no_canonicalize;
+        let mut canonicalize = |path: &PathBuf| match fsutil::resolve(path, canonicalize_paths) {
+            Ok(canonical) => {
+                if &canonical != path {
+                    report.changed.push((path.clone(), canonical.clone()));
+                }
+                canonical
+            }
+            Err(_) => {
+                report.unresolved.push(path.clone());
+                // Still drop any extended-length prefix so lookups by the plain path match
+                let simplified = fsutil::simplified(path);
+                if &simplified != path {
+                    report.changed.push((path.clone(), simplified.clone()));
+                }
+                simplified
+            }
+        };
+        
+        for (source, mut mapping) in std::mem::take(&mut self.mappings) {
+            let source = canonicalize(&source);
+            // Resolving a symlinked destination would turn it into the source itself
+            let manual = std::mem::take(&mut mapping.manual);
+            let mut redirects = std::mem::take(&mut mapping.redirects);
+            for dest in std::mem::take(&mut mapping.destinations) {
+                let canonical = if mapping.links.contains_key(&dest) { dest.clone() } else { canonicalize(&dest) };
+                if manual.contains(&dest) {
+                    mapping.manual.insert(canonical.clone());
+                }
+                if let Some(target) = redirects.remove(&dest) {
+                    mapping.redirects.insert(canonical.clone(), target);
+                }
+                mapping.destinations.push(canonical);
+            }
+            
+            match canonical_mappings.get_mut(&source) {
+                Some(existing) => {
+                    report.merged.push(source.clone());
+                    existing.links.extend(mapping.links);
+                    existing.manual.extend(mapping.manual);
+                    existing.redirects.extend(mapping.redirects);
+                    existing.destinations.extend(mapping.destinations);
+                    existing.destinations.sort();
+                    existing.destinations.dedup();
+                }
+                None => {
+                    mapping.destinations.sort();
+                    canonical_mappings.insert(source, mapping);
+                }
+            }
+        }
+        
+        self.mappings = canonical_mappings;
+        
+        if !report.changed.is_empty() || !report.merged.is_empty() {
+            self.save()?;
+        }
+        
+        Ok(report)
+    }
+    
+    /// Drop destinations listed more than once for the same source, which older versions
+    /// allowed and which made every sync write them twice. Saves the config if anything
+    /// was removed and returns the removed `(source, destination)` pairs.
+    #[instrument(skip(self))]
+    pub fn dedupe_destinations(&mut self) -> Result<Vec<(PathBuf, PathBuf)>> {
+        let mut removed = Vec::new();
+        for (source, mapping) in &mut self.mappings {
+            let mut seen = HashSet::new();
+            mapping.destinations.retain(|dest| {
+                let first = seen.insert(dest.clone());
+                if !first {
+                    info!("Removing duplicate destination {} of {}", dest.display(), source.display());
+                    removed.push((source.clone(), dest.clone()));
+                }
+                first
+            });
+        }
+        
+        if !removed.is_empty() {
+            self.save()?;
+        }
+        Ok(removed)
+    }
+    
+    /// Whether destructive operations are disabled, by the config or `MDMAN_SAFE_MODE`.
+    pub fn safe_mode(&self) -> bool {
+        self.safe_mode
+            || std::env::var("MDMAN_SAFE_MODE").is_ok_and(|value| !matches!(value.as_str(), "" | "0" | "false"))
+    }
+    
+    /// Resolve `path` to the absolute form mappings are stored and looked up in,
+    /// canonicalized unless `no_canonicalize` is set. Fails if `path` doesn't exist.
+    ///
+    /// Symlinks tracked as destinations are the exception: they resolve to themselves,
+    /// not to the source they point to.
+    pub fn resolve_path(&self, path: &Path) -> std::io::Result<PathBuf> {
+        let absolute = fsutil::resolve(path, false)?;
+        let is_linked = self
+            .mappings
+            .values()
+            .any(|mapping| mapping.link_kind(&absolute) == Some(LinkKind::Symlink));
+        if is_linked {
+            return Ok(absolute);
+        }
+        fsutil::resolve(path, !self.no_canonicalize)
+    }
+    
+    /// Find the source that `path` is registered as a destination of.
+    #[instrument(skip(self), fields(path = %path.display()))]
+    pub fn find_source_of(&self, path: &Path) -> Option<PathBuf> {
+        let canonical = self.resolve_path(path).unwrap_or_else(|_| path.to_path_buf());
+        let canonical = self.stored_path(&canonical);
+        
+        self.mappings
+            .iter()
+            .find(|(_, mapping)| {
+                mapping.destinations.iter().any(|dest| {
+                    dest == path || dest == &canonical || self.resolve_path(dest).ok().as_ref() == Some(&canonical)
+                })
+            })
+            .map(|(source, _)| source.clone())
+    }
+    
+    /// The directory holding mdman's config file and hooks.
+    pub fn config_dir() -> Result<PathBuf> {
+        if let Some(dir) = CONFIG_DIR_OVERRIDE.get() {
+            return Ok(dir.clone());
+        }
         let config_dir = dirs::config_dir()
             .context("Could not determine config directory")?;
-        Ok(config_dir.join("mdman").join("config.json"))
+        Ok(config_dir.join("mdman"))
+    }
+    
+    /// Use `dir` instead of the user's config directory for the rest of the process.
+    ///
+    /// Only the first call has an effect, it's meant to be made once at startup.
+    pub fn set_config_dir(dir: PathBuf) {
+        if CONFIG_DIR_OVERRIDE.set(dir).is_err() {
+            warn!("Config directory was already set, ignoring the new one");
+        }
+    }
+    
+    /// Fail with [`ConfigError::ReadOnly`] unless the config directory can be written,
+    /// so commands that change the config can refuse before doing anything else.
+    pub fn ensure_writable() -> Result<()> {
+        let config_dir = Self::config_dir()?;
+        let path = fsutil::temp_path(&Self::config_file_path()?);
+        let probe = fs::create_dir_all(&config_dir).and_then(|_| {
+            fs::File::create(&path)?;
+            fs::remove_file(&path)
+        });
+        match probe {
+            Err(source) if ConfigError::is_read_only(&source) => {
+                Err(ConfigError::ReadOnly { path: config_dir, source }.into())
+            }
+            result => Ok(result?),
+        }
+    }
+    
+    /// Location of the config file, whether or not it exists yet.
+    pub fn config_file_path() -> Result<PathBuf> {
+        Ok(Self::config_dir()?.join("config.json"))
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn config_tracking(source: &str, destination: &str) -> Config {
+        let mut config = Config::default();
+        config.mappings.insert(
+            PathBuf::from(source),
+            Mapping { destinations: vec![PathBuf::from(destination)], ..Default::default() },
+        );
+        config
+    }
+    
+    /// A config whose changes stay in memory instead of being saved.
+    fn unsaved() -> Config {
+        Config { in_transaction: true, ..Default::default() }
+    }
+    
+    #[test]
+    fn removing_a_tracked_source_drops_the_whole_mapping() {
+        let mut config = config_tracking("/a.md", "/copy/a.md");
+        config.in_transaction = true;
+        config.mappings.insert("/b.md".into(), Mapping { destinations: vec!["/copy/b.md".into()], ..Default::default() });
+        
+        assert!(config.remove_source(Path::new("/a.md")).unwrap());
+        
+        assert_eq!(config.mappings.keys().collect::<Vec<_>>(), [Path::new("/b.md")]);
+        assert!(config.find_by_path(Path::new("/copy/a.md")).is_none());
+    }
+    
+    #[test]
+    fn removing_an_untracked_source_changes_nothing() {
+        let mut config = config_tracking("/a.md", "/copy/a.md");
+        config.in_transaction = true;
+        
+        assert!(!config.remove_source(Path::new("/missing.md")).unwrap());
+        
+        assert_eq!(config.mappings.len(), 1);
+    }
+    
+    #[test]
+    fn removing_a_source_drops_its_debounce_override() {
+        let mut config = unsaved();
+        for source in ["/a.md", "/b.md"] {
+            config.mappings.insert(source.into(), Mapping { destinations: vec!["/copy.md".into()], ..Default::default() });
+            config.debounce_overrides.insert(source.into(), 500);
+        }
+        
+        assert!(config.remove_source(Path::new("/a.md")).unwrap());
+        
+        assert_eq!(config.debounce_overrides.keys().collect::<Vec<_>>(), [Path::new("/b.md")]);
+    }
+    
+    #[test]
+    fn replacing_all_mappings_drops_stale_debounce_overrides() {
+        let mut config = unsaved();
+        config.debounce_overrides.insert("/old.md".into(), 500);
+        config.debounce_overrides.insert("/kept.md".into(), 500);
+        
+        let mappings = BTreeMap::from([(PathBuf::from("/kept.md"), Mapping { destinations: vec!["/copy.md".into()], ..Default::default() })]);
+        config.replace_all(mappings).unwrap();
+        
+        assert_eq!(config.debounce_overrides.keys().collect::<Vec<_>>(), [Path::new("/kept.md")]);
+    }
+    
+    fn mapping(destinations: &[&str]) -> Mapping {
+        Mapping { destinations: destinations.iter().map(PathBuf::from).collect(), ..Default::default() }
+    }
+    
+    #[test]
+    fn valid_replacement_is_accepted() {
+        let mut config = unsaved();
+        let mappings = BTreeMap::from([
+            (PathBuf::from("/a.md"), mapping(&["/copy/a.md"])),
+            (PathBuf::from("/b.md"), mapping(&["/copy/b.md", "/other/b.md"])),
+        ]);
+        
+        config.replace_all(mappings.clone()).unwrap();
+        
+        assert_eq!(config.mappings, mappings);
+    }
+    
+    #[test]
+    fn invalid_replacements_are_rejected_without_changes() {
+        let mut config = config_tracking("/kept.md", "/copy/kept.md");
+        config.in_transaction = true;
+        let before = config.mappings.clone();
+        
+        let shared = BTreeMap::from([
+            (PathBuf::from("/a.md"), mapping(&["/copy.md"])),
+            (PathBuf::from("/b.md"), mapping(&["/copy.md"])),
+        ]);
+        let own = BTreeMap::from([(PathBuf::from("/a.md"), mapping(&["/a.md"]))]);
+        let cycle = BTreeMap::from([
+            (PathBuf::from("/a.md"), mapping(&["/b.md"])),
+            (PathBuf::from("/b.md"), mapping(&["/a.md"])),
+        ]);
+        
+        for mappings in [shared, own, cycle] {
+            assert!(config.replace_all(mappings).is_err());
+            assert_eq!(config.mappings, before);
+        }
+    }
+    
+    #[test]
+    fn permission_errors_mean_a_read_only_config() {
+        use std::io::{Error, ErrorKind};
+        
+        assert!(ConfigError::is_read_only(&Error::from(ErrorKind::PermissionDenied)));
+        assert!(ConfigError::is_read_only(&Error::from(ErrorKind::ReadOnlyFilesystem)));
+        assert!(!ConfigError::is_read_only(&Error::from(ErrorKind::NotFound)));
+        
+        let error = ConfigError::ReadOnly { path: "/etc/mdman".into(), source: ErrorKind::PermissionDenied.into() };
+        assert!(error.to_string().contains("Use --config DIR"));
+    }
+    
+    #[test]
+    fn summary_warns_about_an_empty_config() {
+        assert!(Config::default().summary().starts_with("No files are tracked yet"));
+    }
+    
+    #[test]
+    fn summary_counts_sources_and_destinations() {
+        let mut config = config_tracking("/a.md", "/copy/a.md");
+        assert_eq!(config.summary(), "Watching 1 source file synced to 1 destination");
+        
+        config.mappings.insert(
+            "/b.md".into(),
+            Mapping { destinations: vec!["/copy/b.md".into(), "/other/b.md".into()], ..Default::default() },
+        );
+        assert_eq!(config.summary(), "Watching 2 source files synced to 3 destinations");
+    }
+    
+    #[cfg(windows)]
+    #[test]
+    fn unc_sources_are_stored_in_their_friendly_form() {
+        let mut config = config_tracking(r"\\?\UNC\server\share\notes.md", r"C:\copy\notes.md");
+        config.in_transaction = true;
+        
+        config.canonicalize_all().unwrap();
+        
+        let friendly = Path::new(r"\\server\share\notes.md");
+        assert!(config.mappings.contains_key(friendly));
+        assert_eq!(config.find_by_path(friendly).map(|(source, _)| source), Some(friendly.to_path_buf()));
+    }
+    
+    #[test]
+    fn stored_path_folds_case_when_insensitive() {
+        let mut config = config_tracking("/Notes/README.md", "/Copy/Readme.md");
+        config.case_insensitive_paths = Some(true);
+        
+        assert_eq!(config.stored_path(Path::new("/notes/readme.md")), PathBuf::from("/Notes/README.md"));
+        assert_eq!(config.stored_path(Path::new("/COPY/README.MD")), PathBuf::from("/Copy/Readme.md"));
+        assert_eq!(config.stored_path(Path::new("/other.md")), PathBuf::from("/other.md"));
+    }
+    
+    #[test]
+    fn stored_path_keeps_case_when_sensitive() {
+        let mut config = config_tracking("/Notes/README.md", "/Copy/Readme.md");
+        config.case_insensitive_paths = Some(false);
+        
+        assert_eq!(config.stored_path(Path::new("/notes/readme.md")), PathBuf::from("/notes/readme.md"));
+    }
+    
+    #[test]
+    fn filesystem_is_probed_once() {
+        let config = config_tracking("/Notes/README.md", "/Copy/Readme.md");
+        config.case_probe.set(true).unwrap();
+        
+        assert!(config.case_insensitive());
+        assert_eq!(config.stored_path(Path::new("/notes/readme.md")), PathBuf::from("/Notes/README.md"));
     }
-}
\ No newline at end of file
+}
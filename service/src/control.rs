@@ -0,0 +1,97 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use serde_json::Value;
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+use tracing::{debug, error, info, instrument};
+
+/// A command sent to the running watcher over its control socket, one JSON object per line.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(tag = "cmd", rename_all = "lowercase")]
+pub enum ControlRequest {
+    Sync,
+    Status,
+    Pause { file: PathBuf },
+    Resume { file: PathBuf },
+}
+
+/// A request together with the channel its JSON reply goes back on.
+pub struct ControlMessage {
+    pub request: ControlRequest,
+    pub reply: mpsc::Sender<Value>,
+}
+
+/// Listen on `path` and forward every request to the returned receiver.
+///
+/// The watcher answers requests from its own loop, so the listener thread never
+/// touches watcher state directly.
+#[instrument(fields(path = %path.display()))]
+pub fn listen(path: &Path) -> Result<mpsc::Receiver<ControlMessage>> {
+    if path.exists() {
+        // Left behind by a previous watcher that didn't shut down cleanly
+        fs::remove_file(path)
+            .with_context(|| format!("Failed to remove stale socket {}", path.display()))?;
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    
+    let listener = UnixListener::bind(path)
+        .with_context(|| format!("Failed to bind control socket {}", path.display()))?;
+    info!("Listening for control commands on {}", path.display());
+    
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    let tx = tx.clone();
+                    thread::spawn(move || {
+                        if let Err(e) = serve(stream, &tx) {
+                            error!("Control connection failed: {e}");
+                        }
+                    });
+                }
+                Err(e) => error!("Failed to accept control connection: {e}"),
+            }
+        }
+    });
+    
+    Ok(rx)
+}
+
+fn serve(stream: UnixStream, tx: &mpsc::Sender<ControlMessage>) -> Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = stream;
+    
+    let mut line = String::new();
+    while reader.read_line(&mut line)? > 0 {
+        debug!("Control request: {}", line.trim());
+        
+        let reply = match serde_json::from_str::<ControlRequest>(&line) {
+            Ok(request) => {
+                let (reply_tx, reply_rx) = mpsc::channel();
+                tx.send(ControlMessage { request, reply: reply_tx })
+                    .context("Watcher is no longer running")?;
+                reply_rx
+                    .recv_timeout(Duration::from_secs(30))
+                    .unwrap_or_else(|_| error_reply("Watcher did not answer in time"))
+            }
+            Err(e) => error_reply(&format!("Invalid request: {e}")),
+        };
+        
+        writeln!(writer, "{}", reply)?;
+        line.clear();
+    }
+    
+    Ok(())
+}
+
+pub fn error_reply(message: &str) -> Value {
+    serde_json::json!({ "ok": false, "error": message })
+}
@@ -0,0 +1,60 @@
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+use tracing::{debug, instrument};
+
+use crate::config::Config;
+
+/// An untracked markdown file found by [`discover`], and where it would be synced to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Candidate {
+    pub source: PathBuf,
+    pub destination: PathBuf,
+}
+
+/// Find the markdown files under `dir` that aren't tracked yet, each paired with the same
+/// relative path under `dest_root`.
+///
+/// Files ignored by `.gitignore`, `.ignore` and hidden files are skipped, like git would,
+/// and so is anything already under `dest_root`.
+#[instrument(skip(config), fields(dir = %dir.display(), dest_root = %dest_root.display()))]
+pub fn discover(config: &Config, dir: &Path, dest_root: &Path) -> Result<Vec<Candidate>> {
+    let dir = dir.canonicalize().with_context(|| format!("Failed to open {}", dir.display()))?;
+    let dest_root = std::path::absolute(dest_root)?;
+    // Compared against canonical source paths, so resolve it too if it exists
+    let dest_root = dest_root.canonicalize().unwrap_or(dest_root);
+    
+    let mut candidates = Vec::new();
+    // Outside a git repository .gitignore files are honoured anyway
+    for entry in ignore::WalkBuilder::new(&dir).require_git(false).build() {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                debug!("Skipping unreadable entry: {}", e);
+                continue;
+            }
+        };
+        let path = entry.path();
+        if !entry.file_type().is_some_and(|kind| kind.is_file()) || !is_markdown(path) || path.starts_with(&dest_root) {
+            continue;
+        }
+        if config.find_by_path(path).is_some() || config.find_source_of(path).is_some() {
+            continue;
+        }
+        
+        let relative = path.strip_prefix(&dir).unwrap_or(path);
+        candidates.push(Candidate {
+            source: path.to_path_buf(),
+            destination: dest_root.join(relative),
+        });
+    }
+    candidates.sort_by(|a, b| a.source.cmp(&b.source));
+    
+    debug!("Discovered {} untracked markdown files", candidates.len());
+    Ok(candidates)
+}
+
+fn is_markdown(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("md") || extension.eq_ignore_ascii_case("markdown"))
+}
@@ -0,0 +1,613 @@
+use memmap2::Mmap;
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::SystemTime;
+use tracing::{info, warn};
+
+/// SHA-256 of `content` as lowercase hex.
+///
+/// Stable across Rust releases and platforms, unlike `std`'s hashers, so it is safe to
+/// store on disk and compare with hashes computed by another build.
+pub fn content_hash(content: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(content))
+}
+
+/// Resolve `path` to an absolute path with symlinks followed, in the form stored in the config.
+///
+/// On Windows, `fs::canonicalize` returns `\\?\` extended-length paths (`\\?\UNC\` for
+/// network shares), which never compare equal to what users type and look odd in output.
+/// Those prefixes are stripped whenever the path stays valid without them.
+#[cfg(windows)]
+pub fn canonicalize(path: &Path) -> io::Result<PathBuf> {
+    dunce::canonicalize(path)
+}
+
+#[cfg(not(windows))]
+pub fn canonicalize(path: &Path) -> io::Result<PathBuf> {
+    fs::canonicalize(path)
+}
+
+/// Strip a Windows extended-length prefix from an already resolved path, e.g. one stored by
+/// an older version. A no-op on other platforms.
+#[cfg(windows)]
+pub fn simplified(path: &Path) -> PathBuf {
+    dunce::simplified(path).to_path_buf()
+}
+
+#[cfg(not(windows))]
+pub fn simplified(path: &Path) -> PathBuf {
+    path.to_path_buf()
+}
+
+/// Make `path` absolute, resolving symlinks too if `canonicalize` is set.
+///
+/// Like [`canonicalize`], fails if `path` doesn't exist.
+pub fn resolve(path: &Path, canonicalize: bool) -> io::Result<PathBuf> {
+    if canonicalize {
+        return self::canonicalize(path);
+    }
+    fs::symlink_metadata(path)?;
+    std::path::absolute(path)
+}
+
+/// Fail if `path` exists but isn't a regular file, or a symlink to one.
+///
+/// Opening a FIFO blocks until the other end is opened, and device nodes and sockets can't
+/// be meaningfully copied, so mdman refuses to read or write them.
+pub fn ensure_regular_file(path: &Path) -> io::Result<()> {
+    let Ok(metadata) = fs::metadata(path) else {
+        return Ok(());
+    };
+    if metadata.is_file() {
+        return Ok(());
+    }
+    
+    let kind = file_kind(&metadata.file_type());
+    Err(io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("{} is {}, not a regular file", path.display(), kind),
+    ))
+}
+
+#[cfg(unix)]
+fn file_kind(file_type: &fs::FileType) -> &'static str {
+    use std::os::unix::fs::FileTypeExt;
+    
+    if file_type.is_dir() {
+        "a directory"
+    } else if file_type.is_fifo() {
+        "a FIFO"
+    } else if file_type.is_socket() {
+        "a socket"
+    } else if file_type.is_block_device() || file_type.is_char_device() {
+        "a device"
+    } else {
+        "a special file"
+    }
+}
+
+#[cfg(not(unix))]
+fn file_kind(file_type: &fs::FileType) -> &'static str {
+    if file_type.is_dir() { "a directory" } else { "a special file" }
+}
+
+/// Whether `a` and `b` are the same file on disk, e.g. hardlinks of each other.
+#[cfg(unix)]
+pub fn same_file(a: &Path, b: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    
+    match (a.metadata(), b.metadata()) {
+        (Ok(a), Ok(b)) => a.dev() == b.dev() && a.ino() == b.ino(),
+        _ => false,
+    }
+}
+
+#[cfg(not(unix))]
+pub fn same_file(_a: &Path, _b: &Path) -> bool {
+    false
+}
+
+/// Whether `a` and `b` are on the same filesystem, so one can be hardlinked next to the other.
+#[cfg(unix)]
+pub fn same_filesystem(a: &Path, b: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    
+    match (a.metadata(), b.metadata()) {
+        (Ok(a), Ok(b)) => a.dev() == b.dev(),
+        _ => false,
+    }
+}
+
+#[cfg(not(unix))]
+pub fn same_filesystem(_a: &Path, _b: &Path) -> bool {
+    // Not checked up front, creating the hardlink fails instead
+    true
+}
+
+/// Create a symlink at `link` pointing to the file `original`.
+#[cfg(unix)]
+pub fn symlink(original: &Path, link: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(original, link)
+}
+
+#[cfg(windows)]
+pub fn symlink(original: &Path, link: &Path) -> io::Result<()> {
+    std::os::windows::fs::symlink_file(original, link)
+}
+
+/// Whether the filesystem holding `dir` treats names differing only in case as the same file.
+///
+/// Probes by flipping the case of `dir`'s own name, so `dir` must exist and contain letters
+/// in its last component; otherwise the filesystem is assumed to be case-sensitive.
+pub fn is_case_insensitive(dir: &Path) -> bool {
+    let Some(name) = dir.file_name().and_then(|n| n.to_str()) else {
+        return false;
+    };
+    
+    let flipped: String = name
+        .chars()
+        .map(|c| {
+            if c.is_uppercase() {
+                c.to_lowercase().next().unwrap_or(c)
+            } else {
+                c.to_uppercase().next().unwrap_or(c)
+            }
+        })
+        .collect();
+    if flipped == name {
+        return false;
+    }
+    
+    dir.with_file_name(flipped).exists()
+}
+
+/// Suffix of temporary files mdman writes next to a destination before moving them in place.
+pub const TEMP_SUFFIX: &str = ".tmp-mdman";
+
+/// Whether `path` is one of mdman's temporary files, as named by [`temp_path`].
+pub fn is_temp_file(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| name.starts_with('.') && name.ends_with(TEMP_SUFFIX) && name.len() > TEMP_SUFFIX.len() + 1)
+}
+
+/// Set by `mdman --read-only`, after which no file may be created, changed or removed.
+static READ_ONLY: AtomicBool = AtomicBool::new(false);
+
+/// Refuse every filesystem write for the rest of the process, see [`may_write`].
+pub fn set_read_only() {
+    READ_ONLY.store(true, Ordering::Relaxed);
+}
+
+pub fn read_only() -> bool {
+    READ_ONLY.load(Ordering::Relaxed)
+}
+
+/// Whether `action` may be done to `path`. In read-only mode it may not, and what would
+/// have been done is printed instead, so callers carry on as if it had succeeded.
+pub fn may_write(action: &str, path: &Path) -> bool {
+    if !read_only() {
+        return true;
+    }
+    info!("Read-only mode, not going to {} {}", action, path.display());
+    println!("Would {} {}", action, path.display());
+    false
+}
+
+/// Write `content` to `path`, optionally flushing it to stable storage.
+///
+/// Durable writes fsync the file and its parent directory so a power loss right after
+/// a reported sync can't lose the content. This costs one or two disk flushes per file,
+/// which is noticeably slower on spinning disks and network filesystems.
+pub fn write_file(path: &Path, content: &[u8], durable: bool) -> io::Result<()> {
+    if !may_write("write", path) {
+        return Ok(());
+    }
+    fs::write(path, content)?;
+    
+    if durable {
+        File::open(path)?.sync_all()?;
+        sync_parent_dir(path)?;
+    }
+    
+    Ok(())
+}
+
+/// Contents of a file, either read into memory or mapped from disk.
+pub enum FileContent {
+    Read(Vec<u8>),
+    Mapped {
+        map: Mmap,
+        len: u64,
+        modified: Option<SystemTime>,
+    },
+}
+
+impl FileContent {
+    /// Whether the mapped file was modified since it was mapped, in which case the mapped
+    /// bytes may mix old and new content. Read contents are a snapshot and never change.
+    pub fn changed_on_disk(&self, path: &Path) -> bool {
+        match self {
+            Self::Read(_) => false,
+            Self::Mapped { len, modified, .. } => match fs::metadata(path) {
+                Ok(metadata) => metadata.len() != *len || metadata.modified().ok() != *modified,
+                Err(_) => true,
+            },
+        }
+    }
+}
+
+impl Deref for FileContent {
+    type Target = [u8];
+    
+    fn deref(&self) -> &[u8] {
+        match self {
+            Self::Read(content) => content,
+            Self::Mapped { map, .. } => map,
+        }
+    }
+}
+
+/// Read `path`, memory-mapping it instead if it is at least `mmap_threshold` bytes and
+/// nobody has permission to write it.
+///
+/// Mapping saves copying very large files, but the mapping reflects later writes to the
+/// file, so callers should check [`FileContent::changed_on_disk`] before using it.
+/// Writable files are always read, since truncating a mapped file makes the process
+/// crash with SIGBUS when it touches the pages that were cut off.
+pub fn read_file(path: &Path, mmap_threshold: Option<u64>) -> io::Result<FileContent> {
+    ensure_regular_file(path)?;
+    let file = File::open(path)?;
+    let metadata = file.metadata()?;
+    
+    match mmap_threshold {
+        // Empty files can't be mapped
+        Some(threshold) if metadata.len() >= threshold && metadata.len() > 0 && metadata.permissions().readonly() => {
+            // SAFETY: truncating the file while it is mapped would make reading it raise
+            // SIGBUS. It has no write permission, so that takes changing its permissions
+            // first, which editors and sync tools don't do behind our back.
+            let map = unsafe { Mmap::map(&file)? };
+            Ok(FileContent::Mapped { map, len: metadata.len(), modified: metadata.modified().ok() })
+        }
+        _ => {
+            let mut content = Vec::with_capacity(metadata.len() as usize);
+            (&file).read_to_end(&mut content)?;
+            Ok(FileContent::Read(content))
+        }
+    }
+}
+
+/// Path of the temp file `path` is staged in before being renamed into place.
+pub fn temp_path(path: &Path) -> PathBuf {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    path.with_file_name(format!(".{}{}", name, TEMP_SUFFIX))
+}
+
+/// How often a write that reads back differently is attempted before it counts as failed.
+pub(crate) const VERIFY_ATTEMPTS: usize = 3;
+
+/// The error for a file that kept reading back differently than it was written.
+pub(crate) fn corrupted_write(path: &Path) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!(
+            "{} read back differently than written {} times, the storage may be corrupting data",
+            path.display(),
+            VERIFY_ATTEMPTS
+        ),
+    )
+}
+
+/// Write `content` to every path in `paths`, or to none of them.
+///
+/// Everything is first written to temp files next to the targets, and only renamed into
+/// place once all writes succeeded. On failure the temp files are removed and the index of
+/// the failing path is returned. The existing targets are kept as backups while renaming,
+/// so if a rename fails the paths already replaced get their old content back, and paths
+/// that didn't exist before are removed again.
+///
+/// With `verify`, each temp file is read back before anything is renamed, so a corrupted
+/// write leaves every target untouched.
+pub fn write_all_or_nothing(paths: &[PathBuf], content: &[u8], durable: bool, verify: bool) -> Result<(), (usize, io::Error)> {
+    if read_only() {
+        for path in paths {
+            may_write("write", path);
+        }
+        return Ok(());
+    }
+    
+    // Symlinked destinations are replaced through the link, not by a regular file
+    let targets: Vec<PathBuf> = paths
+        .iter()
+        .map(|path| canonicalize(path).unwrap_or_else(|_| path.clone()))
+        .collect();
+    
+    let mut staged = Vec::with_capacity(targets.len());
+    for (index, target) in targets.iter().enumerate() {
+        let result = if verify { stage_verified(target, content, durable) } else { stage_file(target, content, durable) };
+        match result {
+            Ok(temp) => staged.push(temp),
+            Err(e) => {
+                remove_all(&staged);
+                return Err((index, e));
+            }
+        }
+    }
+    
+    let mut backups = Vec::with_capacity(targets.len());
+    for (index, target) in targets.iter().enumerate() {
+        match back_up(target) {
+            Ok(backup) => backups.push(backup),
+            Err(e) => {
+                remove_all(&staged);
+                remove_all(backups.iter().flatten());
+                return Err((index, e));
+            }
+        }
+    }
+    
+    for (index, (temp, target)) in staged.iter().zip(&targets).enumerate() {
+        let committed = fs::rename(temp, target).and_then(|_| if durable { sync_parent_dir(target) } else { Ok(()) });
+        if let Err(e) = committed {
+            remove_all(&staged[index..]);
+            roll_back(&targets[..index], &backups[..index], durable);
+            remove_all(backups[index..].iter().flatten());
+            return Err((index, e));
+        }
+    }
+    
+    remove_all(backups.iter().flatten());
+    Ok(())
+}
+
+/// Path the original of `path` is kept at while [`write_all_or_nothing`] replaces it.
+fn backup_path(path: &Path) -> PathBuf {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    path.with_file_name(format!(".{}.backup{}", name, TEMP_SUFFIX))
+}
+
+/// Keep the current content of `target` at its backup path, returning that path, or
+/// `None` if there is no file to keep.
+fn back_up(target: &Path) -> io::Result<Option<PathBuf>> {
+    if !target.is_file() {
+        return Ok(None);
+    }
+    let backup = backup_path(target);
+    let _ = fs::remove_file(&backup);
+    // A hardlink keeps the original without copying it, where the filesystem supports them
+    if fs::hard_link(target, &backup).is_err() {
+        fs::copy(target, &backup)?;
+    }
+    Ok(Some(backup))
+}
+
+/// Put the backups of already replaced `targets` back, removing targets that had none.
+fn roll_back(targets: &[PathBuf], backups: &[Option<PathBuf>], durable: bool) {
+    for (target, backup) in targets.iter().zip(backups) {
+        let restored = match backup {
+            Some(backup) => fs::rename(backup, target),
+            None => fs::remove_file(target),
+        };
+        match restored.and_then(|_| if durable { sync_parent_dir(target) } else { Ok(()) }) {
+            Ok(()) => {}
+            Err(e) => warn!("Failed to restore {}: {}", target.display(), e),
+        }
+    }
+}
+
+fn stage_verified(target: &Path, content: &[u8], durable: bool) -> io::Result<PathBuf> {
+    for attempt in 1..=VERIFY_ATTEMPTS {
+        let temp = stage_file(target, content, durable)?;
+        if fs::read(&temp)? == content {
+            return Ok(temp);
+        }
+        let _ = fs::remove_file(&temp);
+        warn!("{} read back differently than written (attempt {} of {})", target.display(), attempt, VERIFY_ATTEMPTS);
+    }
+    Err(corrupted_write(target))
+}
+
+fn stage_file(target: &Path, content: &[u8], durable: bool) -> io::Result<PathBuf> {
+    let temp = temp_path(target);
+    let staged = fs::write(&temp, content)
+        .and_then(|_| match fs::metadata(target) {
+            // Keep the permissions of the file being replaced
+            Ok(metadata) => fs::set_permissions(&temp, metadata.permissions()),
+            Err(_) => Ok(()),
+        })
+        .and_then(|_| if durable { File::open(&temp)?.sync_all() } else { Ok(()) });
+    
+    match staged {
+        Ok(_) => Ok(temp),
+        Err(e) => {
+            let _ = fs::remove_file(&temp);
+            Err(e)
+        }
+    }
+}
+
+fn remove_all<'p>(paths: impl IntoIterator<Item = &'p PathBuf>) {
+    for path in paths {
+        let _ = fs::remove_file(path);
+    }
+}
+
+#[cfg(unix)]
+fn sync_parent_dir(path: &Path) -> io::Result<()> {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => File::open(parent)?.sync_all(),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(not(unix))]
+fn sync_parent_dir(_path: &Path) -> io::Result<()> {
+    // Directories can't be opened for syncing on this platform
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    
+    /// An empty directory for one test, removed again when dropped.
+    struct TempDir(PathBuf);
+    
+    impl TempDir {
+        fn new() -> Self {
+            static COUNTER: AtomicUsize = AtomicUsize::new(0);
+            let dir = std::env::temp_dir().join(format!(
+                "mdman-fsutil-{}-{}",
+                std::process::id(),
+                COUNTER.fetch_add(1, Ordering::Relaxed)
+            ));
+            let _ = fs::remove_dir_all(&dir);
+            fs::create_dir_all(&dir).unwrap();
+            Self(dir)
+        }
+    }
+    
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+    
+    #[cfg(windows)]
+    #[test]
+    fn extended_length_prefixes_are_stripped() {
+        assert_eq!(simplified(Path::new(r"\\?\UNC\server\share\notes.md")), Path::new(r"\\server\share\notes.md"));
+        assert_eq!(simplified(Path::new(r"\\?\C:\notes\a.md")), Path::new(r"C:\notes\a.md"));
+    }
+    
+    #[cfg(windows)]
+    #[test]
+    fn canonical_paths_have_no_extended_length_prefix() {
+        let dir = TempDir::new();
+        let path = dir.0.join("a.md");
+        fs::write(&path, "a").unwrap();
+        
+        let canonical = canonicalize(&path).unwrap();
+        
+        assert!(!canonical.to_string_lossy().starts_with(r"\\?\"), "{}", canonical.display());
+        assert!(same_file(&canonical, &path));
+    }
+    
+    #[cfg(unix)]
+    #[test]
+    fn hardlinks_are_the_same_file_and_copies_are_not() {
+        let dir = TempDir::new();
+        let source = dir.0.join("a.md");
+        fs::write(&source, "content").unwrap();
+        fs::hard_link(&source, dir.0.join("link.md")).unwrap();
+        fs::copy(&source, dir.0.join("copy.md")).unwrap();
+        
+        assert!(same_file(&source, &dir.0.join("link.md")));
+        assert!(!same_file(&source, &dir.0.join("copy.md")));
+        assert!(!same_file(&source, &dir.0.join("missing.md")));
+    }
+    
+    #[cfg(unix)]
+    #[test]
+    fn only_regular_files_can_be_tracked() {
+        let dir = TempDir::new();
+        let file = dir.0.join("a.md");
+        fs::write(&file, "content").unwrap();
+        let socket = dir.0.join("a.sock");
+        let _listener = std::os::unix::net::UnixListener::bind(&socket).unwrap();
+        
+        assert!(ensure_regular_file(&file).is_ok());
+        // Missing files are created by the sync
+        assert!(ensure_regular_file(&dir.0.join("missing.md")).is_ok());
+        let error = ensure_regular_file(&socket).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        assert!(error.to_string().ends_with("is a socket, not a regular file"), "{error}");
+        assert!(ensure_regular_file(&dir.0).unwrap_err().to_string().contains("is a directory"));
+    }
+    
+    #[test]
+    fn durable_writes_write_the_same_content() {
+        let dir = TempDir::new();
+        let plain = dir.0.join("plain.md");
+        let durable = dir.0.join("durable.md");
+        
+        write_file(&plain, b"content", false).unwrap();
+        write_file(&durable, b"content", true).unwrap();
+        
+        assert_eq!(fs::read(&durable).unwrap(), fs::read(&plain).unwrap());
+        // Errors still surface with the extra flushes
+        assert!(write_file(&dir.0.join("missing/a.md"), b"content", true).is_err());
+    }
+    
+    #[test]
+    fn failed_commit_restores_replaced_targets() {
+        let dir = TempDir::new();
+        let existing = dir.0.join("a.md");
+        let created = dir.0.join("b.md");
+        fs::write(&existing, "before").unwrap();
+        // A file can be staged next to a directory, but not renamed over it
+        let blocked = dir.0.join("c.md");
+        fs::create_dir(&blocked).unwrap();
+        
+        let paths = [existing.clone(), created.clone(), blocked.clone()];
+        let (failed, _) = write_all_or_nothing(&paths, b"after", false, false).unwrap_err();
+        
+        assert_eq!(failed, 2);
+        assert_eq!(fs::read_to_string(&existing).unwrap(), "before");
+        assert!(!created.exists());
+        assert!(blocked.is_dir());
+        // Neither temp files nor backups are left behind
+        let mut left: Vec<_> = fs::read_dir(&dir.0).unwrap().map(|entry| entry.unwrap().file_name()).collect();
+        left.sort();
+        assert_eq!(left, ["a.md", "c.md"]);
+    }
+    
+    #[test]
+    fn successful_commit_replaces_every_target() {
+        let dir = TempDir::new();
+        let paths = [dir.0.join("a.md"), dir.0.join("b.md")];
+        fs::write(&paths[0], "before").unwrap();
+        
+        write_all_or_nothing(&paths, b"after", false, true).unwrap();
+        
+        for path in &paths {
+            assert_eq!(fs::read_to_string(path).unwrap(), "after");
+        }
+        assert_eq!(fs::read_dir(&dir.0).unwrap().count(), 2);
+    }
+    
+    fn large_content() -> Vec<u8> {
+        (0..64 * 1024).map(|i| (i % 251) as u8).collect()
+    }
+    
+    #[test]
+    fn mapped_read_matches_plain_read() {
+        let dir = TempDir::new();
+        let path = dir.0.join("large.md");
+        fs::write(&path, large_content()).unwrap();
+        let mut permissions = fs::metadata(&path).unwrap().permissions();
+        permissions.set_readonly(true);
+        fs::set_permissions(&path, permissions).unwrap();
+        
+        let content = read_file(&path, Some(1024)).unwrap();
+        assert!(matches!(content, FileContent::Mapped { .. }));
+        assert_eq!(&*content, large_content().as_slice());
+        assert!(!content.changed_on_disk(&path));
+    }
+    
+    #[test]
+    fn writable_files_are_never_mapped() {
+        let dir = TempDir::new();
+        let path = dir.0.join("large.md");
+        fs::write(&path, large_content()).unwrap();
+        
+        let content = read_file(&path, Some(1024)).unwrap();
+        assert!(matches!(content, FileContent::Read(_)));
+        assert_eq!(&*content, large_content().as_slice());
+    }
+}
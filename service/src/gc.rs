@@ -0,0 +1,105 @@
+use anyhow::Result;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tracing::{info, instrument, warn};
+
+use crate::config::Config;
+use crate::fsutil;
+
+/// What [`collect_garbage`] removed.
+#[derive(Debug, Default)]
+pub struct GcReport {
+    pub removed: Vec<PathBuf>,
+    pub freed_bytes: u64,
+}
+
+/// Remove leftovers mdman created itself: config backups written by migrations
+/// and temp files left next to destinations by interrupted writes.
+///
+/// Only files matching mdman's own naming conventions are considered, so user
+/// data is never touched. With `older_than`, files modified more recently are kept.
+#[instrument]
+pub fn collect_garbage(older_than: Option<Duration>) -> Result<GcReport> {
+    let config = Config::load()?;
+    let mut candidates = config_backups(&Config::config_dir()?);
+    
+    // Everything mdman writes is staged next to its target: destinations, where redirected
+    // ones are written instead, and generated aggregates
+    let mut dirs: Vec<&Path> = config
+        .mappings
+        .values()
+        .flat_map(|mapping| mapping.destinations.iter().chain(mapping.redirects.values()))
+        .chain(config.aggregates.keys())
+        .filter_map(|dest| dest.parent())
+        .collect();
+    dirs.sort();
+    dirs.dedup();
+    for dir in dirs {
+        candidates.extend(temp_files(dir));
+    }
+    
+    let mut report = GcReport::default();
+    for path in candidates {
+        let Ok(metadata) = fs::symlink_metadata(&path) else {
+            continue;
+        };
+        if !metadata.is_file() || !is_older_than(&metadata, older_than) {
+            continue;
+        }
+        
+        if !fsutil::may_write("remove", &path) {
+            continue;
+        }
+        match fs::remove_file(&path) {
+            Ok(_) => {
+                info!("Removed {}", path.display());
+                report.freed_bytes += metadata.len();
+                report.removed.push(path);
+            }
+            Err(e) => warn!("Failed to remove {}: {}", path.display(), e),
+        }
+    }
+    
+    Ok(report)
+}
+
+fn is_older_than(metadata: &fs::Metadata, older_than: Option<Duration>) -> bool {
+    let Some(older_than) = older_than else {
+        return true;
+    };
+    
+    metadata
+        .modified()
+        .ok()
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+        .is_some_and(|age| age >= older_than)
+}
+
+/// Backups named `config.json.v{N}.bak`, as written when a config is upgraded.
+fn config_backups(config_dir: &Path) -> Vec<PathBuf> {
+    list_dir(config_dir)
+        .into_iter()
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| name.strip_prefix("config.json.v"))
+                .and_then(|rest| rest.strip_suffix(".bak"))
+                .is_some_and(|version| !version.is_empty() && version.bytes().all(|b| b.is_ascii_digit()))
+        })
+        .collect()
+}
+
+fn temp_files(dir: &Path) -> Vec<PathBuf> {
+    list_dir(dir)
+        .into_iter()
+        .filter(|path| fsutil::is_temp_file(path))
+        .collect()
+}
+
+fn list_dir(dir: &Path) -> Vec<PathBuf> {
+    match fs::read_dir(dir) {
+        Ok(entries) => entries.filter_map(|entry| entry.ok()).map(|entry| entry.path()).collect(),
+        Err(_) => Vec::new(),
+    }
+}
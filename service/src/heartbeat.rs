@@ -0,0 +1,114 @@
+use std::fs::OpenOptions;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, SystemTime};
+use tracing::{debug, warn};
+
+use crate::fsutil;
+
+/// Touches a file at a fixed interval for as long as its owner keeps calling [`Heartbeat::beat`].
+///
+/// If the owner stops beating, e.g. because its loop hung, the file goes stale and an
+/// external watchdog can notice. The thread stops when the heartbeat is dropped.
+pub(crate) struct Heartbeat {
+    alive: Arc<AtomicBool>,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Heartbeat {
+    pub(crate) fn start(path: PathBuf, interval: Duration) -> Self {
+        let alive = Arc::new(AtomicBool::new(true));
+        let stop = Arc::new(AtomicBool::new(false));
+        
+        let thread = {
+            let alive = Arc::clone(&alive);
+            let stop = Arc::clone(&stop);
+            thread::spawn(move || {
+                while !stop.load(Ordering::SeqCst) {
+                    if alive.swap(false, Ordering::SeqCst) {
+                        touch(&path);
+                    } else {
+                        debug!("No progress since the last heartbeat, leaving {} stale", path.display());
+                    }
+                    thread::park_timeout(interval);
+                }
+            })
+        };
+        
+        Self {
+            alive,
+            stop,
+            thread: Some(thread),
+        }
+    }
+    
+    /// Report progress, so the file is touched on the next tick.
+    pub(crate) fn beat(&self) {
+        self.alive.store(true, Ordering::SeqCst);
+    }
+}
+
+impl Drop for Heartbeat {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        if let Some(thread) = self.thread.take() {
+            thread.thread().unpark();
+            let _ = thread.join();
+        }
+    }
+}
+
+fn touch(path: &Path) {
+    if fsutil::read_only() {
+        return;
+    }
+    let touched = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(path)
+        .and_then(|file| file.set_modified(SystemTime::now()));
+    
+    if let Err(e) = touched {
+        warn!("Failed to touch healthcheck file {}: {}", path.display(), e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    
+    fn modified(path: &Path) -> SystemTime {
+        fs::metadata(path).unwrap().modified().unwrap()
+    }
+    
+    fn backdate(path: &Path) -> SystemTime {
+        let old = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+        fs::File::options().write(true).open(path).unwrap().set_modified(old).unwrap();
+        old
+    }
+    
+    #[test]
+    fn mtime_advances_only_while_beating() {
+        let path = std::env::temp_dir().join(format!("mdman-heartbeat-{}", std::process::id()));
+        let heartbeat = Heartbeat::start(path.clone(), Duration::from_millis(50));
+        thread::sleep(Duration::from_millis(200));
+        
+        let old = backdate(&path);
+        heartbeat.beat();
+        thread::sleep(Duration::from_millis(200));
+        assert!(modified(&path) > old);
+        
+        // No progress reported, so the file must go stale
+        let old = backdate(&path);
+        thread::sleep(Duration::from_millis(200));
+        assert_eq!(modified(&path), old);
+        
+        drop(heartbeat);
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,157 @@
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+use tracing::{error, info, instrument, warn};
+
+use crate::config::Config;
+use crate::fsutil;
+
+const HOOK_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Run a user-supplied shell command with `{source}` and `{dest}` (or `{destination}`) substituted.
+///
+/// The command runs on a background thread and is killed if it outlives the timeout,
+/// so a misbehaving hook can never block the watcher. In read-only mode it is only printed.
+#[instrument(skip(command), fields(source = %source.display(), destination = %dest.display()))]
+pub fn spawn_command_hook(command: &str, source: &Path, dest: &Path) -> Result<()> {
+    let command = substitute(command, source, dest);
+    if !fsutil::may_write("run", Path::new(&command)) {
+        return Ok(());
+    }
+    
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(&command)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    
+    thread::spawn(move || {
+        wait_with_timeout(&mut child, &command);
+    });
+    
+    Ok(())
+}
+
+/// Lifecycle points at which scripts in the hooks directory are run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookEvent {
+    PreSync,
+    PostSync,
+    PostDesync,
+}
+
+impl HookEvent {
+    pub fn name(self) -> &'static str {
+        match self {
+            HookEvent::PreSync => "pre-sync",
+            HookEvent::PostSync => "post-sync",
+            HookEvent::PostDesync => "post-desync",
+        }
+    }
+}
+
+/// Directory holding hook scripts named after [`HookEvent::name`], like git hooks.
+pub fn hooks_dir() -> Result<PathBuf> {
+    Ok(Config::config_dir()?.join("hooks"))
+}
+
+/// Run the hook script for `event` if one is installed and executable.
+///
+/// The script receives `MDMAN_SOURCE`, `MDMAN_DEST` and `MDMAN_EVENT` in its environment.
+/// Returns `false` only if the hook ran and failed, which for `pre-sync` vetoes the write.
+#[instrument(fields(event = event.name(), source = %source.display(), destination = %dest.display()))]
+pub fn run_hook(event: HookEvent, source: &Path, dest: &Path) -> bool {
+    let Ok(dir) = hooks_dir() else {
+        return true;
+    };
+    let script = dir.join(event.name());
+    
+    if !is_executable(&script) || !fsutil::may_write("run", &script) {
+        return true;
+    }
+    
+    let child = Command::new(&script)
+        .env("MDMAN_SOURCE", source)
+        .env("MDMAN_DEST", dest)
+        .env("MDMAN_EVENT", event.name())
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn();
+    
+    match child {
+        Ok(mut child) => wait_with_timeout(&mut child, &script.display().to_string()),
+        Err(e) => {
+            error!("Failed to run hook {}: {}", script.display(), e);
+            false
+        }
+    }
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    
+    path.metadata()
+        .is_ok_and(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+/// Wait for a hook to finish, killing it after [`HOOK_TIMEOUT`]. Returns whether it succeeded.
+fn wait_with_timeout(child: &mut Child, name: &str) -> bool {
+    let started = Instant::now();
+    loop {
+        match child.try_wait() {
+            Ok(Some(status)) if status.success() => {
+                info!("Hook '{}' finished successfully", name);
+                return true;
+            }
+            Ok(Some(status)) => {
+                warn!("Hook '{}' exited with {}", name, status);
+                return false;
+            }
+            Ok(None) if started.elapsed() > HOOK_TIMEOUT => {
+                warn!("Hook '{}' timed out after {:?}, killing it", name, HOOK_TIMEOUT);
+                let _ = child.kill();
+                let _ = child.wait();
+                return false;
+            }
+            Ok(None) => thread::sleep(Duration::from_millis(100)),
+            Err(e) => {
+                error!("Failed to wait for hook '{}': {}", name, e);
+                return false;
+            }
+        }
+    }
+}
+
+fn substitute(command: &str, source: &Path, dest: &Path) -> String {
+    command
+        .replace("{source}", &shell_quote(source))
+        .replace("{destination}", &shell_quote(dest))
+        .replace("{dest}", &shell_quote(dest))
+}
+
+fn shell_quote(path: &Path) -> String {
+    format!("'{}'", path.display().to_string().replace('\'', r"'\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn substitutes_quoted_paths() {
+        let command = substitute("diff {source} {destination} {dest}", Path::new("/a b.md"), Path::new("/it's.md"));
+        
+        assert_eq!(command, r"diff '/a b.md' '/it'\''s.md' '/it'\''s.md'");
+    }
+}
@@ -1,7 +1,26 @@
+mod checksum_cache;
+pub mod backend;
 pub mod config;
+pub mod discover;
+#[cfg(all(unix, feature = "control"))]
+pub mod control;
+pub mod fsutil;
+pub mod gc;
+mod heartbeat;
+pub mod hooks;
+pub mod markdown;
+pub mod migration;
+pub mod notifications;
 pub mod sync;
+pub mod sync_state;
+pub mod template;
 pub mod watcher;
 
-pub use config::Config;
-pub use sync::{check_diff, sync_all_files, DiffReport, SyncStats};
-pub use watcher::FileWatcher;
\ No newline at end of file
+pub use backend::{DestinationBackend, LocalBackend};
+pub use config::{Aggregate, CanonicalizeReport, Config, ConfigError, ConfigStats, LinkKind, Mapping, MarkedRegion, QueueFullPolicy, WatchBackend, SCALAR_OPTIONS};
+pub use discover::{discover, Candidate};
+pub use gc::{collect_garbage, GcReport};
+pub use sync::{check_diff, diff_against, sync_all_files, sync_destinations, sync_from_destination, DestinationReport, DestinationStatus, DiffReport, SyncOptions, SyncStats, Timing, TimingPhase};
+pub use sync_state::{last_synced, record_sync, SyncRecord, SyncState};
+pub use template::expand_template;
+pub use watcher::{FileWatcher, WatchEvent, WatchSummary};
\ No newline at end of file
@@ -0,0 +1,369 @@
+use similar::{ChangeTag, TextDiff};
+use std::borrow::Cow;
+use tracing::warn;
+
+/// Normalize markdown whitespace that doesn't affect rendered output:
+/// trailing spaces on each line, runs of blank lines, and trailing blank lines.
+pub fn normalize(text: &str) -> String {
+    let mut normalized = String::with_capacity(text.len());
+    let mut previous_blank = false;
+    
+    for line in text.lines() {
+        let line = line.trim_end();
+        let blank = line.is_empty();
+        if blank && previous_blank {
+            continue;
+        }
+        normalized.push_str(line);
+        normalized.push('\n');
+        previous_blank = blank;
+    }
+    
+    let trimmed_len = normalized.trim_end().len();
+    normalized.truncate(trimmed_len);
+    normalized
+}
+
+/// Length of a leading YAML frontmatter block: a `---` line, the metadata, and a closing
+/// `---` or `...` line including its line break. Zero if `content` doesn't start with one.
+pub fn frontmatter_len(content: &[u8]) -> usize {
+    let Some(mut offset) = line_end(content, 0).filter(|&end| trim_newline(&content[..end]) == b"---") else {
+        return 0;
+    };
+    
+    while offset < content.len() {
+        let Some(end) = line_end(content, offset) else {
+            break;
+        };
+        let line = trim_newline(&content[offset..end]);
+        if line == b"---" || line == b"..." {
+            return end;
+        }
+        offset = end;
+    }
+    
+    // An opening marker without a closing one is just a horizontal rule
+    0
+}
+
+/// `content` without its leading frontmatter block, if it has one.
+pub fn strip_frontmatter(content: &[u8]) -> &[u8] {
+    &content[frontmatter_len(content)..]
+}
+
+/// `content` without the regions between `start` and `end` marker lines, markers included.
+///
+/// Markers must be on lines of their own and are ignored inside fenced code blocks, so
+/// documentation can show them. Regions may nest. An end marker without a start is kept
+/// as is, while a start marker that is never closed removes everything after it: leaving
+/// out too much is safer than publishing what was meant to stay internal.
+pub fn strip_marked_regions<'a>(content: &'a [u8], start: &str, end: &str) -> Cow<'a, [u8]> {
+    let mut kept = Vec::with_capacity(content.len());
+    let mut depth = 0usize;
+    let mut fence: Option<&[u8]> = None;
+    let mut offset = 0;
+    
+    while let Some(line_end) = line_end(content, offset) {
+        let line = &content[offset..line_end];
+        let trimmed = trim_newline(line).trim_ascii();
+        offset = line_end;
+        
+        match fence {
+            Some(marker) if trimmed.starts_with(marker) => fence = None,
+            Some(_) => {}
+            None if trimmed.starts_with(b"```") => fence = Some(b"```"),
+            None if trimmed.starts_with(b"~~~") => fence = Some(b"~~~"),
+            None if trimmed == start.as_bytes() => {
+                depth += 1;
+                continue;
+            }
+            None if trimmed == end.as_bytes() && depth > 0 => {
+                depth -= 1;
+                continue;
+            }
+            None => {}
+        }
+        
+        if depth == 0 {
+            kept.extend_from_slice(line);
+        }
+    }
+    
+    if depth > 0 {
+        warn!("Unclosed {} marker, leaving out everything after it", start);
+    }
+    
+    if kept.len() == content.len() {
+        Cow::Borrowed(content)
+    } else {
+        Cow::Owned(kept)
+    }
+}
+
+/// Index just past the line starting at `start`, including its line break.
+fn line_end(content: &[u8], start: usize) -> Option<usize> {
+    if start >= content.len() {
+        return None;
+    }
+    Some(match content[start..].iter().position(|&b| b == b'\n') {
+        Some(newline) => start + newline + 1,
+        None => content.len(),
+    })
+}
+
+fn trim_newline(line: &[u8]) -> &[u8] {
+    let line = line.strip_suffix(b"\n").unwrap_or(line);
+    line.strip_suffix(b"\r").unwrap_or(line)
+}
+
+/// Compare two file contents, optionally ignoring markdown-insignificant whitespace.
+///
+/// Non-UTF-8 content is always compared byte for byte.
+pub fn contents_equal(a: &[u8], b: &[u8], semantic: bool) -> bool {
+    if a == b {
+        return true;
+    }
+    if !semantic {
+        return false;
+    }
+    
+    match (std::str::from_utf8(a), std::str::from_utf8(b)) {
+        (Ok(a), Ok(b)) => normalize(a) == normalize(b),
+        _ => false,
+    }
+}
+
+/// How much prose changed between two versions of a text file.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ChangeStats {
+    pub lines_added: usize,
+    pub lines_removed: usize,
+    /// Words inserted or deleted, counting a replaced word twice.
+    pub words_changed: usize,
+}
+
+/// Count changed lines and words going from `old` to `new`.
+///
+/// Returns `None` unless both contents are UTF-8, since line counts are meaningless for binary files.
+pub fn change_stats(old: &[u8], new: &[u8]) -> Option<ChangeStats> {
+    let old = std::str::from_utf8(old).ok()?;
+    let new = std::str::from_utf8(new).ok()?;
+    
+    let mut stats = ChangeStats::default();
+    for change in TextDiff::from_lines(old, new).iter_all_changes() {
+        match change.tag() {
+            ChangeTag::Insert => stats.lines_added += 1,
+            ChangeTag::Delete => stats.lines_removed += 1,
+            ChangeTag::Equal => {}
+        }
+    }
+    
+    stats.words_changed = TextDiff::from_words(old, new)
+        .iter_all_changes()
+        .filter(|change| change.tag() != ChangeTag::Equal && !change.value().trim().is_empty())
+        .count();
+    
+    Some(stats)
+}
+
+/// Files larger than this are not compared line by line for [`similarity`], since the diff
+/// grows quadratically in the worst case.
+const SIMILARITY_MAX_BYTES: usize = 1024 * 1024;
+
+/// How similar `old` and `new` are by lines, from 0.0 (nothing in common) to 1.0 (identical).
+///
+/// Returns `None` for binary files and files over a megabyte.
+pub fn similarity(old: &[u8], new: &[u8]) -> Option<f32> {
+    if old.len().max(new.len()) > SIMILARITY_MAX_BYTES {
+        return None;
+    }
+    let old = std::str::from_utf8(old).ok()?;
+    let new = std::str::from_utf8(new).ok()?;
+    Some(TextDiff::from_lines(old, new).ratio())
+}
+
+/// Render the changes from `old` to `new` word by word, like `git diff --word-diff`.
+///
+/// Each changed region is shown with a `@@ -line,count +line,count @@` header and one
+/// line of context; removed words are wrapped in `[-...-]` and added words in `{+...+}`.
+/// With `color`, headers and markers are also colored with ANSI escapes, for terminals.
+pub fn word_diff(old: &str, new: &str, color: bool) -> String {
+    let lines = TextDiff::from_lines(old, new);
+    let mut out = String::new();
+    
+    for group in lines.grouped_ops(1) {
+        let (Some(first), Some(last)) = (group.first(), group.last()) else {
+            continue;
+        };
+        let old_range = first.old_range().start..last.old_range().end;
+        let new_range = first.new_range().start..last.new_range().end;
+        
+        let header = format!(
+            "@@ -{},{} +{},{} @@",
+            old_range.start + 1,
+            old_range.len(),
+            new_range.start + 1,
+            new_range.len()
+        );
+        out.push_str(&paint(&header, CYAN, color));
+        out.push('\n');
+        
+        let old_text = lines.old_slices()[old_range].concat();
+        let new_text = lines.new_slices()[new_range].concat();
+        let words = TextDiff::from_words(old_text.as_str(), new_text.as_str());
+        
+        // Consecutive removals or additions are merged into a single marker
+        let mut pending: Option<(ChangeTag, String)> = None;
+        for change in words.iter_all_changes() {
+            match &mut pending {
+                Some((tag, text)) if *tag == change.tag() => text.push_str(change.value()),
+                _ => {
+                    if let Some((tag, text)) = pending.take() {
+                        push_marked(&mut out, tag, &text, color);
+                    }
+                    pending = Some((change.tag(), change.value().to_string()));
+                }
+            }
+        }
+        if let Some((tag, text)) = pending {
+            push_marked(&mut out, tag, &text, color);
+        }
+        
+        if !out.ends_with('\n') {
+            out.push('\n');
+        }
+    }
+    
+    out
+}
+
+const RED: &str = "\x1b[31m";
+const GREEN: &str = "\x1b[32m";
+const CYAN: &str = "\x1b[36m";
+const RESET: &str = "\x1b[0m";
+
+fn paint(text: &str, code: &str, color: bool) -> String {
+    if color {
+        format!("{}{}{}", code, text, RESET)
+    } else {
+        text.to_string()
+    }
+}
+
+fn push_marked(out: &mut String, tag: ChangeTag, text: &str, color: bool) {
+    match tag {
+        ChangeTag::Equal => out.push_str(text),
+        ChangeTag::Delete => out.push_str(&paint(&format!("[-{}-]", text), RED, color)),
+        ChangeTag::Insert => out.push_str(&paint(&format!("{{+{}+}}", text), GREEN, color)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn insignificant_whitespace_is_normalized() {
+        assert_eq!(normalize("# Title  \n\n\n\nText\t\n\n"), "# Title\n\nText");
+    }
+    
+    #[test]
+    fn semantic_comparison_ignores_only_insignificant_whitespace() {
+        let original = b"# Title\n\nText\n";
+        let reformatted = b"# Title   \n\n\n\nText\n\n";
+        
+        assert!(contents_equal(original, reformatted, true));
+        assert!(!contents_equal(original, reformatted, false));
+        // Indentation changes rendering, e.g. of code blocks
+        assert!(!contents_equal(original, b"# Title\n\n    Text\n", true));
+    }
+    
+    #[test]
+    fn binary_content_is_compared_exactly() {
+        assert!(!contents_equal(b"\xff\xfe ", b"\xff\xfe", true));
+        assert!(contents_equal(b"\xff\xfe", b"\xff\xfe", true));
+    }
+    
+    #[test]
+    fn changed_lines_and_words_are_counted() {
+        let old = b"The quick fox\nSecond line\n";
+        let new = b"The slow fox\nSecond line\nThird\n";
+        
+        assert_eq!(
+            change_stats(old, new),
+            Some(ChangeStats { lines_added: 2, lines_removed: 1, words_changed: 3 })
+        );
+        assert_eq!(change_stats(old, old), Some(ChangeStats::default()));
+    }
+    
+    #[test]
+    fn binary_files_have_no_change_stats() {
+        assert_eq!(change_stats(b"\xff\xfe", b"text"), None);
+        assert_eq!(similarity(b"text", b"\xff\xfe"), None);
+    }
+    
+    #[test]
+    fn similarity_ranges_from_disjoint_to_identical() {
+        assert_eq!(similarity(b"a\nb\n", b"a\nb\n"), Some(1.0));
+        assert_eq!(similarity(b"a\nb\n", b"c\nd\n"), Some(0.0));
+        assert_eq!(similarity(b"a\nb\n", b"a\nc\n"), Some(0.5));
+    }
+    
+    #[test]
+    fn large_files_have_no_similarity() {
+        let large = vec![b'a'; SIMILARITY_MAX_BYTES + 1];
+        assert_eq!(similarity(&large, b"a"), None);
+    }
+    
+    #[test]
+    fn frontmatter_block_is_stripped() {
+        assert_eq!(strip_frontmatter(b"---\ntags: [a]\n---\n# Title\n"), b"# Title\n");
+        assert_eq!(strip_frontmatter(b"---\r\ndate: 2024\r\n...\r\nText"), b"Text");
+    }
+    
+    #[test]
+    fn content_without_frontmatter_is_kept() {
+        assert_eq!(strip_frontmatter(b"# Title\n---\nText\n"), b"# Title\n---\nText\n");
+        // A lone opening marker is a horizontal rule
+        assert_eq!(strip_frontmatter(b"---\nText\n"), b"---\nText\n");
+        assert_eq!(strip_frontmatter(b""), b"");
+    }
+    
+    #[test]
+    fn word_diff_marks_the_changed_word() {
+        let old = "First paragraph.\n\nThe quick brown fox jumps.\n";
+        let new = "First paragraph.\n\nThe quick red fox jumps.\n";
+        
+        assert_eq!(word_diff(old, new, false), "@@ -2,2 +2,2 @@\n\nThe quick [-brown-]{+red+} fox jumps.\n");
+        assert!(word_diff(old, new, true).contains('\x1b'));
+        assert_eq!(word_diff(old, old, false), "");
+    }
+    
+    fn strip(content: &str) -> String {
+        String::from_utf8(strip_marked_regions(content.as_bytes(), "<!-- private -->", "<!-- /private -->").into_owned()).unwrap()
+    }
+    
+    #[test]
+    fn balanced_regions_are_removed_with_their_markers() {
+        assert_eq!(strip("a\n<!-- private -->\nsecret\n<!-- /private -->\nb\n"), "a\nb\n");
+        assert_eq!(strip("no markers\n"), "no markers\n");
+    }
+    
+    #[test]
+    fn nested_regions_end_with_the_outermost_marker() {
+        let content = "a\n<!-- private -->\n<!-- private -->\ninner\n<!-- /private -->\nouter\n<!-- /private -->\nb\n";
+        assert_eq!(strip(content), "a\nb\n");
+    }
+    
+    #[test]
+    fn unbalanced_markers_err_on_the_side_of_removing() {
+        assert_eq!(strip("a\n<!-- private -->\nsecret\nmore\n"), "a\n");
+        assert_eq!(strip("a\n<!-- /private -->\nb\n"), "a\n<!-- /private -->\nb\n");
+    }
+    
+    #[test]
+    fn markers_in_code_blocks_are_kept() {
+        let content = "```\n<!-- private -->\n```\nb\n";
+        assert_eq!(strip(content), content);
+    }
+}
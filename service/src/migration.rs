@@ -0,0 +1,112 @@
+use anyhow::{Context, Result};
+use serde_json::{Map, Value};
+use tracing::{info, instrument};
+
+/// The config schema version written by this build of mdman.
+pub const CURRENT_VERSION: u64 = 1;
+
+/// Upgrade a raw config document to [`CURRENT_VERSION`] in place.
+///
+/// Returns the version the document had before migrating.
+#[instrument(skip(value))]
+pub fn migrate(value: &mut Value) -> Result<u64> {
+    let root = value
+        .as_object_mut()
+        .context("Config root must be a JSON object")?;
+    
+    let original = root.get("version").and_then(Value::as_u64).unwrap_or(0);
+    
+    if original > CURRENT_VERSION {
+        anyhow::bail!(
+            "Config version {} is newer than this mdman supports ({}), please upgrade mdman",
+            original,
+            CURRENT_VERSION
+        );
+    }
+    
+    let mut version = original;
+    while version < CURRENT_VERSION {
+        match version {
+            0 => migrate_v0_to_v1(root)?,
+            _ => unreachable!("no migration from version {version}"),
+        }
+        version += 1;
+        info!("Migrated config to version {}", version);
+    }
+    
+    root.insert("version".to_string(), Value::from(CURRENT_VERSION));
+    Ok(original)
+}
+
+/// v0 stored each source's destinations as a bare array; v1 wraps them in a mapping object.
+fn migrate_v0_to_v1(root: &mut Map<String, Value>) -> Result<()> {
+    let Some(mappings) = root.get_mut("mappings") else {
+        return Ok(());
+    };
+    
+    let mappings = mappings
+        .as_object_mut()
+        .context("Config field 'mappings' must be an object")?;
+    
+    for (source, entry) in mappings.iter_mut() {
+        if entry.is_array() {
+            let destinations = entry.take();
+            let mut mapping = Map::new();
+            mapping.insert("destinations".to_string(), destinations);
+            *entry = Value::Object(mapping);
+        } else if !entry.is_object() {
+            anyhow::bail!("Mapping for {} has an unexpected shape", source);
+        }
+    }
+    
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    
+    #[test]
+    fn v0_destination_lists_become_mappings() {
+        let mut config = json!({
+            "mappings": {
+                "/notes.md": ["/a/notes.md", "/b/notes.md"],
+            },
+        });
+        
+        assert_eq!(migrate(&mut config).unwrap(), 0);
+        assert_eq!(config, json!({
+            "version": CURRENT_VERSION,
+            "mappings": {
+                "/notes.md": { "destinations": ["/a/notes.md", "/b/notes.md"] },
+            },
+        }));
+    }
+    
+    #[test]
+    fn current_configs_are_left_alone() {
+        let original = json!({
+            "version": CURRENT_VERSION,
+            "mappings": { "/notes.md": { "destinations": ["/a/notes.md"] } },
+        });
+        let mut config = original.clone();
+        
+        assert_eq!(migrate(&mut config).unwrap(), CURRENT_VERSION);
+        assert_eq!(config, original);
+    }
+    
+    #[test]
+    fn newer_configs_are_rejected() {
+        let mut config = json!({ "version": CURRENT_VERSION + 1, "mappings": {} });
+        
+        assert!(migrate(&mut config).is_err());
+    }
+    
+    #[test]
+    fn malformed_v0_mappings_are_rejected() {
+        let mut config = json!({ "mappings": { "/notes.md": "/a/notes.md" } });
+        
+        assert!(migrate(&mut config).is_err());
+    }
+}
@@ -0,0 +1,167 @@
+use notify_rust::Notification;
+use serde::{Deserialize, Serialize};
+
+/// How desktop notifications look, configured per event under `notifications` in the config.
+///
+/// Every field is optional; anything left out keeps mdman's built-in style for that event.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct NotificationSettings {
+    /// Whether the watcher notifies about each synced source, once per batch of changes, or not at all.
+    #[serde(default, skip_serializing_if = "SyncNotificationMode::is_default")]
+    pub sync_mode: SyncNotificationMode,
+    /// Style of sync notifications; `timeout_ms` here controls how long they stay up.
+    #[serde(default, skip_serializing_if = "NotificationStyle::is_default")]
+    pub sync: NotificationStyle,
+    #[serde(default, skip_serializing_if = "NotificationStyle::is_default")]
+    pub desync: NotificationStyle,
+    #[serde(default, skip_serializing_if = "NotificationStyle::is_default")]
+    pub source_deleted: NotificationStyle,
+    #[serde(default, skip_serializing_if = "NotificationStyle::is_default")]
+    pub write_failed: NotificationStyle,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct NotificationStyle {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub urgency: Option<Urgency>,
+    /// Icon name from the desktop's icon theme, or a path to an image.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub icon: Option<String>,
+    /// How long the notification stays up; 0 keeps it until dismissed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_ms: Option<u32>,
+    /// Sound from the desktop's sound theme to play, e.g. `message-new-instant`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sound: Option<String>,
+}
+
+impl NotificationStyle {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SyncNotificationMode {
+    /// One notification per synced source, or per batch when the watcher batches events.
+    #[default]
+    PerSource,
+    /// A single notification summing up every source synced for a batch of file events.
+    Summary,
+    /// No sync notifications. Desyncs, deleted sources and failures are still notified.
+    None,
+}
+
+impl SyncNotificationMode {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Urgency {
+    Low,
+    Normal,
+    Critical,
+}
+
+impl From<Urgency> for notify_rust::Urgency {
+    fn from(urgency: Urgency) -> Self {
+        match urgency {
+            Urgency::Low => notify_rust::Urgency::Low,
+            Urgency::Normal => notify_rust::Urgency::Normal,
+            Urgency::Critical => notify_rust::Urgency::Critical,
+        }
+    }
+}
+
+/// Which event a notification is about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotificationKind {
+    /// Files were synced. `warning` is set when desynced destinations were left out.
+    Sync { warning: bool },
+    Desync,
+    SourceDeleted,
+    /// Writing a destination failed, or started working again after failing if `recovered`.
+    WriteFailed { recovered: bool },
+}
+
+impl NotificationSettings {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+    
+    /// Build a notification for `kind`, applying the configured style over the built-in one.
+    pub fn build(&self, kind: NotificationKind, summary: &str, body: &str) -> Notification {
+        let (style, urgency, icon, timeout_ms) = match kind {
+            NotificationKind::Sync { warning } => (
+                &self.sync,
+                None,
+                if warning { "dialog-warning" } else { "document-save" },
+                3000,
+            ),
+            NotificationKind::Desync => (&self.desync, Some(Urgency::Critical), "dialog-warning", 0),
+            NotificationKind::SourceDeleted => (&self.source_deleted, Some(Urgency::Critical), "dialog-warning", 0),
+            NotificationKind::WriteFailed { recovered: false } => (&self.write_failed, Some(Urgency::Critical), "dialog-error", 0),
+            NotificationKind::WriteFailed { recovered: true } => (&self.write_failed, None, "document-save", 3000),
+        };
+        
+        let mut notification = Notification::new();
+        notification
+            .summary(summary)
+            .body(body)
+            .icon(style.icon.as_deref().unwrap_or(icon))
+            .timeout(i32::try_from(style.timeout_ms.unwrap_or(timeout_ms)).unwrap_or(i32::MAX));
+        
+        if let Some(urgency) = style.urgency.or(urgency) {
+            notification.urgency(urgency.into());
+        }
+        if let Some(sound) = &style.sound {
+            notification.sound_name(sound);
+        }
+        
+        notification
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use notify_rust::{Hint, Timeout};
+    
+    #[test]
+    fn configured_style_is_applied() {
+        let settings = NotificationSettings {
+            desync: NotificationStyle {
+                urgency: Some(Urgency::Low),
+                icon: Some("mdman".into()),
+                timeout_ms: Some(5000),
+                sound: Some("message-new-instant".into()),
+            },
+            ..Default::default()
+        };
+        
+        let notification = settings.build(NotificationKind::Desync, "Desync", "body");
+        
+        assert_eq!(notification.icon, "mdman");
+        assert_eq!(notification.timeout, Timeout::Milliseconds(5000));
+        assert!(notification.hints.contains(&Hint::Urgency(notify_rust::Urgency::Low)));
+        assert!(notification.hints.contains(&Hint::SoundName("message-new-instant".into())));
+    }
+    
+    #[test]
+    fn unconfigured_events_keep_the_built_in_style() {
+        let settings = NotificationSettings::default();
+        
+        let desync = settings.build(NotificationKind::Desync, "Desync", "body");
+        let sync = settings.build(NotificationKind::Sync { warning: false }, "Synced", "body");
+        
+        assert_eq!(desync.timeout, Timeout::Never);
+        assert!(desync.hints.contains(&Hint::Urgency(notify_rust::Urgency::Critical)));
+        assert_eq!(sync.icon, "document-save");
+        assert_eq!(sync.timeout, Timeout::Milliseconds(3000));
+        assert!(!sync.hints.iter().any(|hint| matches!(hint, Hint::Urgency(_) | Hint::SoundName(_))));
+    }
+}
@@ -1,76 +1,676 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
+use std::borrow::Cow;
 use std::fs;
-use std::path::Path;
-use tracing::{error, info, instrument, warn};
+use globset::{Glob, GlobMatcher};
+use rayon::prelude::*;
+use serde::{Serialize, Serializer};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Mutex, PoisonError};
+use std::time::{Duration, Instant, SystemTime};
+use tracing::{debug, error, info, instrument, warn};
 
-use crate::config::Config;
+use crate::backend;
+use crate::checksum_cache::ChecksumCache;
+use crate::config::{Aggregate, Config, Mapping};
+use crate::fsutil;
+use crate::hooks::{self, HookEvent};
+use crate::markdown::{self, ChangeStats};
+use crate::sync_state::SyncState;
 
+#[derive(Debug, Default, Clone, Serialize)]
 pub struct SyncStats {
     pub synced_count: usize,
     pub error_count: usize,
+    /// Whether the run stopped early because the error limit was reached.
+    pub aborted: bool,
+    /// What happened to each destination, in no particular order.
+    pub destinations: Vec<DestinationReport>,
+    /// How long reading sources and writing destinations took, if [`SyncOptions::timings`] is set.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub timings: Vec<Timing>,
+}
+
+impl SyncStats {
+    fn merge(mut self, other: SyncStats) -> SyncStats {
+        self.synced_count += other.synced_count;
+        self.error_count += other.error_count;
+        self.aborted |= other.aborted;
+        self.destinations.extend(other.destinations);
+        self.timings.extend(other.timings);
+        self
+    }
+    
+    fn record(&mut self, source: &Path, destination: &Path, status: DestinationStatus, bytes_written: usize, message: Option<String>) {
+        self.destinations.push(DestinationReport {
+            source: source.to_path_buf(),
+            destination: destination.to_path_buf(),
+            status,
+            bytes_written,
+            message,
+        });
+    }
+}
+
+/// The outcome for one destination of a sync run.
+#[derive(Debug, Clone, Serialize)]
+pub struct DestinationReport {
+    pub source: PathBuf,
+    pub destination: PathBuf,
+    pub status: DestinationStatus,
+    pub bytes_written: usize,
+    /// Why the destination was skipped or failed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+/// How long one phase of a sync took for `path`.
+#[derive(Debug, Clone, Serialize)]
+pub struct Timing {
+    pub path: PathBuf,
+    pub phase: TimingPhase,
+    #[serde(rename = "ms", serialize_with = "serialize_millis")]
+    pub elapsed: Duration,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TimingPhase {
+    /// Reading a source.
+    Read,
+    /// Writing a destination, or generating an aggregate. Destinations written in one
+    /// transaction each get the time of the whole transaction.
+    Write,
+}
+
+fn serialize_millis<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_f64(duration.as_secs_f64() * 1000.0)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DestinationStatus {
+    Synced,
+    Skipped,
+    Failed,
+    Aborted,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct SyncOptions {
+    /// Number of sources synced concurrently. `None` uses the config default,
+    /// falling back to one thread per CPU.
+    pub threads: Option<usize>,
+    /// Stop the run once this many errors occurred in total.
+    pub max_errors: Option<usize>,
+    /// Only sync sources whose path matches this glob.
+    pub only: Option<String>,
+    /// Write the destinations of each source concurrently.
+    pub parallel_destinations: bool,
+    /// Don't print progress to stdout; errors are still printed to stderr.
+    pub quiet: bool,
+    /// Receives the outcome of every destination as soon as its source is done.
+    pub outcomes: Option<mpsc::Sender<DestinationReport>>,
+    /// Also write destinations whose auto-sync is turned off, e.g. because they were picked explicitly.
+    pub include_manual: bool,
+    /// Measure how long each read and write takes, see [`SyncStats::timings`].
+    pub timings: bool,
+}
+
+/// State shared by every source synced in one run.
+struct SyncContext<'a> {
+    options: &'a SyncOptions,
+    config: &'a Config,
+    errors: AtomicUsize,
+    state: Mutex<SyncState>,
+}
+
+impl<'a> SyncContext<'a> {
+    fn new(options: &'a SyncOptions, config: &'a Config) -> Self {
+        Self {
+            options,
+            config,
+            errors: AtomicUsize::new(0),
+            state: Mutex::new(SyncState::load()),
+        }
+    }
+    
+    /// Remember when `content` was written to `destination`, see [`SyncContext::save_state`].
+    fn record_sync(&self, source: &Path, destination: &Path, content: &[u8]) {
+        self.state
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .record(source, destination, SystemTime::now(), content);
+    }
+    
+    /// Persist the recorded sync times. Failing to do so doesn't fail the sync itself.
+    fn save_state(&self) {
+        let state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Err(e) = state.save() {
+            warn!("Failed to save sync state: {:#}", e);
+        }
+    }
+    
+    fn parallel_destinations(&self) -> bool {
+        self.options.parallel_destinations || self.config.parallel_destinations
+    }
+    
+    /// Add the outcome for `destination` to `stats`, and stream it if anybody listens.
+    fn record(
+        &self,
+        stats: &mut SyncStats,
+        source: &Path,
+        destination: &Path,
+        status: DestinationStatus,
+        bytes_written: usize,
+        message: Option<String>,
+    ) {
+        stats.record(source, destination, status, bytes_written, message);
+        if let Some(outcomes) = &self.options.outcomes
+            && let Some(report) = stats.destinations.last()
+        {
+            // A dropped receiver just means nobody is listening anymore
+            let _ = outcomes.send(report.clone());
+        }
+    }
+    
+    fn record_timing(&self, stats: &mut SyncStats, path: &Path, phase: TimingPhase, elapsed: Duration) {
+        if self.options.timings {
+            stats.timings.push(Timing { path: path.to_path_buf(), phase, elapsed });
+        }
+    }
+    
+    fn record_error(&self) {
+        self.errors.fetch_add(1, Ordering::SeqCst);
+    }
+    
+    fn exhausted(&self) -> bool {
+        self.options
+            .max_errors
+            .is_some_and(|max| self.errors.load(Ordering::SeqCst) >= max)
+    }
 }
 
 #[instrument]
-pub fn sync_all_files() -> Result<SyncStats> {
+pub fn sync_all_files(options: &SyncOptions) -> Result<SyncStats> {
     let config = Config::load()?;
-    let mappings = config.list_mappings();
+    let mappings = filter_by_source(config.list_mappings(), options.only.as_deref())?;
     
-    let mut synced_count = 0;
-    let mut error_count = 0;
+    let threads = options.threads.or(config.sync_threads).unwrap_or(0);
+    let context = SyncContext::new(options, &config);
     
-    for (source, destinations) in mappings {
-        if !source.exists() {
-            warn!("Source file {} does not exist", source.display());
-            eprintln!("Warning: Source file {} does not exist", source.display());
-            error_count += 1;
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build()?;
+    
+    // Priority groups run one after another, only sources within a group run concurrently
+    let mut stats = SyncStats::default();
+    for group in group_by_priority(&config, mappings) {
+        let group_stats = if threads == 1 {
+            group
+                .iter()
+                .map(|(source, destinations)| sync_source(&context, source, destinations))
+                .fold(SyncStats::default(), SyncStats::merge)
+        } else {
+            pool.install(|| {
+                group
+                    .par_iter()
+                    .map(|(source, destinations)| sync_source(&context, source, destinations))
+                    .reduce(SyncStats::default, SyncStats::merge)
+            })
+        };
+        stats = stats.merge(group_stats);
+    }
+    
+    // Aggregates are generated last so they see every source after it was synced
+    let matcher = options.only.as_deref().map(source_matcher).transpose()?;
+    for (destination, aggregate) in &config.aggregates {
+        if let Some(matcher) = &matcher
+            && !aggregate.sources.iter().any(|source| matcher.is_match(source))
+        {
             continue;
         }
-        
-        let content = match fs::read(&source) {
-            Ok(content) => content,
-            Err(e) => {
-                error!("Error reading {}: {}", source.display(), e);
-                eprintln!("Error reading {}: {}", source.display(), e);
-                error_count += 1;
-                continue;
+        stats = stats.merge(sync_aggregate(&context, destination, aggregate));
+    }
+    
+    context.save_state();
+    Ok(stats)
+}
+
+fn sync_aggregate(context: &SyncContext, destination: &Path, aggregate: &Aggregate) -> SyncStats {
+    let mut stats = SyncStats::default();
+    
+    if context.exhausted() {
+        stats.aborted = true;
+        return stats;
+    }
+    
+    let started = Instant::now();
+    let config = context.config;
+    let written = aggregate.render().and_then(|content| {
+        backend::resolve(destination)
+            .and_then(|backend| backend::write_verified(&*backend, destination, &content, config.durable_writes, config.verify_after_write))
+            .with_context(|| format!("Failed to write {}", destination.display()))?;
+        Ok(content)
+    });
+    context.record_timing(&mut stats, destination, TimingPhase::Write, started.elapsed());
+    
+    // Reported under the first source, the aggregate as a whole has no single one
+    let source = aggregate.sources.first().map_or(Path::new(""), PathBuf::as_path);
+    match written {
+        Ok(content) => {
+            info!("Aggregated {} sources → {}", aggregate.sources.len(), destination.display());
+            if !context.options.quiet {
+                println!("Aggregated {} sources → {}", aggregate.sources.len(), destination.display());
             }
-        };
-        
+            stats.synced_count += 1;
+            context.record(&mut stats, source, destination, DestinationStatus::Synced, content.len(), None);
+            context.record_sync(source, destination, &content);
+        }
+        Err(e) => {
+            error!("Error generating {}: {:#}", destination.display(), e);
+            eprintln!("Error generating {}: {:#}", destination.display(), e);
+            stats.error_count += 1;
+            context.record_error();
+            context.record(&mut stats, source, destination, DestinationStatus::Failed, 0, Some(format!("{:#}", e)));
+        }
+    }
+    
+    stats
+}
+
+/// Split mappings into groups of equal priority, lowest first. Within a group
+/// sources stay sorted by path so the order is deterministic.
+fn group_by_priority(
+    config: &Config,
+    mut mappings: Vec<(PathBuf, Vec<PathBuf>)>,
+) -> Vec<Vec<(PathBuf, Vec<PathBuf>)>> {
+    let priority = |source: &PathBuf| config.mappings.get(source).map_or(0, |m| m.priority);
+    mappings.sort_by(|(a, _), (b, _)| priority(a).cmp(&priority(b)).then_with(|| a.cmp(b)));
+    
+    mappings
+        .chunk_by(|(a, _), (b, _)| priority(a) == priority(b))
+        .map(<[_]>::to_vec)
+        .collect()
+}
+
+fn sync_source(context: &SyncContext, source: &Path, destinations: &[PathBuf]) -> SyncStats {
+    let mut stats = SyncStats::default();
+    
+    if context.exhausted() {
+        stats.aborted = true;
+        return stats;
+    }
+    
+    if !source.exists() {
+        warn!("Source file {} does not exist", source.display());
+        eprintln!("Warning: Source file {} does not exist", source.display());
+        stats.error_count += 1;
+        context.record_error();
+        for dest in destinations {
+            context.record(&mut stats, source, dest, DestinationStatus::Failed, 0, Some("source file does not exist".to_string()));
+        }
+        return stats;
+    }
+    
+    let read_error = |stats: &mut SyncStats, e: std::io::Error| {
+        error!("Error reading {}: {}", source.display(), e);
+        eprintln!("Error reading {}: {}", source.display(), e);
+        stats.error_count += 1;
+        context.record_error();
         for dest in destinations {
-            match fs::write(&dest, &content) {
-                Ok(_) => {
-                    info!("Synced {} → {}", source.display(), dest.display());
+            context.record(stats, source, dest, DestinationStatus::Failed, 0, Some(format!("failed to read source: {}", e)));
+        }
+    };
+    
+    let started = Instant::now();
+    let read = fsutil::read_file(source, context.config.mmap_threshold).and_then(|data| {
+        if data.changed_on_disk(source) {
+            // The mapping may mix old and new bytes, use a snapshot before anything is written
+            warn!("{} changed while it was being mapped, reading it instead", source.display());
+            return fs::read(source).map(fsutil::FileContent::Read);
+        }
+        Ok(data)
+    });
+    let data = match read {
+        Ok(data) => data,
+        Err(e) => {
+            read_error(&mut stats, e);
+            return stats;
+        }
+    };
+    context.record_timing(&mut stats, source, TimingPhase::Read, started.elapsed());
+    
+    let outcomes = write_destinations(context, source, destinations, &data);
+    let content = match context.config.mappings.get(source) {
+        Some(mapping) => mapping.destination_content(&data),
+        None => Cow::Borrowed(&*data),
+    };
+    
+    // Outcomes are reported in destination order regardless of how they were written
+    for (dest, outcome, elapsed) in outcomes {
+        if matches!(outcome, WriteOutcome::Synced | WriteOutcome::Failed(_)) {
+            context.record_timing(&mut stats, dest, TimingPhase::Write, elapsed);
+        }
+        match outcome {
+            WriteOutcome::Synced => {
+                info!("Synced {} → {}", source.display(), dest.display());
+                if !context.options.quiet {
                     println!("Synced {} → {}", source.display(), dest.display());
-                    synced_count += 1;
                 }
-                Err(e) => {
-                    error!("Error syncing to {}: {}", dest.display(), e);
-                    eprintln!("Error syncing to {}: {}", dest.display(), e);
-                    error_count += 1;
+                stats.synced_count += 1;
+                context.record(&mut stats, source, dest, DestinationStatus::Synced, content.len(), None);
+                context.record_sync(source, dest, &content);
+            }
+            WriteOutcome::Linked => {
+                debug!("{} links to {}, nothing to write", dest.display(), source.display());
+                context.record(&mut stats, source, dest, DestinationStatus::Skipped, 0, Some("it links to the source".to_string()));
+            }
+            WriteOutcome::Manual => {
+                debug!("Auto-sync is off for {}, leaving it alone", dest.display());
+                context.record(&mut stats, source, dest, DestinationStatus::Skipped, 0, Some("auto-sync is off for it".to_string()));
+            }
+            WriteOutcome::Skipped(reason) => {
+                warn!("Skipped {}: {}", dest.display(), reason);
+                eprintln!("Skipped {}: {}", dest.display(), reason);
+                context.record(&mut stats, source, dest, DestinationStatus::Skipped, 0, Some(reason.to_string()));
+            }
+            WriteOutcome::Failed(e) => {
+                error!("Error syncing to {}: {}", dest.display(), e);
+                eprintln!("Error syncing to {}: {}", dest.display(), e);
+                stats.error_count += 1;
+                context.record(&mut stats, source, dest, DestinationStatus::Failed, 0, Some(e.to_string()));
+            }
+            WriteOutcome::Aborted => {
+                stats.aborted = true;
+                context.record(&mut stats, source, dest, DestinationStatus::Aborted, 0, None);
+            }
+        }
+    }
+    
+    stats
+}
+
+/// Write the content of `source` to each of its destinations in the configured mode,
+/// along with how long each write took.
+fn write_destinations<'d>(
+    context: &SyncContext,
+    source: &Path,
+    destinations: &'d [PathBuf],
+    data: &[u8],
+) -> Vec<(&'d PathBuf, WriteOutcome, Duration)> {
+    let content = match context.config.mappings.get(source) {
+        Some(mapping) => mapping.destination_content(data),
+        None => Cow::Borrowed(data),
+    };
+    let timed = |dest: &'d PathBuf| {
+        let started = Instant::now();
+        let outcome = sync_destination(context, source, dest, &content);
+        (dest, outcome, started.elapsed())
+    };
+    
+    if context.config.transactional {
+        let started = Instant::now();
+        let outcomes = sync_transaction(context, source, destinations, &content);
+        let elapsed = started.elapsed();
+        outcomes.into_iter().map(|(dest, outcome)| (dest, outcome, elapsed)).collect()
+    } else if context.parallel_destinations() {
+        destinations.par_iter().map(timed).collect()
+    } else {
+        destinations.iter().map(timed).collect()
+    }
+}
+
+enum WriteOutcome {
+    Synced,
+    /// The destination is an intact link to the source, so there was nothing to write.
+    Linked,
+    /// Auto-sync is turned off for the destination, see [`crate::Mapping::manual`].
+    Manual,
+    Skipped(&'static str),
+    Failed(std::io::Error),
+    Aborted,
+}
+
+/// Checks a destination must pass before it is written, or the outcome if it fails one.
+fn check_destination(context: &SyncContext, source: &Path, dest: &Path) -> Option<WriteOutcome> {
+    if context.exhausted() {
+        return Some(WriteOutcome::Aborted);
+    }
+    
+    let mapping = context.config.mappings.get(source);
+    if !context.options.include_manual && mapping.is_some_and(|mapping| !mapping.auto_syncs(dest)) {
+        return Some(WriteOutcome::Manual);
+    }
+    
+    // Links share the source's content, only broken ones need to be made again
+    let link = mapping.and_then(|mapping| mapping.link_kind(dest));
+    if let Some(kind) = link {
+        return Some(match kind.restore(source, dest) {
+            Ok(false) => WriteOutcome::Linked,
+            Ok(true) => WriteOutcome::Synced,
+            Err(e) => {
+                context.record_error();
+                WriteOutcome::Failed(e)
+            }
+        });
+    }
+    
+    if fsutil::same_file(source, dest) {
+        return Some(WriteOutcome::Skipped("it is a hardlink of the source"));
+    }
+    
+    // Remote backends create whatever they need themselves
+    if !backend::is_remote(dest) {
+        match context.config.on_missing_parent.prepare(dest) {
+            Ok(true) => {}
+            Ok(false) => return Some(WriteOutcome::Skipped("its directory is missing, retrying on the next sync")),
+            Err(e) => {
+                context.record_error();
+                return Some(WriteOutcome::Failed(e));
+            }
+        }
+    }
+    
+    if fsutil::read_only() {
+        return Some(WriteOutcome::Skipped("read-only mode, it would have been written"));
+    }
+    
+    if !hooks::run_hook(HookEvent::PreSync, source, dest) {
+        return Some(WriteOutcome::Skipped("pre-sync hook failed"));
+    }
+    
+    None
+}
+
+fn sync_destination(context: &SyncContext, source: &Path, dest: &Path, content: &[u8]) -> WriteOutcome {
+    if let Some(outcome) = check_destination(context, source, dest) {
+        return outcome;
+    }
+    write_destination(context, source, dest, content)
+}
+
+/// Write a destination that passed [`check_destination`] through its backend.
+fn write_destination(context: &SyncContext, source: &Path, dest: &Path, content: &[u8]) -> WriteOutcome {
+    let config = context.config;
+    let written = backend::resolve(dest)
+        .and_then(|backend| backend::write_verified(&*backend, dest, content, config.durable_writes, config.verify_after_write));
+    match written {
+        Ok(_) => {
+            hooks::run_hook(HookEvent::PostSync, source, dest);
+            WriteOutcome::Synced
+        }
+        Err(e) => {
+            context.record_error();
+            WriteOutcome::Failed(e)
+        }
+    }
+}
+
+/// Write all destinations that pass their checks, or none of them if any write fails.
+/// Remote destinations are written after the local ones were committed, so a failing
+/// remote write can't be rolled back.
+fn sync_transaction<'d>(
+    context: &SyncContext,
+    source: &Path,
+    destinations: &'d [PathBuf],
+    content: &[u8],
+) -> Vec<(&'d PathBuf, WriteOutcome)> {
+    let mut outcomes: Vec<Option<WriteOutcome>> = destinations
+        .iter()
+        .map(|dest| check_destination(context, source, dest))
+        .collect();
+    // Remote backends can't take part in the local commit, they are written once it succeeded
+    let (indices, to_write): (Vec<usize>, Vec<PathBuf>) = destinations
+        .iter()
+        .enumerate()
+        .filter(|(i, dest)| outcomes[*i].is_none() && !backend::is_remote(dest))
+        .map(|(i, dest)| (i, dest.clone()))
+        .unzip();
+    
+    match fsutil::write_all_or_nothing(&to_write, content, context.config.durable_writes, context.config.verify_after_write) {
+        Ok(()) => {
+            for (i, dest) in indices.into_iter().zip(&to_write) {
+                hooks::run_hook(HookEvent::PostSync, source, dest);
+                outcomes[i] = Some(WriteOutcome::Synced);
+            }
+            for (dest, outcome) in destinations.iter().zip(&mut outcomes) {
+                if outcome.is_none() {
+                    *outcome = Some(write_destination(context, source, dest, content));
                 }
             }
         }
+        Err((failed, e)) => {
+            context.record_error();
+            outcomes[indices[failed]] = Some(WriteOutcome::Failed(e));
+        }
+    }
+    
+    destinations
+        .iter()
+        .zip(outcomes)
+        .map(|(dest, outcome)| {
+            (dest, outcome.unwrap_or(WriteOutcome::Skipped("another destination failed, so none were written")))
+        })
+        .collect()
+}
+
+/// Promote a destination to the source of truth: copy its content to the
+/// source and every sibling destination of the same mapping.
+#[instrument(fields(destination = %destination.display()))]
+pub fn sync_from_destination(destination: &Path) -> Result<SyncStats> {
+    let config = Config::load()?;
+    let source = config
+        .find_source_of(destination)
+        .with_context(|| format!("{} is not a tracked destination", destination.display()))?;
+    let destination = config.resolve_path(destination).unwrap_or_else(|_| destination.to_path_buf());
+    // A redirected destination's content is at its temporary location
+    let destination = config.mappings[&source].target(&destination).to_path_buf();
+    
+    if !config.mappings[&source].strip_regions.is_empty() {
+        anyhow::bail!(
+            "{} leaves out marked regions of {}, promoting it would delete them from the source",
+            destination.display(),
+            source.display()
+        );
     }
     
-    Ok(SyncStats { synced_count, error_count })
+    let mut content = fs::read(&destination)
+        .with_context(|| format!("Failed to read {}", destination.display()))?;
+    
+    if config.mappings[&source].strip_frontmatter {
+        // The destination never had the source's frontmatter, keep it
+        let source_content = fs::read(&source).unwrap_or_default();
+        let frontmatter = &source_content[..markdown::frontmatter_len(&source_content)];
+        content.splice(0..0, frontmatter.iter().copied());
+    }
+    
+    fsutil::write_file(&source, &content, config.durable_writes)
+        .with_context(|| format!("Failed to write source {}", source.display()))?;
+    info!("Promoted {} → {}", destination.display(), source.display());
+    println!("Promoted {} → {}", destination.display(), source.display());
+    
+    let siblings: Vec<PathBuf> = config.mappings[&source]
+        .targets()
+        .into_iter()
+        .filter(|dest| *dest != destination)
+        .collect();
+    
+    let options = SyncOptions::default();
+    let context = SyncContext::new(&options, &config);
+    let mut stats = sync_source(&context, &source, &siblings);
+    context.save_state();
+    stats.synced_count += 1;
+    // Content flowed from the destination into the source here
+    stats.record(&destination, &source, DestinationStatus::Synced, content.len(), None);
+    Ok(stats)
 }
 
-#[instrument(skip_all, fields(file = ?file))]
-pub fn check_diff(file: Option<&Path>) -> Result<Vec<DiffReport>> {
+/// Overwrite the given destinations of `source` with its content, leaving its other destinations alone.
+/// Destinations with auto-sync turned off are written too, since they were asked for by name.
+#[instrument(skip(destinations), fields(source = %source.display(), destination_count = destinations.len()))]
+pub fn sync_destinations(source: &Path, destinations: &[PathBuf]) -> Result<SyncStats> {
     let config = Config::load()?;
-    let mappings = config.list_mappings();
+    let destinations: Vec<PathBuf> = match config.mappings.get(source) {
+        Some(mapping) => destinations.iter().map(|dest| mapping.target(dest).to_path_buf()).collect(),
+        None => destinations.to_vec(),
+    };
+    let options = SyncOptions { include_manual: true, ..Default::default() };
+    Ok(sync_source(&SyncContext::new(&options, &config), source, &destinations))
+}
+
+/// Compile a glob matched against absolute source paths.
+///
+/// Relative patterns such as `notes/*.md` may match anywhere in the path.
+pub fn source_matcher(pattern: &str) -> Result<GlobMatcher> {
+    let pattern = if Path::new(pattern).is_absolute() || pattern.starts_with("**") {
+        pattern.to_string()
+    } else {
+        format!("**/{pattern}")
+    };
+    
+    Ok(Glob::new(&pattern)
+        .with_context(|| format!("Invalid glob pattern {}", pattern))?
+        .compile_matcher())
+}
+
+fn filter_by_source(
+    mappings: Vec<(PathBuf, Vec<PathBuf>)>,
+    only: Option<&str>,
+) -> Result<Vec<(PathBuf, Vec<PathBuf>)>> {
+    let Some(pattern) = only else {
+        return Ok(mappings);
+    };
+    
+    let matcher = source_matcher(pattern)?;
+    Ok(mappings
+        .into_iter()
+        .filter(|(source, _)| matcher.is_match(source))
+        .collect())
+}
+
+#[instrument(skip_all, fields(file = ?file, only = ?only))]
+pub fn check_diff(file: Option<&Path>, only: Option<&str>) -> Result<Vec<DiffReport>> {
+    let config = Config::load()?;
+    let mappings = filter_by_source(config.list_mappings(), only)?;
+    
+    // A directory selects every mapping with a source or destination below it
+    let filter = file.map(|file| {
+        let canonical = config.resolve_path(file).unwrap_or_else(|_| file.to_path_buf());
+        (file.is_dir(), canonical)
+    });
+    let selected = |path: &Path| match &filter {
+        Some((true, dir)) => path.starts_with(dir),
+        Some((false, file)) => path == file,
+        None => true,
+    };
     
     let mut diffs = Vec::new();
+    let mut cache = ChecksumCache::load();
     
     for (source, destinations) in mappings {
-        if let Some(specific_file) = file {
-            let canonical_specific = specific_file.canonicalize().unwrap_or_else(|_| specific_file.to_path_buf());
-            let matches_source = source == canonical_specific;
-            let matches_dest = destinations.iter().any(|d| d == &canonical_specific);
-            
-            if !matches_source && !matches_dest {
-                continue;
-            }
+        if !selected(&source) && !destinations.iter().any(|d| selected(d)) {
+            continue;
         }
         
         if !source.exists() {
@@ -78,16 +678,25 @@ pub fn check_diff(file: Option<&Path>) -> Result<Vec<DiffReport>> {
             continue;
         }
         
-        let source_content = match fs::read(&source) {
-            Ok(content) => content,
-            Err(e) => {
-                error!("Error reading {}: {}", source.display(), e);
-                continue;
-            }
+        // Byte-identical destinations are in sync, which cached hashes tell without reading
+        // either file. Filtered destinations never match the raw source, so skip the lookup.
+        let mapping = config.mappings.get(&source);
+        let source_hash = if mapping.is_some_and(Mapping::transforms_content) {
+            None
+        } else {
+            cache.hash(&source).ok()
         };
+        let mut source_data = None;
         
         for dest in destinations {
-            if !dest.exists() {
+            let backend = match backend::resolve(&dest) {
+                Ok(backend) => backend,
+                Err(e) => {
+                    diffs.push(DiffReport::unreadable(&source, &dest, e));
+                    continue;
+                }
+            };
+            if !backend.exists(&dest) {
                 diffs.push(DiffReport::DestinationMissing {
                     source: source.clone(),
                     destination: dest.clone(),
@@ -95,28 +704,89 @@ pub fn check_diff(file: Option<&Path>) -> Result<Vec<DiffReport>> {
                 continue;
             }
             
-            let dest_content = match fs::read(&dest) {
+            if source_hash.is_some() && cache.hash(&dest).ok() == source_hash {
+                continue;
+            }
+            
+            if source_data.is_none() {
+                match fsutil::ensure_regular_file(&source).and_then(|_| fs::read(&source)) {
+                    Ok(content) => source_data = Some(content),
+                    Err(e) => {
+                        diffs.push(DiffReport::unreadable(&source, &source, e));
+                        break;
+                    }
+                }
+            }
+            let Some(source_content) = source_data.as_deref() else {
+                break;
+            };
+            let source_content = match mapping {
+                Some(mapping) => mapping.destination_content(source_content),
+                None => Cow::Borrowed(source_content),
+            };
+            
+            let dest_content = match backend.read(&dest) {
                 Ok(content) => content,
                 Err(e) => {
-                    error!("Error reading {}: {}", dest.display(), e);
+                    diffs.push(DiffReport::unreadable(&source, &dest, e));
                     continue;
                 }
             };
             
-            if source_content != dest_content {
-                diffs.push(DiffReport::ContentDiffers {
-                    source: source.clone(),
-                    destination: dest.clone(),
-                    source_size: source_content.len(),
-                    dest_size: dest_content.len(),
-                });
-            }
+            diffs.extend(compare_contents(&config, &source, &dest, &source_content, &dest_content));
         }
     }
     
+    cache.save();
     Ok(diffs)
 }
 
+/// Compare `source` with an arbitrary `other` file, as if it was one of its destinations.
+///
+/// Neither file has to be tracked, but if `source` is, its mapping's filters apply.
+#[instrument(fields(source = %source.display(), other = %other.display()))]
+pub fn diff_against(source: &Path, other: &Path) -> Result<Option<DiffReport>> {
+    let config = Config::load()?;
+    let source = config.resolve_path(source).unwrap_or_else(|_| source.to_path_buf());
+    let other = config.resolve_path(other).unwrap_or_else(|_| other.to_path_buf());
+    
+    if !source.exists() {
+        return Ok(Some(DiffReport::SourceMissing { source }));
+    }
+    if !other.exists() {
+        return Ok(Some(DiffReport::DestinationMissing { source, destination: other }));
+    }
+    
+    let source_content = fs::read(&source).with_context(|| format!("Failed to read {}", source.display()))?;
+    let source_content = match config.mappings.get(&source) {
+        Some(mapping) => mapping.destination_content(&source_content),
+        None => Cow::Borrowed(&source_content[..]),
+    };
+    let other_content = fs::read(&other).with_context(|| format!("Failed to read {}", other.display()))?;
+    
+    Ok(compare_contents(&config, &source, &other, &source_content, &other_content))
+}
+
+fn compare_contents(
+    config: &Config,
+    source: &Path,
+    destination: &Path,
+    source_content: &[u8],
+    dest_content: &[u8],
+) -> Option<DiffReport> {
+    if markdown::contents_equal(source_content, dest_content, config.semantic_md_compare) {
+        return None;
+    }
+    Some(DiffReport::ContentDiffers {
+        source: source.to_path_buf(),
+        destination: destination.to_path_buf(),
+        source_size: source_content.len(),
+        dest_size: dest_content.len(),
+        changes: markdown::change_stats(source_content, dest_content),
+        similarity: markdown::similarity(source_content, dest_content),
+    })
+}
+
 #[derive(Debug)]
 pub enum DiffReport {
     SourceMissing {
@@ -131,5 +801,94 @@ pub enum DiffReport {
         destination: std::path::PathBuf,
         source_size: usize,
         dest_size: usize,
+        /// How the destination differs from the source, for text files.
+        changes: Option<ChangeStats>,
+        /// Line similarity from 0.0 to 1.0, for text files up to a megabyte.
+        similarity: Option<f32>,
+    },
+    /// `path`, the source or one of its destinations, couldn't be read to compare them.
+    Unreadable {
+        source: std::path::PathBuf,
+        path: std::path::PathBuf,
+        error: String,
     },
-}
\ No newline at end of file
+}
+
+impl DiffReport {
+    /// The path the report is about: the source if it is missing, otherwise the destination.
+    pub fn path(&self) -> &Path {
+        match self {
+            DiffReport::SourceMissing { source } => source,
+            DiffReport::DestinationMissing { destination, .. } => destination,
+            DiffReport::ContentDiffers { destination, .. } => destination,
+            DiffReport::Unreadable { path, .. } => path,
+        }
+    }
+    
+    /// Whether the problem is with the source rather than one of its destinations.
+    pub fn is_source_side(&self) -> bool {
+        match self {
+            DiffReport::SourceMissing { .. } => true,
+            DiffReport::Unreadable { source, path, .. } => source == path,
+            _ => false,
+        }
+    }
+    
+    fn unreadable(source: &Path, path: &Path, error: std::io::Error) -> Self {
+        DiffReport::Unreadable {
+            source: source.to_path_buf(),
+            path: path.to_path_buf(),
+            error: error.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn lower_priorities_are_synced_first_in_their_own_groups() {
+        let mut config = Config::default();
+        for (source, priority) in [("/a.md", 0), ("/b.md", -1), ("/c.md", 5), ("/d.md", 0)] {
+            config.mappings.insert(source.into(), Mapping { priority, ..Default::default() });
+        }
+        let mappings = ["/d.md", "/c.md", "/b.md", "/a.md"].map(|source| (PathBuf::from(source), Vec::new()));
+        
+        let groups: Vec<Vec<PathBuf>> = group_by_priority(&config, mappings.to_vec())
+            .into_iter()
+            .map(|group| group.into_iter().map(|(source, _)| source).collect())
+            .collect();
+        
+        assert_eq!(groups, [vec![PathBuf::from("/b.md")], vec!["/a.md".into(), "/d.md".into()], vec!["/c.md".into()]]);
+    }
+    
+    #[test]
+    fn relative_patterns_match_anywhere() {
+        let matcher = source_matcher("docs/*.md").unwrap();
+        
+        assert!(matcher.is_match("/home/me/project/docs/intro.md"));
+        assert!(!matcher.is_match("/home/me/project/notes/intro.md"));
+    }
+    
+    #[test]
+    fn absolute_patterns_match_from_the_root() {
+        let matcher = source_matcher("/notes/*.md").unwrap();
+        
+        assert!(matcher.is_match("/notes/a.md"));
+        assert!(!matcher.is_match("/home/notes/a.md"));
+    }
+    
+    #[test]
+    fn only_keeps_matching_sources() {
+        let mappings = vec![
+            (PathBuf::from("/a/docs/x.md"), vec![PathBuf::from("/b/x.md")]),
+            (PathBuf::from("/a/notes/y.md"), vec![PathBuf::from("/b/y.md")]),
+        ];
+        
+        let kept = filter_by_source(mappings.clone(), Some("docs/*")).unwrap();
+        assert_eq!(kept, mappings[..1]);
+        assert_eq!(filter_by_source(mappings.clone(), None).unwrap(), mappings);
+        assert!(filter_by_source(mappings, Some("docs/[")).is_err());
+    }
+}
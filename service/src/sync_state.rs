@@ -0,0 +1,117 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use tracing::{debug, instrument};
+
+use crate::config::Config;
+use crate::fsutil;
+
+/// When each destination was last written and with what, kept in `sync-state.json` next
+/// to the config so that syncing doesn't rewrite the config itself.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SyncState {
+    destinations: BTreeMap<PathBuf, SyncRecord>,
+    /// Destinations recorded since loading, the only ones [`SyncState::save`] writes back.
+    #[serde(skip)]
+    changed: BTreeSet<PathBuf>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncRecord {
+    pub source: PathBuf,
+    pub synced_at: SystemTime,
+    /// Hash of the content written, to tell whether the destination changed since.
+    pub hash: String,
+}
+
+impl SyncState {
+    pub fn file_path() -> Result<PathBuf> {
+        Ok(Config::config_dir()?.join("sync-state.json"))
+    }
+    
+    /// Load the stored state, starting empty if there is none or it can't be read.
+    pub fn load() -> Self {
+        Self::file_path()
+            .ok()
+            .and_then(|path| fs::read(path).ok())
+            .and_then(|content| serde_json::from_slice(&content).ok())
+            .unwrap_or_default()
+    }
+    
+    /// Remember that `content` of `source` was written to `destination` at `when`.
+    pub fn record(&mut self, source: &Path, destination: &Path, when: SystemTime, content: &[u8]) {
+        self.destinations.insert(destination.to_path_buf(), SyncRecord {
+            source: source.to_path_buf(),
+            synced_at: when,
+            hash: fsutil::content_hash(content),
+        });
+        self.changed.insert(destination.to_path_buf());
+    }
+    
+    pub fn get(&self, destination: &Path) -> Option<&SyncRecord> {
+        self.destinations.get(destination)
+    }
+    
+    /// Write the recorded destinations back.
+    ///
+    /// The file is re-read first and only the entries recorded through this instance are
+    /// replaced, so a concurrent `mdman sync` and watcher don't undo each other's records.
+    #[instrument(skip(self), fields(changed = self.changed.len()))]
+    pub fn save(&self) -> Result<()> {
+        if self.changed.is_empty() || fsutil::read_only() {
+            return Ok(());
+        }
+        
+        let mut current = Self::load();
+        for destination in &self.changed {
+            if let Some(record) = self.destinations.get(destination) {
+                current.destinations.insert(destination.clone(), record.clone());
+            }
+        }
+        
+        let path = Self::file_path()?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, serde_json::to_string_pretty(&current)?)?;
+        debug!("Saved sync state for {} destinations", self.changed.len());
+        Ok(())
+    }
+}
+
+/// Record a single write and save it right away.
+pub fn record_sync(source: &Path, destination: &Path, when: SystemTime, content: &[u8]) -> Result<()> {
+    let mut state = SyncState::load();
+    state.record(source, destination, when, content);
+    state.save()
+}
+
+/// When `destination` was last written by mdman, if it ever was.
+pub fn last_synced(destination: &Path) -> Option<SystemTime> {
+    SyncState::load().get(destination).map(|record| record.synced_at)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    
+    #[test]
+    fn latest_record_of_a_destination_wins() {
+        let mut state = SyncState::default();
+        let earlier = SystemTime::UNIX_EPOCH + Duration::from_secs(100);
+        let later = earlier + Duration::from_secs(60);
+        
+        state.record(Path::new("/a.md"), Path::new("/copy.md"), earlier, b"one");
+        state.record(Path::new("/a.md"), Path::new("/copy.md"), later, b"two");
+        
+        let record = state.get(Path::new("/copy.md")).unwrap();
+        assert_eq!(record.synced_at, later);
+        assert_eq!(record.hash, fsutil::content_hash(b"two"));
+        assert_eq!(record.source, Path::new("/a.md"));
+        assert_eq!(state.get(Path::new("/other.md")), None);
+    }
+}
@@ -0,0 +1,69 @@
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+use tracing::{debug, instrument};
+
+/// Expand a destination template such as `~/projects/*/README.md` into concrete paths.
+///
+/// Only the directory part may contain glob patterns; it is matched against existing
+/// directories and the file name is appended to each match.
+#[instrument]
+pub fn expand_template(template: &str) -> Result<Vec<PathBuf>> {
+    let expanded = expand_home(template)?;
+    let template_path = Path::new(&expanded);
+    
+    let file_name = template_path
+        .file_name()
+        .context("Template must end with a file name")?;
+    if is_glob(&file_name.to_string_lossy()) {
+        anyhow::bail!("Template file name {} must not contain wildcards", file_name.to_string_lossy());
+    }
+    
+    let dir_pattern = template_path
+        .parent()
+        .context("Template must contain a directory part")?
+        .to_string_lossy()
+        .into_owned();
+    
+    let mut destinations = Vec::new();
+    for entry in glob::glob(&dir_pattern).context("Invalid template pattern")? {
+        let dir = entry?;
+        if dir.is_dir() {
+            destinations.push(dir.join(file_name));
+        }
+    }
+    destinations.sort();
+    
+    debug!("Template {} expanded to {} destinations", template, destinations.len());
+    Ok(destinations)
+}
+
+fn expand_home(template: &str) -> Result<String> {
+    if template == "~" || template.starts_with("~/") {
+        let home = dirs::home_dir().context("Could not determine home directory")?;
+        Ok(format!("{}{}", home.display(), &template[1..]))
+    } else {
+        Ok(template.to_string())
+    }
+}
+
+fn is_glob(s: &str) -> bool {
+    s.contains(['*', '?', '['])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn wildcard_file_names_are_rejected() {
+        assert!(expand_template("/tmp/*/notes-*.md").is_err());
+    }
+    
+    #[test]
+    fn home_is_expanded() {
+        let home = dirs::home_dir().unwrap();
+        
+        assert_eq!(expand_home("~/notes.md").unwrap(), format!("{}/notes.md", home.display()));
+        assert_eq!(expand_home("/notes/~/a.md").unwrap(), "/notes/~/a.md");
+    }
+}
@@ -1,21 +1,262 @@
-use anyhow::Result;
-use notify::{Config as NotifyConfig, Event, RecommendedWatcher, RecursiveMode, Watcher};
-use notify_rust::Notification;
-use std::collections::HashMap;
+use anyhow::{Context, Result};
+use notify::event::{MetadataKind, ModifyKind};
+use notify::{Config as NotifyConfig, Event, EventHandler, PollWatcher, RecommendedWatcher, RecursiveMode, Watcher};
+use rayon::prelude::*;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs;
 use std::path::{Path, PathBuf};
-use std::sync::mpsc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc};
 use std::thread;
-use std::time::{Duration, Instant};
-use tracing::{error, info, instrument, warn};
+use std::time::{Duration, Instant, SystemTime};
+use tracing::{debug, error, info, instrument, warn};
 
-use crate::config::Config;
+use crate::backend;
+use crate::config::{Config, Mapping, QueueFullPolicy, WatchBackend};
+#[cfg(all(unix, feature = "control"))]
+use crate::control::{self, ControlMessage, ControlRequest};
+use crate::fsutil;
+use crate::heartbeat::Heartbeat;
+use crate::hooks::{self, HookEvent};
+use crate::markdown;
+use crate::notifications::{NotificationKind, SyncNotificationMode};
+use crate::sync_state;
+
+/// What the watcher did, for library users that want to react without parsing logs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WatchEvent {
+    Synced { source: PathBuf, destinations: Vec<PathBuf> },
+    Desynced { source: PathBuf, destination: PathBuf },
+    SourceDeleted { source: PathBuf, destinations: Vec<PathBuf> },
+    DestinationMoved { source: PathBuf, from: PathBuf, to: PathBuf },
+    /// A destination of `source` was moved onto `to`, which is a destination of `owner`.
+    MoveConflict { source: PathBuf, from: PathBuf, to: PathBuf, owner: PathBuf },
+    SourceRenamed { from: PathBuf, to: PathBuf, destinations: Vec<PathBuf> },
+    Error { message: String },
+}
+
+/// What the watcher did, returned once it stops on an exit condition.
+#[derive(Debug, Clone, Default)]
+pub struct WatchSummary {
+    /// Number of [`WatchEvent`]s, whether or not anybody listened for them.
+    pub events: usize,
+    pub synced: usize,
+    pub desynced: usize,
+    pub errors: usize,
+    pub elapsed: Duration,
+}
+
+impl WatchSummary {
+    fn record(&mut self, event: &WatchEvent) {
+        self.events += 1;
+        match event {
+            WatchEvent::Synced { destinations, .. } => self.synced += destinations.len(),
+            WatchEvent::Desynced { .. } => self.desynced += 1,
+            WatchEvent::Error { .. } => self.errors += 1,
+            WatchEvent::SourceDeleted { .. }
+            | WatchEvent::DestinationMoved { .. }
+            | WatchEvent::MoveConflict { .. }
+            | WatchEvent::SourceRenamed { .. } => {}
+        }
+    }
+}
 
 pub struct FileWatcher {
     config: Config,
     reverse_mappings: HashMap<PathBuf, PathBuf>,
     last_known_content: HashMap<PathBuf, Vec<u8>>,
     recently_synced: HashMap<PathBuf, Instant>,
+    batch_window: Option<Duration>,
+    on_desync: Option<String>,
+    events: Option<mpsc::Sender<WatchEvent>>,
+    watched: Vec<PathBuf>,
+    state_file: Option<PathBuf>,
+    paused: HashSet<PathBuf>,
+    healthcheck: Option<(PathBuf, Duration)>,
+    /// Destinations put off because their directory was missing, with their source.
+    queued: HashMap<PathBuf, PathBuf>,
+    /// Sources with a debounce override that changed, and when they are due to be synced.
+    debounced: HashMap<PathBuf, Instant>,
+    /// Destinations that just disappeared, kept briefly in case they reappear elsewhere.
+    vanished: Vec<VanishedFile>,
+    /// Rename destinations along with their source, see [`FileWatcher::with_follow_renames`].
+    follow_renames: bool,
+    /// Start monitoring destinations created after startup, see [`FileWatcher::with_watch_new_destinations`].
+    watch_new_destinations: bool,
+    /// Log how many destinations are out of sync when starting, see [`FileWatcher::with_quiet_startup`].
+    startup_summary: bool,
+    /// Only log what would be synced, see [`FileWatcher::with_dry_run`].
+    dry_run: bool,
+    /// Sources that just disappeared; only treated as deleted once [`RENAME_WINDOW`] passed.
+    vanished_sources: Vec<VanishedFile>,
+    failing: HashMap<PathBuf, FailingDestination>,
+    /// Stop after running this long, e.g. for short-lived runs in CI.
+    exit_after: Option<Duration>,
+    /// Stop once this many events were emitted.
+    exit_after_events: Option<usize>,
+    summary: WatchSummary,
+    #[cfg(all(unix, feature = "control"))]
+    control: Option<mpsc::Receiver<ControlMessage>>,
+}
+
+/// Longest a batch keeps growing under a steady stream of events, and the most events it holds.
+const MAX_BATCH_AGE: Duration = Duration::from_secs(5);
+const MAX_BATCH_EVENTS: usize = 10_000;
+
+/// How long after a destination disappears a file with the same content counts as it being moved.
+const RENAME_WINDOW: Duration = Duration::from_secs(2);
+
+/// Wait before notifying about the same failing destination again, doubled after every
+/// notification up to [`MAX_FAILURE_BACKOFF`].
+const INITIAL_FAILURE_BACKOFF: Duration = Duration::from_secs(60);
+const MAX_FAILURE_BACKOFF: Duration = Duration::from_secs(60 * 60);
+
+/// A destination whose writes keep failing, e.g. because its disk is full.
+struct FailingDestination {
+    failures: u32,
+    /// Failures since the last notification about them.
+    unreported: u32,
+    next_notification: Instant,
+    backoff: Duration,
+}
+
+impl FailingDestination {
+    fn new(now: Instant) -> Self {
+        Self {
+            failures: 0,
+            unreported: 0,
+            next_notification: now,
+            backoff: INITIAL_FAILURE_BACKOFF,
+        }
+    }
+    
+    /// Count a failure at `now`, returning the notification body if it is due.
+    fn fail(&mut self, now: Instant, dest: &Path, message: &str) -> Option<String> {
+        self.failures += 1;
+        self.unreported += 1;
+        if now < self.next_notification {
+            return None;
+        }
+        
+        let body = if self.unreported > 1 {
+            format!("{}\n{} (failed {} times since the last notice)", dest.display(), message, self.unreported)
+        } else {
+            format!("{}\n{}", dest.display(), message)
+        };
+        self.unreported = 0;
+        self.next_notification = now + self.backoff;
+        self.backoff = (self.backoff * 2).min(MAX_FAILURE_BACKOFF);
+        Some(body)
+    }
+}
+
+/// A tracked file that just disappeared, possibly because it is being moved.
+struct VanishedFile {
+    path: PathBuf,
+    /// Hash of the content the file should have had.
+    hash: String,
+    when: Instant,
+}
+
+/// Whether an event can change what's synced: data or name changes, creations and removals.
+///
+/// Pure metadata changes (permissions, timestamps, xattrs) are ignored unless `include_metadata` is set.
+fn is_relevant(kind: &notify::EventKind, include_metadata: bool) -> bool {
+    match kind {
+        notify::EventKind::Modify(ModifyKind::Metadata(_)) => include_metadata,
+        notify::EventKind::Modify(_) | notify::EventKind::Create(_) | notify::EventKind::Remove(_) => true,
+        _ => false,
+    }
+}
+
+type EventResult = Result<Event, notify::Error>;
+
+/// Hands events from notify's thread to the watch loop, through a queue bounded by
+/// `event_queue_capacity` if one is configured.
+enum EventSender {
+    Unbounded(mpsc::Sender<EventResult>),
+    Bounded {
+        tx: mpsc::SyncSender<EventResult>,
+        policy: QueueFullPolicy,
+        /// Events dropped since the watch loop last reported them.
+        dropped: Arc<AtomicUsize>,
+    },
+}
+
+impl EventHandler for EventSender {
+    fn handle_event(&mut self, event: EventResult) {
+        // Send errors only mean the watch loop is gone
+        match self {
+            EventSender::Unbounded(tx) => {
+                let _ = tx.send(event);
+            }
+            EventSender::Bounded { tx, policy: QueueFullPolicy::Block, .. } => {
+                let _ = tx.send(event);
+            }
+            EventSender::Bounded { tx, policy: QueueFullPolicy::Drop, dropped } => {
+                if let Err(mpsc::TrySendError::Full(_)) = tx.try_send(event) {
+                    dropped.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        }
+    }
+}
+
+/// A file watcher using the configured backend, and the queue it delivers events to.
+struct EventSource {
+    watcher: Box<dyn Watcher>,
+    rx: mpsc::Receiver<EventResult>,
+    dropped: Arc<AtomicUsize>,
+}
+
+fn start_watcher(config: &Config) -> Result<EventSource> {
+    let dropped = Arc::new(AtomicUsize::new(0));
+    let (sender, rx) = match config.event_queue_capacity {
+        Some(capacity) => {
+            let (tx, rx) = mpsc::sync_channel(capacity);
+            let policy = config.on_event_queue_full;
+            (EventSender::Bounded { tx, policy, dropped: Arc::clone(&dropped) }, rx)
+        }
+        None => {
+            let (tx, rx) = mpsc::channel();
+            (EventSender::Unbounded(tx), rx)
+        }
+    };
+    
+    let mut notify_config = NotifyConfig::default();
+    if let Some(interval) = config.poll_interval_ms {
+        notify_config = notify_config.with_poll_interval(Duration::from_millis(interval));
+    }
+    let watcher: Box<dyn Watcher> = match config.watch_backend {
+        WatchBackend::Native => Box::new(RecommendedWatcher::new(sender, notify_config)?),
+        WatchBackend::Poll => Box::new(PollWatcher::new(sender, notify_config)?),
+    };
+    Ok(EventSource { watcher, rx, dropped })
+}
+
+/// What happened to the tracking of a deleted source, `kept` in safe mode.
+fn source_deleted_note(kept: bool) -> &'static str {
+    if kept {
+        "Its tracking was kept since safe mode is enabled, restore the source or run `mdman untrack` to drop it."
+    } else {
+        "Its tracking was removed and the destinations are no longer watched."
+    }
+}
+
+/// Most paths listed in a notification; notification daemons truncate long bodies anyway.
+const MAX_NOTIFIED_PATHS: usize = 5;
+
+/// `paths` as a bulleted list for a notification body, cut short after [`MAX_NOTIFIED_PATHS`].
+fn capped_list(paths: &[PathBuf]) -> String {
+    let mut lines: Vec<String> = paths
+        .iter()
+        .take(MAX_NOTIFIED_PATHS)
+        .map(|path| format!("  - {}", path.display()))
+        .collect();
+    if paths.len() > MAX_NOTIFIED_PATHS {
+        lines.push(format!("  +{} more", paths.len() - MAX_NOTIFIED_PATHS));
+    }
+    lines.join("\n")
 }
 
 impl FileWatcher {
@@ -25,16 +266,16 @@ impl FileWatcher {
         let mut reverse_mappings = HashMap::new();
         let mut last_known_content = HashMap::new();
         
-        for (source, destinations) in config.mappings.iter() {
-            for dest in destinations {
-                reverse_mappings.insert(dest.clone(), source.clone());
+        for (source, mapping) in config.mappings.iter() {
+            for dest in mapping.targets() {
+                reverse_mappings.insert(dest, source.clone());
             }
             
             // Initialize with current content
-            if source.exists() {
-                if let Ok(content) = fs::read(source) {
-                    last_known_content.insert(source.clone(), content);
-                }
+            if source.exists()
+                && let Ok(content) = fs::read(source)
+            {
+                last_known_content.insert(source.clone(), content);
             }
         }
         
@@ -43,38 +284,308 @@ impl FileWatcher {
             reverse_mappings, 
             last_known_content,
             recently_synced: HashMap::new(),
+            batch_window: None,
+            on_desync: None,
+            events: None,
+            watched: Vec::new(),
+            state_file: None,
+            paused: HashSet::new(),
+            healthcheck: None,
+            queued: HashMap::new(),
+            debounced: HashMap::new(),
+            vanished: Vec::new(),
+            follow_renames: false,
+            watch_new_destinations: false,
+            startup_summary: true,
+            dry_run: false,
+            vanished_sources: Vec::new(),
+            failing: HashMap::new(),
+            exit_after: None,
+            exit_after_events: None,
+            summary: WatchSummary::default(),
+            #[cfg(all(unix, feature = "control"))]
+            control: None,
         })
     }
     
+    /// Collect events until none arrive for `window`, then process them in one pass.
+    /// A batch is processed after at most 5 seconds even if events keep arriving.
+    pub fn with_batch_window(mut self, window: Duration) -> Self {
+        self.batch_window = Some(window);
+        self
+    }
+    
+    /// Run `command` on every detected desync, taking precedence over the config's `on_desync`
+    /// but not over that of the desynced destination's mapping.
+    pub fn with_on_desync(mut self, command: String) -> Self {
+        self.on_desync = Some(command);
+        self
+    }
+    
+    /// Touch `path` every `interval` while the event loop keeps running, for external watchdogs.
+    pub fn with_healthcheck_file(mut self, path: PathBuf, interval: Duration) -> Self {
+        self.healthcheck = Some((path, interval));
+        self
+    }
+    
+    /// Rewrite a JSON snapshot of the watcher's state to `path` after every handled batch.
+    pub fn with_state_file(mut self, path: PathBuf) -> Self {
+        self.state_file = Some(path);
+        self
+    }
+    
+    /// When a source is renamed, update its mapping and rename destinations that share its
+    /// file name the same way, instead of treating the rename as a deletion.
+    pub fn with_follow_renames(mut self) -> Self {
+        self.follow_renames = true;
+        self
+    }
+    
+    /// Watch the directories of destinations missing at startup, so a destination created
+    /// later is monitored from then on and checked against its source right away.
+    pub fn with_watch_new_destinations(mut self) -> Self {
+        self.watch_new_destinations = true;
+        self
+    }
+    
+    /// Skip checking which destinations are out of sync when starting, which reads every
+    /// tracked file.
+    pub fn with_quiet_startup(mut self) -> Self {
+        self.startup_summary = false;
+        self
+    }
+    
+    /// Decide what each event would sync and log it, without writing any destination.
+    ///
+    /// Switches the whole process to read-only mode, so renames and deletions aren't
+    /// saved to the config either. The content destinations are compared against stays
+    /// as it was, so skipped syncs aren't later mistaken for direct edits.
+    pub fn with_dry_run(mut self) -> Self {
+        fsutil::set_read_only();
+        self.dry_run = true;
+        self
+    }
+    
+    /// Stop watching once `duration` has passed.
+    pub fn with_exit_after(mut self, duration: Duration) -> Self {
+        self.exit_after = Some(duration);
+        self
+    }
+    
+    /// Stop watching once `count` events (syncs, desyncs, errors, ...) happened.
+    pub fn with_exit_after_events(mut self, count: usize) -> Self {
+        self.exit_after_events = Some(count);
+        self
+    }
+    
+    /// Watch until an exit condition is met, which without one is never.
+    #[instrument(skip(self))]
+    pub fn run(&mut self) -> Result<WatchSummary> {
+        self.events = None;
+        self.watch_loop()
+    }
+    
+    /// Like [`FileWatcher::run`], but also reports everything it does on `events`.
+    #[instrument(skip(self, events))]
+    pub fn run_with_events(&mut self, events: mpsc::Sender<WatchEvent>) -> Result<WatchSummary> {
+        self.events = Some(events);
+        self.watch_loop()
+    }
+    
+    /// Watch only the config file and log every reload, without watching or syncing
+    /// any mapped files. Useful for checking that config changes are picked up.
     #[instrument(skip(self))]
-    pub fn run(&mut self) -> Result<()> {
-        let (tx, rx) = mpsc::channel();
+    pub fn run_config_only(&mut self) -> Result<()> {
+        let config_path = Config::config_file_path()?;
+        let config_dir = Config::config_dir()?;
+        fs::create_dir_all(&config_dir)?;
         
-        let mut watcher = RecommendedWatcher::new(tx, NotifyConfig::default())?;
+        let EventSource { mut watcher, rx, .. } = start_watcher(&self.config)?;
+        // Watch the directory since editors often replace the file rather than modify it
+        watcher.watch(&config_dir, RecursiveMode::NonRecursive)?;
         
-        let mut watched_count = 0;
+        info!("Watching {} for config changes only", config_path.display());
         
-        for (source_file, destinations) in &self.config.mappings {
+        loop {
+            let event = match rx.recv() {
+                Ok(event) => event,
+                Err(e) => {
+                    error!("Watch error: {e}");
+                    thread::sleep(Duration::from_secs(1));
+                    continue;
+                }
+            };
+            
+            // A single save usually produces several events, coalesce them into one reload
+            let events = Self::collect_batch(event, &rx, Duration::from_millis(200), MAX_BATCH_AGE);
+            let touches_config = events
+                .iter()
+                .flatten()
+                .any(|event| event.paths.iter().any(|path| path == &config_path));
+            if !touches_config {
+                continue;
+            }
+            
+            let previous = self.config.mappings.len();
+            match Config::load() {
+                Ok(config) => {
+                    let current = config.mappings.len();
+                    info!(
+                        "Config reloaded: {} mappings ({:+})",
+                        current,
+                        current as i64 - previous as i64
+                    );
+                    self.config = config;
+                }
+                Err(e) => error!("Failed to reload config: {e}"),
+            }
+        }
+    }
+    
+    fn emit(&mut self, event: WatchEvent) {
+        self.summary.record(&event);
+        if let Some(events) = &self.events {
+            // A dropped receiver just means nobody is listening anymore
+            let _ = events.send(event);
+        }
+    }
+    
+    fn debounce_due(&self) -> bool {
+        let now = Instant::now();
+        self.debounced.values().any(|due| *due <= now)
+    }
+    
+    /// Whether an exit condition configured with `with_exit_after*` is met.
+    fn should_exit(&self, started: Instant) -> bool {
+        self.exit_after.is_some_and(|duration| started.elapsed() >= duration)
+            || self.exit_after_events.is_some_and(|count| self.summary.events >= count)
+    }
+    
+    fn watch_loop(&mut self) -> Result<WatchSummary> {
+        let EventSource { mut watcher, rx, dropped } = start_watcher(&self.config)?;
+        
+        self.watched.clear();
+        
+        for (source_file, mapping) in &self.config.mappings {
             if source_file.exists() {
                 watcher.watch(source_file, RecursiveMode::NonRecursive)?;
-                watched_count += 1;
+                self.watched.push(source_file.clone());
             }
             
-            for dest_file in destinations {
+            for dest_file in mapping.targets() {
                 if dest_file.exists() {
-                    watcher.watch(dest_file, RecursiveMode::NonRecursive)?;
-                    watched_count += 1;
+                    watcher.watch(&dest_file, RecursiveMode::NonRecursive)?;
+                    self.watched.push(dest_file);
                 }
             }
         }
         
-        info!("Watching {watched_count} files for changes...");
+        // Moved files are only seen if the directory they land in is watched
+        let mut dirs: Vec<PathBuf> = Vec::new();
+        if self.config.detect_renames {
+            let destinations = self.config.mappings.values().flat_map(Mapping::targets);
+            dirs.extend(destinations.filter_map(|dest| dest.parent().map(Path::to_path_buf)));
+        }
+        if self.watch_new_destinations {
+            let destinations = self.config.mappings.values().flat_map(Mapping::targets);
+            let missing = destinations.filter(|dest| !backend::is_remote(dest) && !dest.exists());
+            dirs.extend(missing.filter_map(|dest| dest.parent().map(Path::to_path_buf)));
+        }
+        if self.follow_renames {
+            dirs.extend(self.config.mappings.keys().filter_map(|source| source.parent()).map(Path::to_path_buf));
+        }
+        dirs.retain(|dir| dir.is_dir());
+        dirs.sort();
+        dirs.dedup();
+        for dir in dirs {
+            watcher.watch(&dir, RecursiveMode::NonRecursive)?;
+            self.watched.push(dir);
+        }
+        
+        for aggregate in self.config.aggregates.values() {
+            for source in &aggregate.sources {
+                if source.exists() && !self.watched.contains(source) {
+                    watcher.watch(source, RecursiveMode::NonRecursive)?;
+                    self.watched.push(source.clone());
+                }
+            }
+        }
+        
+        info!("Watching {} files for changes...", self.watched.len());
+        if self.dry_run {
+            info!("Dry run, destinations will not be written");
+        }
+        if self.startup_summary {
+            // Only reported, drifted destinations are left for the user to sync or repair
+            match crate::sync::check_diff(None, None) {
+                Ok(diffs) => info!("{} watched, {} currently drifted", self.watched.len(), diffs.len()),
+                Err(e) => warn!("Failed to check which files are in sync: {:#}", e),
+            }
+        }
+        
+        #[cfg(all(unix, feature = "control"))]
+        if let Some(path) = self.config.control_socket.clone() {
+            self.control = Some(control::listen(&path)?);
+        }
+        
+        // SIGUSR1 asks for a state dump, handy for "why didn't my file sync" debugging
+        let dump_requested = Arc::new(AtomicBool::new(false));
+        #[cfg(unix)]
+        signal_hook::flag::register(signal_hook::consts::SIGUSR1, Arc::clone(&dump_requested))?;
+        
+        let heartbeat = self.healthcheck
+            .clone()
+            .map(|(path, interval)| Heartbeat::start(path, interval));
+        
+        let started = Instant::now();
+        self.summary = WatchSummary::default();
         
         loop {
-            match rx.recv() {
+            if self.should_exit(started) {
+                self.summary.elapsed = started.elapsed();
+                info!("Exit condition reached, stopping after {} events", self.summary.events);
+                return Ok(self.summary.clone());
+            }
+            
+            if let Some(heartbeat) = &heartbeat {
+                heartbeat.beat();
+            }
+            
+            if dump_requested.swap(false, Ordering::SeqCst) {
+                info!("State dump:\n{}", self.dump_state());
+                self.write_state_file();
+            }
+            
+            #[cfg(all(unix, feature = "control"))]
+            self.poll_control();
+            
+            let dropped_count = dropped.swap(0, Ordering::Relaxed);
+            if dropped_count > 0 {
+                warn!("Event queue was full, dropped {} file events; affected files sync on their next change", dropped_count);
+            }
+            
+            match rx.recv_timeout(Duration::from_millis(250)) {
                 Ok(event) => {
-                    if let Err(e) = self.handle_event(event) {
+                    let events = match self.batch_window {
+                        Some(window) => Self::collect_batch(event, &rx, window, MAX_BATCH_AGE),
+                        None => vec![event],
+                    };
+                    if let Err(e) = self.handle_events(events) {
                         error!("Error handling event: {e}");
+                        self.emit(WatchEvent::Error { message: e.to_string() });
+                    }
+                    self.write_state_file();
+                }
+                Err(mpsc::RecvTimeoutError::Timeout) => {
+                    if let Err(e) = self.expire_vanished_sources() {
+                        error!("Error handling deleted source: {e}");
+                    }
+                    if self.debounce_due() {
+                        if let Err(e) = self.handle_events(Vec::new()) {
+                            error!("Error handling event: {e}");
+                            self.emit(WatchEvent::Error { message: e.to_string() });
+                        }
+                        self.write_state_file();
                     }
                 }
                 Err(e) => {
@@ -85,14 +596,159 @@ impl FileWatcher {
         }
     }
     
-    #[instrument(skip(self, event))]
-    fn handle_event(&mut self, event: Result<Event, notify::Error>) -> Result<()> {
-        let event = event?;
+    #[cfg(all(unix, feature = "control"))]
+    fn poll_control(&mut self) {
+        let Some(control) = &self.control else {
+            return;
+        };
+        let messages: Vec<ControlMessage> = control.try_iter().collect();
+        
+        for message in messages {
+            let reply = self.handle_control(message.request);
+            let _ = message.reply.send(reply);
+        }
+    }
+    
+    #[cfg(all(unix, feature = "control"))]
+    fn handle_control(&mut self, request: ControlRequest) -> serde_json::Value {
+        match request {
+            ControlRequest::Status => serde_json::json!({
+                "ok": true,
+                "mappings": self.config.mappings.len(),
+                "watched": self.watched,
+                "paused": self.paused,
+            }),
+            ControlRequest::Sync => {
+                let sources: Vec<PathBuf> = self.config.mappings
+                    .keys()
+                    .filter(|source| !self.paused.contains(*source))
+                    .cloned()
+                    .collect();
+                let mut synced = 0;
+                for source in &sources {
+                    match self.sync_file(source) {
+                        Ok((synced_files, _)) => synced += synced_files.len(),
+                        Err(e) => return control::error_reply(&e.to_string()),
+                    }
+                }
+                serde_json::json!({ "ok": true, "synced": synced })
+            }
+            ControlRequest::Pause { file } => match self.config.find_by_path(&file) {
+                Some((source, _)) => {
+                    info!("Paused syncing {}", source.display());
+                    self.paused.insert(source);
+                    serde_json::json!({ "ok": true })
+                }
+                None => control::error_reply(&format!("{} is not a tracked source", file.display())),
+            },
+            ControlRequest::Resume { file } => match self.config.find_by_path(&file) {
+                Some((source, _)) => {
+                    info!("Resumed syncing {}", source.display());
+                    self.paused.remove(&source);
+                    serde_json::json!({ "ok": true })
+                }
+                None => control::error_reply(&format!("{} is not a tracked source", file.display())),
+            },
+        }
+    }
+    
+    /// A snapshot of the watcher's internal state for debugging.
+    ///
+    /// File contents are represented by hashes only, never included verbatim.
+    pub fn dump_state(&self) -> String {
+        let reverse_mappings: BTreeMap<_, _> = self.reverse_mappings
+            .iter()
+            .map(|(dest, source)| (dest.display().to_string(), source.display().to_string()))
+            .collect();
+        let recently_synced: BTreeMap<_, _> = self.recently_synced
+            .iter()
+            .map(|(path, when)| (path.display().to_string(), when.elapsed().as_millis() as u64))
+            .collect();
+        let queued: BTreeMap<_, _> = self.queued
+            .iter()
+            .map(|(dest, source)| (dest.display().to_string(), source.display().to_string()))
+            .collect();
+        let debounced: BTreeMap<_, _> = self.debounced
+            .iter()
+            .map(|(source, due)| (source.display().to_string(), due.saturating_duration_since(Instant::now()).as_millis() as u64))
+            .collect();
+        let failing: BTreeMap<_, _> = self.failing
+            .iter()
+            .map(|(dest, state)| (dest.display().to_string(), state.failures))
+            .collect();
+        let content_hashes: BTreeMap<_, _> = self.last_known_content
+            .iter()
+            .map(|(path, content)| (path.display().to_string(), fsutil::content_hash(content)))
+            .collect();
+        
+        let state = serde_json::json!({
+            "watched": self.watched,
+            "reverse_mappings": reverse_mappings,
+            "recently_synced_ms_ago": recently_synced,
+            "last_known_content_hashes": content_hashes,
+            "queued_for_missing_directory": queued,
+            "debounced_ms_left": debounced,
+            "failing_destinations": failing,
+        });
+        
+        serde_json::to_string_pretty(&state).unwrap_or_default()
+    }
+    
+    fn write_state_file(&self) {
+        if let Some(path) = &self.state_file
+            && fsutil::may_write("write", path)
+            && let Err(e) = fs::write(path, self.dump_state())
+        {
+            error!("Failed to write state file {}: {}", path.display(), e);
+        }
+    }
+    
+    fn collect_batch(
+        first: Result<Event, notify::Error>,
+        rx: &mpsc::Receiver<EventResult>,
+        window: Duration,
+        max_age: Duration,
+    ) -> Vec<Result<Event, notify::Error>> {
+        let mut events = vec![first];
+        // A file rewritten more often than the window would otherwise never be synced
+        let deadline = Instant::now() + max_age.max(window);
+        
+        // Keep extending the batch while events keep arriving within the window
+        while events.len() < MAX_BATCH_EVENTS {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                break;
+            }
+            match rx.recv_timeout(window.min(remaining)) {
+                Ok(event) => events.push(event),
+                Err(_) => break,
+            }
+        }
+        
+        debug!("Collected a batch of {} events", events.len());
+        events
+    }
+    
+    #[instrument(skip(self, events), fields(event_count = events.len()))]
+    fn handle_events(&mut self, events: Vec<Result<Event, notify::Error>>) -> Result<()> {
+        let events: Vec<Event> = events
+            .into_iter()
+            .filter_map(|event| match event {
+                Ok(event) => Some(event),
+                Err(e) => {
+                    error!("Watch error: {e}");
+                    None
+                }
+            })
+            .filter(|event| {
+                // Polling reports writes as modification time changes
+                let polled_write = self.config.watch_backend == WatchBackend::Poll
+                    && event.kind == notify::EventKind::Modify(ModifyKind::Metadata(MetadataKind::WriteTime));
+                polled_write || is_relevant(&event.kind, self.config.sync_on_metadata_changes)
+            })
+            .collect();
         
-        if !matches!(
-            event.kind,
-            notify::EventKind::Modify(_) | notify::EventKind::Create(_) | notify::EventKind::Remove(_)
-        ) {
+        if events.is_empty() && !self.debounce_due() {
             return Ok(());
         }
         
@@ -105,122 +761,831 @@ impl FileWatcher {
             now.duration_since(*sync_time) < Duration::from_secs(5)
         });
         
-        for path in event.paths {
-            // Handle file removal
-            if matches!(event.kind, notify::EventKind::Remove(_)) {
-                // Check if it's a source file that was removed
-                if let Some(destinations) = self.config.mappings.get(&path).cloned() {
-                    self.warn_source_deleted(&path, &destinations)?;
-                    
-                    // Remove the deleted source from config
-                    self.config.mappings.remove(&path);
-                    
-                    // Save the updated config to persist the removal
-                    if let Err(e) = self.config.save() {
-                        error!("Failed to save config after removing deleted source: {}", e);
-                    }
-                    
-                    // Update reverse mappings to stop watching the destination files
-                    for dest in destinations {
-                        self.reverse_mappings.remove(&dest);
-                    }
-                }
+        // Each source is synced at most once per batch, even if it changed repeatedly
+        let mut pending_sources = Vec::new();
+        // Aggregated destinations to regenerate, with the source that triggered it
+        let mut pending_aggregates: Vec<(PathBuf, PathBuf)> = Vec::new();
+        
+        // Retry destinations whose directory has shown up since they were queued
+        let ready: Vec<PathBuf> = self.queued
+            .keys()
+            .filter(|dest| dest.parent().is_none_or(Path::exists))
+            .cloned()
+            .collect();
+        for dest in ready {
+            if let Some(source) = self.queued.remove(&dest)
+                && self.config.mappings.contains_key(&source)
+                && !self.paused.contains(&source)
+                && !pending_sources.contains(&source)
+            {
+                pending_sources.push(source);
+            }
+        }
+        
+        // Sync sources whose own debounce window passed without further changes
+        let now = Instant::now();
+        let due: Vec<PathBuf> = self.debounced
+            .iter()
+            .filter(|(_, due)| **due <= now)
+            .map(|(source, _)| source.clone())
+            .collect();
+        for source in due {
+            self.debounced.remove(&source);
+            if self.config.mappings.contains_key(&source)
+                && !self.paused.contains(&source)
+                && !pending_sources.contains(&source)
+            {
+                pending_sources.push(source);
+            }
+        }
+        
+        self.vanished.retain(|vanished| vanished.when.elapsed() < RENAME_WINDOW);
+        self.expire_vanished_sources()?;
+        
+        for event in events {
+            if self.config.detect_renames && self.handle_rename(&event)? {
+                continue;
+            }
+            if self.follow_renames && self.handle_source_rename(&event)? {
                 continue;
             }
             
-            let canonical_path = path.canonicalize().unwrap_or(path.clone());
-            
-            if self.config.mappings.contains_key(&canonical_path) {
-                self.sync_file(&canonical_path)?;
-            } else if let Some(source) = self.reverse_mappings.get(&canonical_path) {
-                // Check if this file was recently synced (within 2 seconds)
-                if let Some(sync_time) = self.recently_synced.get(&canonical_path) {
-                    if sync_time.elapsed() < Duration::from_secs(2) {
+            for path in event.paths {
+                // Handle file removal
+                if matches!(event.kind, notify::EventKind::Remove(_)) {
+                    let path = self.config.stored_path(&path);
+                    // Check if it's a source file that was removed
+                    if self.config.mappings.contains_key(&path) {
+                        self.forget_deleted_source(&path)?;
+                        pending_sources.retain(|source| source != &path);
+                    }
+                    continue;
+                }
+                
+                let canonical_path = self.config.resolve_path(&path).unwrap_or(path.clone());
+                let canonical_path = self.config.stored_path(&canonical_path);
+                
+                for destination in self.config.aggregates_containing(&canonical_path) {
+                    if !pending_aggregates.iter().any(|(pending, _)| *pending == destination) {
+                        pending_aggregates.push((destination, canonical_path.clone()));
+                    }
+                }
+                
+                if self.config.mappings.contains_key(&canonical_path) {
+                    if self.paused.contains(&canonical_path) {
+                        debug!("Skipping paused source {}", canonical_path.display());
+                    } else if let Some(ms) = self.config.debounce_overrides.get(&canonical_path) {
+                        // Every change restarts the window
+                        debug!("Syncing {} once it is unchanged for {}ms", canonical_path.display(), ms);
+                        pending_sources.retain(|source| source != &canonical_path);
+                        self.debounced.insert(canonical_path, Instant::now() + Duration::from_millis(*ms));
+                    } else if !pending_sources.contains(&canonical_path) {
+                        pending_sources.push(canonical_path);
+                    }
+                } else if let Some(source) = self.reverse_mappings.get(&canonical_path).cloned() {
+                    if self.watch_new_destinations
+                        && matches!(event.kind, notify::EventKind::Create(_))
+                        && !self.watched.contains(&canonical_path)
+                    {
+                        info!(
+                            event_kind = "destination-created",
+                            source = %source.display(),
+                            destination = %canonical_path.display(),
+                            "Destination {} was created, now monitoring it",
+                            canonical_path.display()
+                        );
+                        self.watched.push(canonical_path.clone());
+                    }
+                    // Check if this file was recently synced (within 2 seconds)
+                    if let Some(sync_time) = self.recently_synced.get(&canonical_path)
+                        && sync_time.elapsed() < Duration::from_secs(2)
+                    {
                         // Skip warning - this is likely our own modification
                         continue;
                     }
+                    if self.matches_source(&canonical_path, &source) {
+                        // The destination was edited but still matches its source
+                        continue;
+                    }
+                    self.warn_desync(&canonical_path, &source);
                 }
-                self.warn_desync(&canonical_path, source)?;
             }
         }
         
+        // Everything else is summed up in one notification at the end
+        let per_source = self.batch_window.is_none()
+            && self.config.notifications.sync_mode == SyncNotificationMode::PerSource;
+        
+        let mut results = Vec::new();
+        for source in pending_sources {
+            let (synced_files, desynced_files) = self.sync_file(&source)?;
+            if synced_files.is_empty() && desynced_files.is_empty() {
+                continue;
+            }
+            if !synced_files.is_empty() {
+                self.emit(WatchEvent::Synced {
+                    source: source.clone(),
+                    destinations: synced_files.clone(),
+                });
+            }
+            for destination in &desynced_files {
+                self.emit(WatchEvent::Desynced {
+                    source: source.clone(),
+                    destination: destination.clone(),
+                });
+            }
+            if per_source {
+                self.send_sync_notification(&source, &synced_files, &desynced_files)?;
+            }
+            results.push((source, synced_files, desynced_files));
+        }
+        
+        for (destination, source) in pending_aggregates {
+            if let Err(e) = self.regenerate_aggregate(&destination) {
+                error!("Failed to regenerate {}: {:#}", destination.display(), e);
+                self.emit(WatchEvent::Error { message: format!("{:#}", e) });
+                continue;
+            }
+            let synced_files = vec![destination];
+            self.emit(WatchEvent::Synced {
+                source: source.clone(),
+                destinations: synced_files.clone(),
+            });
+            if per_source {
+                self.send_sync_notification(&source, &synced_files, &[])?;
+            }
+            results.push((source, synced_files, Vec::new()));
+        }
+        
+        if !per_source && !results.is_empty() {
+            self.send_batch_notification(&results)?;
+        }
+        
         Ok(())
     }
     
+    /// Follow a destination that was moved, returning whether `event` was fully handled.
+    ///
+    /// A rename reported with both paths is followed directly. Otherwise a destination that
+    /// disappears is remembered for [`RENAME_WINDOW`], and an untracked file showing up with
+    /// the content it should have had is taken to be it.
+    fn handle_rename(&mut self, event: &Event) -> Result<bool> {
+        use notify::event::{CreateKind, RenameMode};
+        
+        match (event.kind, event.paths.as_slice()) {
+            (notify::EventKind::Modify(ModifyKind::Name(RenameMode::Both)), [from, to]) => {
+                self.follow_move(from, to)
+            }
+            (notify::EventKind::Remove(_) | notify::EventKind::Modify(ModifyKind::Name(RenameMode::From)), [path]) => {
+                let Some(source) = self.reverse_mappings.get(path).cloned() else {
+                    return Ok(false);
+                };
+                let Some(mapping) = self.config.mappings.get(&source) else {
+                    return Ok(false);
+                };
+                let content = fs::read(&source).unwrap_or_default();
+                self.vanished.push(VanishedFile {
+                    path: path.clone(),
+                    hash: fsutil::content_hash(&mapping.destination_content(&content)),
+                    when: Instant::now(),
+                });
+                Ok(true)
+            }
+            (
+                notify::EventKind::Create(CreateKind::File | CreateKind::Any)
+                | notify::EventKind::Modify(ModifyKind::Name(RenameMode::To)),
+                [path],
+            ) => {
+                if self.vanished.is_empty()
+                    || self.recently_synced.get(path).is_some_and(|when| when.elapsed() < RENAME_WINDOW)
+                {
+                    return Ok(false);
+                }
+                let Ok(content) = fs::read(path) else {
+                    return Ok(false);
+                };
+                let hash = fsutil::content_hash(&content);
+                match self.vanished.iter().position(|vanished| vanished.hash == hash) {
+                    // Landing on another destination of the same source is just an edit of it
+                    Some(index) if self.reverse_mappings.contains_key(path)
+                        && self.reverse_mappings.get(path) == self.reverse_mappings.get(&self.vanished[index].path) =>
+                    {
+                        Ok(false)
+                    }
+                    Some(index) => {
+                        let vanished = self.vanished.remove(index);
+                        self.follow_move(&vanished.path, path)
+                    }
+                    None => Ok(false),
+                }
+            }
+            _ => Ok(false),
+        }
+    }
+    
+    /// Stop tracking a source that was deleted, unless safe mode keeps its mapping.
+    fn forget_deleted_source(&mut self, path: &Path) -> Result<()> {
+        let Some(destinations) = self.config.mappings.get(path).map(|m| m.destinations.clone()) else {
+            return Ok(());
+        };
+        let safe_mode = self.config.safe_mode();
+        self.warn_source_deleted(path, &destinations, safe_mode);
+        self.emit(WatchEvent::SourceDeleted {
+            source: path.to_path_buf(),
+            destinations: destinations.clone(),
+        });
+        
+        if safe_mode {
+            return Ok(());
+        }
+        
+        // Remove the deleted source from config
+        self.config.mappings.remove(path);
+        self.config.debounce_overrides.remove(path);
+        
+        // Save the updated config to persist the removal
+        if !self.dry_run && let Err(e) = self.config.save() {
+            error!("Failed to save config after removing deleted source: {}", e);
+        }
+        
+        // Update reverse mappings to stop watching the destination files
+        for dest in destinations {
+            self.reverse_mappings.remove(&dest);
+        }
+        Ok(())
+    }
+    
+    /// Treat sources that vanished more than [`RENAME_WINDOW`] ago without reappearing
+    /// elsewhere as deleted.
+    fn expire_vanished_sources(&mut self) -> Result<()> {
+        let (expired, waiting): (Vec<VanishedFile>, _) = std::mem::take(&mut self.vanished_sources)
+            .into_iter()
+            .partition(|vanished| vanished.when.elapsed() >= RENAME_WINDOW);
+        self.vanished_sources = waiting;
+        
+        for vanished in expired {
+            if !vanished.path.exists() {
+                self.forget_deleted_source(&vanished.path)?;
+            }
+        }
+        Ok(())
+    }
+    
+    /// Follow a renamed source, returning whether `event` was fully handled.
+    ///
+    /// Like [`FileWatcher::handle_rename`], a rename may be reported with both paths, or as
+    /// separate removal and creation events, or only as the creation of a file with the
+    /// source's last known content while the source itself is gone.
+    fn handle_source_rename(&mut self, event: &Event) -> Result<bool> {
+        use notify::event::{CreateKind, RenameMode};
+        
+        match (event.kind, event.paths.as_slice()) {
+            (notify::EventKind::Modify(ModifyKind::Name(RenameMode::Both)), [from, to])
+                if self.config.mappings.contains_key(from) =>
+            {
+                self.follow_source_rename(from, to)
+            }
+            (notify::EventKind::Remove(_) | notify::EventKind::Modify(ModifyKind::Name(_)), [path])
+                if self.config.mappings.contains_key(path) && !path.exists() =>
+            {
+                if !self.vanished_sources.iter().any(|vanished| &vanished.path == path) {
+                    let content = self.last_known_content.get(path).cloned().unwrap_or_default();
+                    self.vanished_sources.push(VanishedFile {
+                        path: path.clone(),
+                        hash: fsutil::content_hash(&content),
+                        when: Instant::now(),
+                    });
+                }
+                Ok(true)
+            }
+            (
+                notify::EventKind::Create(CreateKind::File | CreateKind::Any)
+                | notify::EventKind::Modify(ModifyKind::Name(RenameMode::To)),
+                [path],
+            ) => {
+                if self.config.mappings.contains_key(path) || self.reverse_mappings.contains_key(path) {
+                    return Ok(false);
+                }
+                let Ok(content) = fs::read(path) else {
+                    return Ok(false);
+                };
+                let hash = fsutil::content_hash(&content);
+                
+                let from = match self.vanished_sources.iter().position(|vanished| vanished.hash == hash) {
+                    Some(index) => Some(self.vanished_sources.remove(index).path),
+                    // The removal may not have been reported at all
+                    None => self.last_known_content
+                        .iter()
+                        .find(|(source, known)| {
+                            self.config.mappings.contains_key(*source) && !source.exists() && fsutil::content_hash(known) == hash
+                        })
+                        .map(|(source, _)| source.clone()),
+                };
+                match from {
+                    Some(from) => self.follow_source_rename(&from, path),
+                    None => Ok(false),
+                }
+            }
+            _ => Ok(false),
+        }
+    }
+    
+    /// Move the mapping of `from` to `to`, renaming destinations named like the old source.
+    fn follow_source_rename(&mut self, from: &Path, to: &Path) -> Result<bool> {
+        let to = self.config.resolve_path(to).unwrap_or_else(|_| to.to_path_buf());
+        let to = self.config.stored_path(&to);
+        if self.config.mappings.contains_key(&to) {
+            return Ok(false);
+        }
+        let Some(mut mapping) = self.config.mappings.remove(from) else {
+            return Ok(false);
+        };
+        
+        if let (Some(old_name), Some(new_name)) = (from.file_name(), to.file_name()) {
+            for dest in &mut mapping.destinations {
+                if dest.file_name() != Some(old_name) {
+                    continue;
+                }
+                let renamed = dest.with_file_name(new_name);
+                if renamed.exists() {
+                    warn!("Not renaming {} since {} already exists", dest.display(), renamed.display());
+                    continue;
+                }
+                if self.dry_run {
+                    info!("Would rename destination {} to {}", dest.display(), renamed.display());
+                    continue;
+                }
+                if dest.exists() && fsutil::may_write("rename", dest) {
+                    fs::rename(&*dest, &renamed)
+                        .with_context(|| format!("Failed to rename {} to {}", dest.display(), renamed.display()))?;
+                }
+                info!("Renamed destination {} to {}", dest.display(), renamed.display());
+                if let Some(kind) = mapping.links.remove(&*dest) {
+                    // Symlinks still point to the old name of the source
+                    if let Err(e) = kind.restore(&to, &renamed) {
+                        warn!("Failed to relink {} to {}: {}", renamed.display(), to.display(), e);
+                    }
+                    mapping.links.insert(renamed.clone(), kind);
+                }
+                if mapping.manual.remove(&*dest) {
+                    mapping.manual.insert(renamed.clone());
+                }
+                self.recently_synced.insert(renamed.clone(), Instant::now());
+                *dest = renamed;
+            }
+            mapping.destinations.sort();
+        }
+        
+        for aggregate in self.config.aggregates.values_mut() {
+            for source in &mut aggregate.sources {
+                if source == from {
+                    *source = to.clone();
+                }
+            }
+        }
+        
+        let destinations = mapping.destinations.clone();
+        self.config.mappings.insert(to.clone(), mapping);
+        if !self.dry_run {
+            self.config.save()?;
+        }
+        
+        if let Some(content) = self.last_known_content.remove(from) {
+            self.last_known_content.insert(to.clone(), content);
+        }
+        self.update_reverse_mappings();
+        info!(
+            event_kind = "source-renamed",
+            source = %to.display(),
+            from = %from.display(),
+            "Source {} renamed to {}, updated its mapping",
+            from.display(),
+            to.display()
+        );
+        self.emit(WatchEvent::SourceRenamed {
+            from: from.to_path_buf(),
+            to,
+            destinations,
+        });
+        Ok(true)
+    }
+    
+    fn follow_move(&mut self, from: &Path, to: &Path) -> Result<bool> {
+        let Some(source) = self.reverse_mappings.get(from).cloned() else {
+            return Ok(false);
+        };
+        let to = self.config.resolve_path(to).unwrap_or_else(|_| to.to_path_buf());
+        
+        // Following it would leave two sources writing the same file
+        if let Some(owner) = self.reverse_mappings.get(&to).cloned()
+            && owner != source
+        {
+            self.warn_move_conflict(&source, from, &to, &owner)?;
+            return Ok(true);
+        }
+        
+        if self.dry_run {
+            info!("Destination {} moved to {}, would update its mapping", from.display(), to.display());
+            return Ok(true);
+        }
+        if !self.config.move_destination(from, &to)? {
+            return Ok(false);
+        }
+        info!(
+            event_kind = "destination-moved",
+            source = %source.display(),
+            destination = %to.display(),
+            from = %from.display(),
+            "Destination {} moved to {}, updated its mapping",
+            from.display(),
+            to.display()
+        );
+        self.update_reverse_mappings();
+        self.recently_synced.insert(to.clone(), Instant::now());
+        self.emit(WatchEvent::DestinationMoved {
+            source,
+            from: from.to_path_buf(),
+            to,
+        });
+        Ok(true)
+    }
+    
+    #[instrument(skip(self), fields(source = %source.display(), from = %from.display(), to = %to.display()))]
+    fn warn_move_conflict(&mut self, source: &Path, from: &Path, to: &Path, owner: &Path) -> Result<()> {
+        let message = format!(
+            "{} was moved onto {}, but they are destinations of different sources.\nMoved file's source: {}\nReplaced file's source: {}\nNeither mapping was changed; untrack one of them or move the file back",
+            from.display(),
+            to.display(),
+            source.display(),
+            owner.display()
+        );
+        
+        warn!(event_kind = "move-conflict", owner = %owner.display(), "{message}");
+        
+        self.emit(WatchEvent::MoveConflict {
+            source: source.to_path_buf(),
+            from: from.to_path_buf(),
+            to: to.to_path_buf(),
+            owner: owner.to_path_buf(),
+        });
+        
+        self.config.notifications
+            .build(NotificationKind::Desync, "mdman: Conflicting move!", &message)
+            .show()?;
+        Ok(())
+    }
+    
+    fn regenerate_aggregate(&mut self, destination: &Path) -> Result<()> {
+        let Some(aggregate) = self.config.aggregates.get(destination) else {
+            return Ok(());
+        };
+        
+        let content = aggregate.render()?;
+        if self.dry_run {
+            info!(
+                event_kind = "aggregate",
+                destination = %destination.display(),
+                bytes = content.len(),
+                outcome = "would-sync",
+                "Would regenerate {} from {} sources",
+                destination.display(),
+                aggregate.sources.len()
+            );
+            return Ok(());
+        }
+        backend::resolve(destination)
+            .and_then(|backend| {
+                backend::write_verified(&*backend, destination, &content, self.config.durable_writes, self.config.verify_after_write)
+            })
+            .with_context(|| format!("Failed to write {}", destination.display()))?;
+        info!(
+            event_kind = "aggregate",
+            destination = %destination.display(),
+            bytes = content.len(),
+            outcome = "synced",
+            "Regenerated {} from {} sources",
+            destination.display(),
+            aggregate.sources.len()
+        );
+        self.recently_synced.insert(destination.to_path_buf(), Instant::now());
+        
+        let source = aggregate.sources.first().map_or(Path::new(""), PathBuf::as_path);
+        if let Err(e) = sync_state::record_sync(source, destination, SystemTime::now(), &content) {
+            warn!("Failed to save sync state: {:#}", e);
+        }
+        Ok(())
+    }
+    
+    fn matches_source(&self, dest_path: &Path, source_path: &Path) -> bool {
+        let Some(mapping) = self.config.mappings.get(source_path) else {
+            return false;
+        };
+        match (fs::read(dest_path), fs::read(source_path)) {
+            (Ok(dest), Ok(source)) => {
+                markdown::contents_equal(&dest, &mapping.destination_content(&source), self.config.semantic_md_compare)
+            }
+            _ => false,
+        }
+    }
+    
     fn update_reverse_mappings(&mut self) {
         self.reverse_mappings.clear();
-        for (source, destinations) in self.config.mappings.iter() {
-            for dest in destinations {
-                self.reverse_mappings.insert(dest.clone(), source.clone());
+        for (source, mapping) in self.config.mappings.iter() {
+            for dest in mapping.targets() {
+                self.reverse_mappings.insert(dest, source.clone());
             }
         }
     }
     
     #[instrument(skip(self), fields(source = %source_path.display()))]
-    fn sync_file(&mut self, source_path: &Path) -> Result<()> {
-        let canonical_source = source_path.canonicalize()?;
+    fn sync_file(&mut self, source_path: &Path) -> Result<(Vec<PathBuf>, Vec<PathBuf>)> {
+        let canonical_source = self.config.resolve_path(source_path)?;
+        
+        let mut synced_files = Vec::new();
+        let mut desynced_files = Vec::new();
+        let mut failures = Vec::new();
         
-        if let Some(destinations) = self.config.mappings.get(&canonical_source) {
+        if let Some(mapping) = self.config.mappings.get(&canonical_source) {
+            let destinations = mapping.targets();
+            
             // Read old content before the change for comparison
             let old_source_content = self.last_known_content.get(&canonical_source)
                 .cloned()
                 .unwrap_or_else(Vec::new);
             
+            fsutil::ensure_regular_file(&canonical_source)?;
             let source_content = fs::read(&canonical_source)?;
             
-            // Store new content for next time
-            self.last_known_content.insert(canonical_source.clone(), source_content.clone());
+            // Store new content for next time. Destinations still have the old content after a
+            // dry run, so it stays what they are compared against.
+            if !self.dry_run {
+                self.last_known_content.insert(canonical_source.clone(), source_content.clone());
+            }
             
-            let mut synced_files = Vec::new();
-            let mut desynced_files = Vec::new();
+            let old_source_content = mapping.destination_content(&old_source_content);
+            let source_content = mapping.destination_content(&source_content);
             
-            for dest in destinations {
-                if dest.exists() {
-                    let dest_content = fs::read(dest).unwrap_or_default();
+            let mut to_write = Vec::new();
+            for dest in &destinations {
+                let link = mapping.link_kind(dest);
+                if link.is_some_and(|kind| kind.is_intact(&canonical_source, dest)) {
+                    continue;
+                }
+                if !mapping.auto_syncs(dest) {
+                    debug!("Auto-sync is off for {}, leaving it alone", dest.display());
+                    continue;
+                }
+                
+                if fsutil::same_file(&canonical_source, dest) {
+                    // Writing would modify the source itself
+                    warn!("{} is a hardlink of {}, skipping", dest.display(), canonical_source.display());
+                    continue;
+                }
+                
+                let backend = match backend::resolve(dest) {
+                    Ok(backend) => backend,
+                    Err(e) => {
+                        error!("Failed to sync to {}: {}", dest.display(), e);
+                        failures.push((dest.clone(), e.to_string()));
+                        continue;
+                    }
+                };
+                
+                if backend.exists(dest) {
+                    let dest_content = backend.read(dest).unwrap_or_default();
                     
                     // Check if destination was in sync with the OLD source content
-                    let was_in_sync = dest_content == old_source_content || old_source_content.is_empty();
+                    let was_in_sync = old_source_content.is_empty()
+                        || markdown::contents_equal(&dest_content, &old_source_content, self.config.semantic_md_compare);
                     
-                    if was_in_sync {
-                        // File was in sync, so update it
-                        match fs::write(dest, &source_content) {
-                            Ok(_) => {
-                                synced_files.push(dest.clone());
-                                // Mark this file as recently synced
-                                self.recently_synced.insert(dest.clone(), Instant::now());
-                            }
-                            Err(e) => {
-                                error!("Failed to sync to {}: {}", dest.display(), e);
-                            }
-                        }
-                    } else {
+                    if !was_in_sync {
                         // File was not in sync, leave it alone
+                        info!(
+                            event_kind = "sync",
+                            source = %canonical_source.display(),
+                            destination = %dest.display(),
+                            outcome = "desynced",
+                            "{} was edited directly, leaving it alone",
+                            dest.display()
+                        );
                         desynced_files.push(dest.clone());
+                        continue;
                     }
-                } else {
-                    // Create new file
-                    if let Some(parent) = dest.parent() {
-                        let _ = fs::create_dir_all(parent);
+                }
+                
+                // A link broken by an editor that was still in sync is made again
+                if let Some(kind) = link {
+                    if self.dry_run {
+                        info!(
+                            event_kind = "sync",
+                            source = %canonical_source.display(),
+                            destination = %dest.display(),
+                            outcome = "would-relink",
+                            "Would relink {} to {}",
+                            dest.display(),
+                            canonical_source.display()
+                        );
+                        synced_files.push(dest.clone());
+                        continue;
                     }
-                    match fs::write(dest, &source_content) {
+                    match kind.restore(&canonical_source, dest) {
                         Ok(_) => {
+                            info!(
+                                event_kind = "sync",
+                                source = %canonical_source.display(),
+                                destination = %dest.display(),
+                                outcome = "relinked",
+                                "Relinked {} to {}",
+                                dest.display(),
+                                canonical_source.display()
+                            );
                             synced_files.push(dest.clone());
-                            // Mark this file as recently synced
                             self.recently_synced.insert(dest.clone(), Instant::now());
                         }
                         Err(e) => {
-                            error!("Failed to create {}: {}", dest.display(), e);
+                            error!(
+                                event_kind = "sync",
+                                source = %canonical_source.display(),
+                                destination = %dest.display(),
+                                outcome = "failed",
+                                "Failed to relink {}: {}",
+                                dest.display(),
+                                e
+                            );
+                            failures.push((dest.clone(), e.to_string()));
+                        }
+                    }
+                    continue;
+                }
+                
+                if !self.dry_run && !backend.exists(dest) && !backend::is_remote(dest) {
+                    match self.config.on_missing_parent.prepare(dest) {
+                        Ok(true) => {}
+                        Ok(false) => {
+                            info!("Directory of {} is missing, queued for later", dest.display());
+                            self.queued.insert(dest.clone(), canonical_source.clone());
+                            continue;
+                        }
+                        Err(e) => {
+                            error!("Failed to sync to {}: {}", dest.display(), e);
+                            failures.push((dest.clone(), e.to_string()));
+                            continue;
+                        }
+                    }
+                }
+                
+                to_write.push(dest.clone());
+            }
+            
+            let write = |dest: &PathBuf| -> Option<std::io::Result<()>> {
+                if !hooks::run_hook(HookEvent::PreSync, &canonical_source, dest) {
+                    warn!("pre-sync hook vetoed writing {}", dest.display());
+                    return None;
+                }
+                let result = backend::resolve(dest)
+                    .and_then(|backend| {
+                        backend::write_verified(&*backend, dest, &source_content, self.config.durable_writes, self.config.verify_after_write)
+                    });
+                if result.is_ok() {
+                    hooks::run_hook(HookEvent::PostSync, &canonical_source, dest);
+                }
+                Some(result)
+            };
+            
+            let results: Vec<_> = if self.dry_run {
+                to_write.iter().map(|dest| (dest, Some(Ok(())))).collect()
+            } else if self.config.transactional {
+                // Remote backends can't take part in the local commit, they are written once it succeeded
+                let (remote, local): (Vec<&PathBuf>, Vec<&PathBuf>) = to_write.iter().partition(|dest| backend::is_remote(dest));
+                let local: Vec<PathBuf> = local
+                    .into_iter()
+                    .filter(|dest| {
+                        let approved = hooks::run_hook(HookEvent::PreSync, &canonical_source, dest);
+                        if !approved {
+                            warn!("pre-sync hook vetoed writing {}", dest.display());
                         }
+                        approved
+                    })
+                    .cloned()
+                    .collect();
+                match fsutil::write_all_or_nothing(&local, &source_content, self.config.durable_writes, self.config.verify_after_write) {
+                    Ok(()) => to_write
+                        .iter()
+                        .filter(|dest| local.contains(dest))
+                        .map(|dest| {
+                            hooks::run_hook(HookEvent::PostSync, &canonical_source, dest);
+                            (dest, Some(Ok(())))
+                        })
+                        .chain(remote.into_iter().map(|dest| (dest, write(dest))))
+                        .collect(),
+                    Err((failed, e)) => {
+                        error!("Nothing written for {}, {} failed: {}", canonical_source.display(), local[failed].display(), e);
+                        failures.push((local[failed].clone(), e.to_string()));
+                        Vec::new()
+                    }
+                }
+            } else if self.config.parallel_destinations {
+                to_write.par_iter().map(|dest| (dest, write(dest))).collect()
+            } else {
+                to_write.iter().map(|dest| (dest, write(dest))).collect()
+            };
+            
+            for (dest, result) in results {
+                match result {
+                    Some(Ok(())) => {
+                        let (outcome, verb) = if self.dry_run { ("would-sync", "Would sync") } else { ("synced", "Synced") };
+                        info!(
+                            event_kind = "sync",
+                            source = %canonical_source.display(),
+                            destination = %dest.display(),
+                            bytes = source_content.len(),
+                            outcome,
+                            "{} {} to {}",
+                            verb,
+                            canonical_source.display(),
+                            dest.display()
+                        );
+                        synced_files.push(dest.clone());
+                        // Mark this file as recently synced
+                        if !self.dry_run {
+                            self.recently_synced.insert(dest.clone(), Instant::now());
+                        }
+                    }
+                    Some(Err(e)) => {
+                        error!(
+                            event_kind = "sync",
+                            source = %canonical_source.display(),
+                            destination = %dest.display(),
+                            bytes = source_content.len(),
+                            outcome = "failed",
+                            "Failed to sync to {}: {}",
+                            dest.display(),
+                            e
+                        );
+                        failures.push((dest.clone(), e.to_string()));
                     }
+                    None => {}
                 }
             }
             
-            if !synced_files.is_empty() || !desynced_files.is_empty() {
-                self.send_sync_notification(&canonical_source, &synced_files, &desynced_files)?;
+            if !synced_files.is_empty() && !self.dry_run {
+                let mut state = sync_state::SyncState::load();
+                for dest in &synced_files {
+                    state.record(&canonical_source, dest, SystemTime::now(), &source_content);
+                }
+                if let Err(e) = state.save() {
+                    warn!("Failed to save sync state: {:#}", e);
+                }
             }
         }
         
-        Ok(())
+        for (dest, message) in failures {
+            self.record_failure(dest, &message);
+        }
+        // A dry run hasn't shown that a failing destination can be written again
+        if !self.dry_run {
+            for dest in &synced_files {
+                self.record_success(dest);
+            }
+        }
+        
+        Ok((synced_files, desynced_files))
+    }
+    
+    /// Notify about a failed write, unless the same destination was reported recently.
+    ///
+    /// Repeated failures are reported with exponentially growing gaps, so a destination that
+    /// keeps failing on every save doesn't flood the desktop with notifications.
+    fn record_failure(&mut self, dest: PathBuf, message: &str) {
+        let now = Instant::now();
+        let state = self.failing.entry(dest.clone()).or_insert_with(|| FailingDestination::new(now));
+        let Some(body) = state.fail(now, &dest, message) else {
+            debug!("Suppressed failure notification for {}", dest.display());
+            return;
+        };
+        
+        if let Err(e) = self.config.notifications
+            .build(NotificationKind::WriteFailed { recovered: false }, "mdman: Failed to write destination", &body)
+            .show()
+        {
+            error!("Failed to show notification: {e}");
+        }
+    }
+    
+    /// Notify once that a destination which was failing could be written again.
+    fn record_success(&mut self, dest: &Path) {
+        let Some(state) = self.failing.remove(dest) else {
+            return;
+        };
+        
+        info!("{} recovered after {} failed writes", dest.display(), state.failures);
+        let body = format!("{} is being written again after {} failures", dest.display(), state.failures);
+        if let Err(e) = self.config.notifications
+            .build(NotificationKind::WriteFailed { recovered: true }, "mdman: Destination recovered", &body)
+            .show()
+        {
+            error!("Failed to show notification: {e}");
+        }
     }
     
     
@@ -233,9 +1598,9 @@ impl FileWatcher {
         let desynced_count = desynced_files.len();
         
         let mut message = if synced_count == 1 {
-            format!("{} file has been synced", synced_count)
+            format!("{} file {} synced", synced_count, if self.dry_run { "would be" } else { "has been" })
         } else if synced_count > 1 {
-            format!("{} files have been synced", synced_count)
+            format!("{} files {} synced", synced_count, if self.dry_run { "would be" } else { "have been" })
         } else {
             String::new()
         };
@@ -252,12 +1617,20 @@ impl FileWatcher {
         }
         
         if !message.is_empty() {
-            Notification::new()
-                .summary(&format!("mdman: {}", source_name))
-                .body(&message)
-                .icon(if desynced_count > 0 { "dialog-warning" } else { "document-save" })
-                .timeout(3000)
-                .show()?;
+            if self.config.notifications.sync_mode != SyncNotificationMode::None {
+                let body = if desynced_count > 0 {
+                    format!("{}:\n{}", message, capped_list(desynced_files))
+                } else {
+                    message.clone()
+                };
+                self.config.notifications
+                    .build(
+                        NotificationKind::Sync { warning: desynced_count > 0 },
+                        &format!("mdman: {}", source_name),
+                        &body,
+                    )
+                    .show()?;
+            }
             
             info!("{}: {}", source_name, message);
             
@@ -273,8 +1646,43 @@ impl FileWatcher {
         Ok(())
     }
     
-    #[instrument(skip(self), fields(dest = %dest_path.display(), source = %source_path.display()))]
-    fn warn_desync(&self, dest_path: &Path, source_path: &Path) -> Result<()> {
+    fn send_batch_notification(&self, results: &[(PathBuf, Vec<PathBuf>, Vec<PathBuf>)]) -> Result<()> {
+        let source_count = results.len();
+        let synced_count: usize = results.iter().map(|(_, synced, _)| synced.len()).sum();
+        let desynced_count: usize = results.iter().map(|(_, _, desynced)| desynced.len()).sum();
+        
+        let mut message = format!(
+            "{} source{} changed, {} file{} {}synced",
+            source_count,
+            if source_count == 1 { "" } else { "s" },
+            synced_count,
+            if synced_count == 1 { "" } else { "s" },
+            if self.dry_run { "would be " } else { "" }
+        );
+        
+        if desynced_count > 0 {
+            message.push_str(&format!(", {} desynced left out", desynced_count));
+        }
+        
+        if self.config.notifications.sync_mode != SyncNotificationMode::None {
+            self.config.notifications
+                .build(NotificationKind::Sync { warning: desynced_count > 0 }, "mdman: batch sync", &message)
+                .show()?;
+        }
+        
+        info!("Batch: {}", message);
+        
+        for (source, _, desynced_files) in results {
+            for file in desynced_files {
+                warn!("Desynced from {}: {}", source.display(), file.display());
+            }
+        }
+        
+        Ok(())
+    }
+    
+    #[instrument(skip(self), fields(source = %source_path.display(), destination = %dest_path.display()))]
+    fn warn_desync(&mut self, dest_path: &Path, source_path: &Path) {
         let dest_name = dest_path.file_name()
             .and_then(|n| n.to_str())
             .unwrap_or("unknown");
@@ -285,56 +1693,217 @@ impl FileWatcher {
             source_path.display()
         );
         
-        Notification::new()
-            .summary("mdman: Desync detected!")
-            .body(&message)
-            .icon("dialog-warning")
-            .urgency(notify_rust::Urgency::Critical)
-            .timeout(0)
-            .show()?;
+        warn!(event_kind = "desynced", "{message}");
+        // Headless machines have no notification daemon, the hooks below must run regardless
+        if let Err(e) = self.config.notifications
+            .build(NotificationKind::Desync, "mdman: Desync detected!", &message)
+            .show()
+        {
+            warn!("Failed to show desync notification: {e:#}");
+        }
         
-        warn!("{message}");
+        self.emit(WatchEvent::Desynced {
+            source: source_path.to_path_buf(),
+            destination: dest_path.to_path_buf(),
+        });
         
-        Ok(())
+        hooks::run_hook(HookEvent::PostDesync, source_path, dest_path);
+        
+        let command = self.config.mappings
+            .get(source_path)
+            .and_then(|mapping| mapping.on_desync.as_ref())
+            .or(self.on_desync.as_ref())
+            .or(self.config.on_desync.as_ref());
+        if let Some(command) = command
+            && let Err(e) = hooks::spawn_command_hook(command, source_path, dest_path)
+        {
+            error!("Failed to run desync hook: {e}");
+        }
     }
     
-    #[instrument(skip(self, destinations), fields(source = %source_path.display(), dest_count = destinations.len()))]
-    fn warn_source_deleted(&self, source_path: &Path, destinations: &[PathBuf]) -> Result<()> {
+    #[instrument(skip(self, destinations), fields(source = %source_path.display(), destination_count = destinations.len()))]
+    fn warn_source_deleted(&self, source_path: &Path, destinations: &[PathBuf], kept: bool) {
         let source_name = source_path.file_name()
             .and_then(|n| n.to_str())
             .unwrap_or("unknown");
         
         let dest_count = destinations.len();
-        let message = if dest_count == 1 {
+        // The log gets every destination, the notification only as many as stay readable
+        let message = |dest_list: String| if dest_count == 1 {
             format!(
-                "Source file {} was deleted!\nDestination file remains at:\n{}",
+                "Source file {} was deleted!\nDestination file remains at:\n{}\n{}",
                 source_name,
-                destinations[0].display()
+                destinations[0].display(),
+                source_deleted_note(kept)
             )
         } else {
-            let dest_list: Vec<String> = destinations.iter()
-                .map(|d| format!("  - {}", d.display()))
-                .collect();
             format!(
-                "Source file {} was deleted!\n{} destination files remain at:\n{}",
+                "Source file {} was deleted!\n{} destination files remain at:\n{}\n{}",
                 source_name,
                 dest_count,
-                dest_list.join("\n")
+                dest_list,
+                source_deleted_note(kept)
             )
         };
         
-        Notification::new()
-            .summary("mdman: Source file deleted!")
-            .body(&message)
-            .icon("dialog-warning")
-            .urgency(notify_rust::Urgency::Critical)
-            .timeout(0)
-            .show()?;
+        let dest_list: Vec<String> = destinations.iter()
+            .map(|d| format!("  - {}", d.display()))
+            .collect();
+        warn!(event_kind = "source-deleted", "{}", message(dest_list.join("\n")));
+        
+        if let Err(e) = self.config.notifications
+            .build(NotificationKind::SourceDeleted, "mdman: Source file deleted!", &message(capped_list(destinations)))
+            .show()
+        {
+            warn!("Failed to show source deletion notification: {e:#}");
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn event() -> EventResult {
+        Ok(Event::new(notify::EventKind::Any))
+    }
+    
+    #[test]
+    fn burst_is_collected_into_one_batch() {
+        let (tx, rx) = mpsc::sync_channel(100);
+        for _ in 0..20 {
+            tx.send(event()).unwrap();
+        }
         
-        warn!("{}", message);
-        warn!("Note: Destination files were not deleted and are no longer being watched.");
-        warn!("The tracking for {} has been automatically removed.", source_path.display());
+        let batch = FileWatcher::collect_batch(event(), &rx, Duration::from_millis(50), MAX_BATCH_AGE);
         
-        Ok(())
+        assert_eq!(batch.len(), 21);
+    }
+    
+    #[test]
+    fn steady_stream_is_flushed_after_the_max_age() {
+        let (tx, rx) = mpsc::sync_channel(100);
+        let sender = thread::spawn(move || {
+            // Stops once the receiver is dropped
+            while tx.send(event()).is_ok() {
+                thread::sleep(Duration::from_millis(10));
+            }
+        });
+        
+        let started = Instant::now();
+        let batch = FileWatcher::collect_batch(event(), &rx, Duration::from_millis(50), Duration::from_millis(300));
+        
+        assert!(started.elapsed() < Duration::from_secs(2));
+        assert!(batch.len() > 1);
+        drop(rx);
+        sender.join().unwrap();
+    }
+    
+    #[test]
+    fn metadata_only_events_are_ignored_by_default() {
+        use notify::event::{CreateKind, DataChange, MetadataKind};
+        
+        let metadata = notify::EventKind::Modify(ModifyKind::Metadata(MetadataKind::Permissions));
+        let data = notify::EventKind::Modify(ModifyKind::Data(DataChange::Content));
+        
+        assert!(!is_relevant(&metadata, false));
+        assert!(is_relevant(&metadata, true));
+        assert!(is_relevant(&data, false));
+        assert!(is_relevant(&notify::EventKind::Create(CreateKind::File), false));
+        assert!(!is_relevant(&notify::EventKind::Access(notify::event::AccessKind::Any), true));
+    }
+    
+    #[test]
+    fn repeated_failures_are_notified_with_growing_gaps() {
+        let start = Instant::now();
+        let mut state = FailingDestination::new(start);
+        
+        // A failure every 10 seconds for 10 minutes
+        let notified: Vec<u64> = (0..60)
+            .filter_map(|i| {
+                let now = start + Duration::from_secs(i * 10);
+                state.fail(now, Path::new("/copy.md"), "disk full").map(|_| i * 10)
+            })
+            .collect();
+        
+        assert_eq!(notified, [0, 60, 180, 420]);
+        assert_eq!(state.failures, 60);
+    }
+    
+    #[test]
+    fn suppressed_failures_are_counted_in_the_next_notification() {
+        let start = Instant::now();
+        let mut state = FailingDestination::new(start);
+        
+        assert_eq!(state.fail(start, Path::new("/copy.md"), "disk full").unwrap(), "/copy.md\ndisk full");
+        assert_eq!(state.fail(start + Duration::from_secs(1), Path::new("/copy.md"), "disk full"), None);
+        let body = state.fail(start + INITIAL_FAILURE_BACKOFF, Path::new("/copy.md"), "disk full").unwrap();
+        
+        assert!(body.ends_with("(failed 2 times since the last notice)"), "{body}");
+    }
+    
+    fn bounded(capacity: usize, policy: QueueFullPolicy) -> (EventSender, mpsc::Receiver<EventResult>, Arc<AtomicUsize>) {
+        let (tx, rx) = mpsc::sync_channel(capacity);
+        let dropped = Arc::new(AtomicUsize::new(0));
+        (EventSender::Bounded { tx, policy, dropped: Arc::clone(&dropped) }, rx, dropped)
+    }
+    
+    #[test]
+    fn full_queue_drops_events_under_the_drop_policy() {
+        let (mut sender, rx, dropped) = bounded(2, QueueFullPolicy::Drop);
+        
+        for _ in 0..10 {
+            sender.handle_event(event());
+        }
+        
+        assert_eq!(rx.try_iter().count(), 2);
+        assert_eq!(dropped.load(Ordering::Relaxed), 8);
     }
-}
\ No newline at end of file
+    
+    #[test]
+    fn full_queue_holds_up_the_sender_under_the_block_policy() {
+        let (mut sender, rx, dropped) = bounded(2, QueueFullPolicy::Block);
+        let burst = thread::spawn(move || {
+            for _ in 0..10 {
+                sender.handle_event(event());
+            }
+        });
+        
+        thread::sleep(Duration::from_millis(100));
+        assert!(!burst.is_finished());
+        let received = rx.iter().take(10).count();
+        burst.join().unwrap();
+        
+        assert_eq!(received, 10);
+        assert_eq!(dropped.load(Ordering::Relaxed), 0);
+    }
+    
+    #[test]
+    fn long_path_lists_are_capped_in_notifications() {
+        let paths: Vec<PathBuf> = (1..=20).map(|i| PathBuf::from(format!("/copy/{i}.md"))).collect();
+        
+        let list = capped_list(&paths);
+        
+        let lines: Vec<&str> = list.lines().collect();
+        assert_eq!(lines.len(), MAX_NOTIFIED_PATHS + 1);
+        assert_eq!(lines[0], "  - /copy/1.md");
+        assert_eq!(lines[4], "  - /copy/5.md");
+        assert_eq!(lines[5], "  +15 more");
+    }
+    
+    #[test]
+    fn short_path_lists_are_kept_whole() {
+        let paths: Vec<PathBuf> = (1..=MAX_NOTIFIED_PATHS).map(|i| PathBuf::from(format!("/copy/{i}.md"))).collect();
+        
+        let list = capped_list(&paths);
+        
+        assert_eq!(list.lines().count(), MAX_NOTIFIED_PATHS);
+        assert!(!list.contains("more"));
+    }
+    
+    #[test]
+    fn deleted_source_note_matches_what_happened() {
+        assert!(source_deleted_note(true).contains("kept since safe mode is enabled"));
+        assert!(source_deleted_note(false).contains("removed"));
+        assert!(!source_deleted_note(true).contains("removed"));
+    }
+}
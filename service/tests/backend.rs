@@ -0,0 +1,57 @@
+use mdman_service::{backend, Config, DestinationBackend, SyncOptions};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Destinations kept in memory instead of on disk.
+#[derive(Default)]
+struct MemoryBackend {
+    files: Mutex<HashMap<PathBuf, Vec<u8>>>,
+}
+
+impl DestinationBackend for MemoryBackend {
+    fn write(&self, path: &Path, content: &[u8], _durable: bool) -> io::Result<()> {
+        self.files.lock().unwrap().insert(path.to_path_buf(), content.to_vec());
+        Ok(())
+    }
+    
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        self.files.lock().unwrap().get(path).cloned().ok_or_else(|| io::ErrorKind::NotFound.into())
+    }
+    
+    fn exists(&self, path: &Path) -> bool {
+        self.files.lock().unwrap().contains_key(path)
+    }
+}
+
+/// Destinations with a registered scheme are written and compared through its backend.
+#[test]
+fn sync_and_diff_go_through_registered_backends() {
+    let root = std::env::temp_dir().join(format!("mdman-backend-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    fs::create_dir_all(root.join("config")).unwrap();
+    let root = root.canonicalize().unwrap();
+    let source = root.join("notes.md");
+    fs::write(&source, "one\n").unwrap();
+    let destination = PathBuf::from("mem://notes.md");
+    
+    // This test binary has no other tests, so nothing else depends on the config directory
+    Config::set_config_dir(root.join("config"));
+    let config = serde_json::json!({
+        "version": 1,
+        "mappings": { source.to_str().unwrap(): { "destinations": [destination] } },
+    });
+    fs::write(Config::config_file_path().unwrap(), config.to_string()).unwrap();
+    let memory = Arc::new(MemoryBackend::default());
+    backend::register("mem", memory.clone());
+    
+    assert_eq!(mdman_service::check_diff(None, None).unwrap().len(), 1);
+    let stats = mdman_service::sync_all_files(&SyncOptions { quiet: true, ..Default::default() }).unwrap();
+    
+    assert_eq!(stats.synced_count, 1);
+    assert_eq!(memory.read(&destination).unwrap(), b"one\n");
+    assert!(mdman_service::check_diff(None, None).unwrap().is_empty());
+    let _ = fs::remove_dir_all(&root);
+}
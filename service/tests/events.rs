@@ -0,0 +1,56 @@
+use mdman_service::{Config, FileWatcher, WatchEvent};
+use std::fs;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+/// Library users get every sync as a [`WatchEvent`] while the watcher runs.
+#[test]
+fn watcher_reports_syncs_to_subscribers() {
+    let root = std::env::temp_dir().join(format!("mdman-events-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    fs::create_dir_all(root.join("config")).unwrap();
+    fs::create_dir_all(root.join("copy")).unwrap();
+    let root = root.canonicalize().unwrap();
+    let source = root.join("notes.md");
+    let destination = root.join("copy/notes.md");
+    fs::write(&source, "one\n").unwrap();
+    fs::write(&destination, "one\n").unwrap();
+    
+    // This test binary has no other tests, so nothing else depends on the config directory
+    Config::set_config_dir(root.join("config"));
+    let config = serde_json::json!({
+        "version": 1,
+        "mappings": { source.to_str().unwrap(): { "destinations": [destination] } },
+        "notifications": { "sync_mode": "none" },
+    });
+    fs::write(Config::config_file_path().unwrap(), config.to_string()).unwrap();
+    
+    let (tx, rx) = mpsc::channel();
+    let editor = {
+        let source = source.clone();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(1500));
+            fs::write(source, "two\n").unwrap();
+        })
+    };
+    let summary = FileWatcher::new()
+        .unwrap()
+        .with_exit_after(Duration::from_secs(4))
+        .run_with_events(tx)
+        .unwrap();
+    editor.join().unwrap();
+    
+    // Writing the source may show up as a truncation and a write, each synced on its own
+    let events: Vec<WatchEvent> = rx.try_iter().collect();
+    assert_eq!(summary.events, events.len());
+    assert!(!events.is_empty());
+    for event in &events {
+        assert!(
+            matches!(event, WatchEvent::Synced { source: s, destinations } if *s == source && *destinations == [destination.clone()]),
+            "{event:?}"
+        );
+    }
+    assert_eq!(fs::read_to_string(&destination).unwrap(), "two\n");
+    let _ = fs::remove_dir_all(&root);
+}
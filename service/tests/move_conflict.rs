@@ -0,0 +1,64 @@
+use mdman_service::{Config, FileWatcher, WatchEvent};
+use std::fs;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+/// A destination moved onto a destination of another source is reported, not synced.
+#[test]
+fn move_onto_another_sources_destination_is_a_conflict() {
+    let root = std::env::temp_dir().join(format!("mdman-move-conflict-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    fs::create_dir_all(root.join("config")).unwrap();
+    fs::create_dir_all(root.join("copy")).unwrap();
+    let root = root.canonicalize().unwrap();
+    let (a, b) = (root.join("a.md"), root.join("b.md"));
+    let (copy_a, copy_b) = (root.join("copy/a.md"), root.join("copy/b.md"));
+    for (path, content) in [(&a, "a\n"), (&b, "b\n"), (&copy_a, "a\n"), (&copy_b, "b\n")] {
+        fs::write(path, content).unwrap();
+    }
+    
+    // This test binary has no other tests, so nothing else depends on the config directory
+    Config::set_config_dir(root.join("config"));
+    let config = serde_json::json!({
+        "version": 1,
+        "detect_renames": true,
+        "mappings": {
+            a.to_str().unwrap(): { "destinations": [copy_a] },
+            b.to_str().unwrap(): { "destinations": [copy_b] },
+        },
+        "notifications": { "sync_mode": "none" },
+    });
+    fs::write(Config::config_file_path().unwrap(), config.to_string()).unwrap();
+    
+    let (tx, rx) = mpsc::channel();
+    let mover = {
+        let (copy_a, copy_b) = (copy_a.clone(), copy_b.clone());
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(1500));
+            fs::rename(copy_a, copy_b).unwrap();
+        })
+    };
+    FileWatcher::new()
+        .unwrap()
+        .with_exit_after(Duration::from_secs(4))
+        .run_with_events(tx)
+        .unwrap();
+    mover.join().unwrap();
+    
+    let events: Vec<WatchEvent> = rx.try_iter().collect();
+    assert!(
+        events.iter().any(|event| matches!(
+            event,
+            WatchEvent::MoveConflict { source, from, to, owner } if *source == a && *from == copy_a && *to == copy_b && *owner == b
+        )),
+        "{events:?}"
+    );
+    assert!(!events.iter().any(|event| matches!(event, WatchEvent::Synced { .. })), "{events:?}");
+    // Neither mapping was changed, and nothing was written over the moved file
+    let config = Config::load().unwrap();
+    assert_eq!(config.mappings[&a].destinations, [copy_a]);
+    assert_eq!(fs::read_to_string(&copy_b).unwrap(), "a\n");
+    assert_eq!(config.mappings[&b].destinations, [copy_b]);
+    let _ = fs::remove_dir_all(&root);
+}
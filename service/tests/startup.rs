@@ -0,0 +1,43 @@
+mod common;
+
+use common::Log;
+use mdman_service::{Config, FileWatcher};
+use std::fs;
+use std::time::Duration;
+
+/// What the watcher logs when run briefly, with `--quiet-startup` if `quiet` is set.
+fn watch_log(quiet: bool) -> String {
+    Log::capture(|| {
+        let watcher = FileWatcher::new().unwrap().with_exit_after(Duration::from_millis(500));
+        let mut watcher = if quiet { watcher.with_quiet_startup() } else { watcher };
+        watcher.run().unwrap();
+    })
+}
+
+/// The watcher logs how many of its files drifted while it wasn't running, unless told to be quiet.
+#[test]
+fn startup_summary_counts_drifted_destinations_unless_quiet() {
+    let root = std::env::temp_dir().join(format!("mdman-startup-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    fs::create_dir_all(root.join("config")).unwrap();
+    fs::create_dir_all(root.join("copy")).unwrap();
+    let root = root.canonicalize().unwrap();
+    let source = root.join("notes.md");
+    let destinations = [root.join("copy/a.md"), root.join("copy/b.md")];
+    fs::write(&source, "one\n").unwrap();
+    fs::write(&destinations[0], "one\n").unwrap();
+    fs::write(&destinations[1], "edited\n").unwrap();
+    
+    // This test binary has no other tests, so nothing else depends on the config directory
+    Config::set_config_dir(root.join("config"));
+    let config = serde_json::json!({
+        "version": 1,
+        "mappings": { source.to_str().unwrap(): { "destinations": destinations } },
+    });
+    fs::write(Config::config_file_path().unwrap(), config.to_string()).unwrap();
+    
+    let log = watch_log(false);
+    assert!(log.contains("3 watched, 1 currently drifted"), "{log}");
+    assert!(!watch_log(true).contains("currently drifted"));
+    let _ = fs::remove_dir_all(&root);
+}
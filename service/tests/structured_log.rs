@@ -0,0 +1,53 @@
+mod common;
+
+use common::Log;
+use mdman_service::{Config, FileWatcher};
+use std::fs;
+use std::thread;
+use std::time::Duration;
+
+/// Syncs are logged with fields that log aggregation can query, not just a message.
+#[test]
+fn sync_events_carry_structured_fields() {
+    let root = std::env::temp_dir().join(format!("mdman-structured-log-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    fs::create_dir_all(root.join("config")).unwrap();
+    fs::create_dir_all(root.join("copy")).unwrap();
+    let root = root.canonicalize().unwrap();
+    let source = root.join("notes.md");
+    let destination = root.join("copy/notes.md");
+    fs::write(&source, "one\n").unwrap();
+    fs::write(&destination, "one\n").unwrap();
+    
+    // This test binary has no other tests, so nothing else depends on the config directory
+    Config::set_config_dir(root.join("config"));
+    let config = serde_json::json!({
+        "version": 1,
+        "mappings": { source.to_str().unwrap(): { "destinations": [destination] } },
+        "notifications": { "sync_mode": "none" },
+    });
+    fs::write(Config::config_file_path().unwrap(), config.to_string()).unwrap();
+    
+    let editor = {
+        let source = source.clone();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(1500));
+            fs::write(source, "two\n").unwrap();
+        })
+    };
+    let log = Log::capture(|| {
+        FileWatcher::new().unwrap().with_exit_after(Duration::from_secs(3)).run().unwrap();
+    });
+    editor.join().unwrap();
+    
+    // Writing the source may show up as a truncation and a write, the last sync has the new content
+    let synced = log
+        .lines()
+        .rfind(|line| line.contains(r#"outcome="synced""#))
+        .unwrap_or_else(|| panic!("no sync was logged:\n{log}"));
+    assert!(synced.contains(r#"event_kind="sync""#), "{synced}");
+    assert!(synced.contains(&format!("source={}", source.display())), "{synced}");
+    assert!(synced.contains(&format!("destination={}", destination.display())), "{synced}");
+    assert!(synced.contains("bytes=4"), "{synced}");
+    let _ = fs::remove_dir_all(&root);
+}
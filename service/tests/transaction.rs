@@ -0,0 +1,42 @@
+use mdman_service::Config;
+use std::fs;
+
+/// Changes made in a transaction are saved once at the end, and not at all if it fails.
+#[test]
+fn transaction_saves_once_and_rolls_back_on_error() {
+    let root = std::env::temp_dir().join(format!("mdman-transaction-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    fs::create_dir_all(root.join("config")).unwrap();
+    let root = root.canonicalize().unwrap();
+    for name in ["a.md", "b.md", "copy-a.md", "copy-b.md"] {
+        fs::write(root.join(name), "content\n").unwrap();
+    }
+    
+    // This test binary has no other tests, so nothing else depends on the config directory
+    Config::set_config_dir(root.join("config"));
+    let config_file = Config::config_file_path().unwrap();
+    let mut config = Config::default();
+    
+    config
+        .transaction(|config| {
+            config.add_mapping(root.join("a.md"), root.join("copy-a.md"))?;
+            assert!(!config_file.exists(), "saved before the transaction ended");
+            config.add_mapping(root.join("b.md"), root.join("copy-b.md"))?;
+            assert!(!config_file.exists(), "saved before the transaction ended");
+            Ok(())
+        })
+        .unwrap();
+    
+    assert_eq!(Config::load().unwrap().mappings.len(), 2);
+    let saved = fs::read(&config_file).unwrap();
+    
+    let result = config.transaction(|config| {
+        config.remove_source(&root.join("a.md"))?;
+        config.add_mapping(root.join("missing.md"), root.join("copy-a.md"))
+    });
+    
+    assert!(result.is_err());
+    assert_eq!(config.mappings.len(), 2);
+    assert_eq!(fs::read(&config_file).unwrap(), saved);
+    let _ = fs::remove_dir_all(&root);
+}